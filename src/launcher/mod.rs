@@ -1,53 +1,435 @@
 #![allow(dead_code)]
 
+use std::io;
+use std::fs;
+use std::str;
 use std::path;
 use std::result::Result;
-use std::collections::HashMap;
-use std::process::{Child, Command};
+use std::env;
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::ops;
+use std::ffi::OsString;
+use std::process::{self, Child, Command, ExitStatus, Output, Stdio};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json;
+use uuid::Uuid;
+#[cfg(feature = "progress-stream")]
+use futures::{Async, Poll, Stream};
+#[cfg(feature = "progress-stream")]
+use futures::sync::mpsc;
 
 use parsing;
 use versions;
 use yggdrasil;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptionSource {
+    Default,
+    User,
+    Version,
+    Loader,
+}
+
+type EnvLookup = dyn Fn(&str) -> Option<String>;
+
 #[derive(Debug)]
-pub struct JvmOption(String);
+pub struct JvmOption(String, OptionSource);
 
 #[derive(Debug)]
-pub struct GameOption(String, Option<String>);
+pub struct GameOption(String, Option<String>, OptionSource);
 
 #[derive(Default)]
 pub struct MinecraftLauncherBuilder {
     program_path: Option<String>,
+    jre_candidates: Vec<String>,
+    jre_mapping: HashMap<u32, String>,
+    jre_preference: Option<JrePreference>,
     game_root_dir: Option<path::PathBuf>,
+    profile_game_dir: Option<path::PathBuf>,
     assets_dir: Option<path::PathBuf>,
+    assets_objects_dir: Option<path::PathBuf>,
+    excluded_asset_prefixes: Vec<String>,
+    asset_index_override: Option<String>,
     libraries_dir: Option<path::PathBuf>,
+    libraries_overlay_dir: Option<path::PathBuf>,
     launcher_name_version: Option<(String, String)>,
     auth_info: Option<yggdrasil::AuthInfo>,
+    auth_sources: Vec<(String, yggdrasil::AuthInfo)>,
+    access_token_provider: Option<SecretProvider>,
+    session_format: Option<SessionFormat>,
+    auth_uuid_format: Option<UuidFormat>,
+    session_uuid_format: Option<UuidFormat>,
+    client_id: Option<String>,
     min_memory_mib: Option<f32>,
     max_memory_mib: Option<f32>,
+    max_memory_percent: Option<f32>,
+    total_memory_mib: Option<f32>,
+    stack_size_kib: Option<u32>,
     window_resolution: Option<(u32, u32)>,
+    primary_jar_override: Option<path::PathBuf>,
+    fallback_main_class: Option<String>,
+    entrypoint_wrapper: Option<(path::PathBuf, String)>,
+    natives_classifier: Option<String>,
+    strip_native_versions: bool,
+    library_classifiers: HashMap<String, String>,
+    relative_classpath: bool,
+    command_line_limit: Option<usize>,
+    classpath_wildcard_dirs: Vec<path::PathBuf>,
+    classpath_separator: Option<String>,
+    classpath_jar: Option<path::PathBuf>,
+    argfile: Option<Option<path::PathBuf>>,
+    keep_argfile: bool,
+    disable_chat: bool,
+    map_to_resources: bool,
+    proxy: Option<(String, u16)>,
+    proxy_credentials: Option<(String, String)>,
+    telemetry_host: Option<String>,
+    auth_server: Option<String>,
+    auth_server_metadata: Option<String>,
+    authlib_injector: Option<path::PathBuf>,
+    debug_port: Option<u16>,
+    debug_suspend: bool,
+    exit_on_out_of_memory: bool,
+    probe_gc_flags: bool,
+    env_lookup: Option<Box<EnvLookup>>,
+    instance_config: Option<InstanceConfig>,
+    crash_reports_dir: Option<path::PathBuf>,
+    create_game_dirs: Option<bool>,
+    verify_natives: bool,
+    hasher: Option<Arc<dyn versions::FileHasher>>,
+    file_encoding: Option<Option<String>>,
+    sandbox_command: Vec<String>,
+    version_kind_jvm_options: HashMap<versions::VersionKind, Vec<String>>,
+    extra_jvm_args: Vec<String>,
+    main_class_adjacent_jvm_args: Vec<String>,
 }
 
 pub struct MinecraftLauncher {
-    program_path: String,
+    program_path: Option<String>,
+    jre_candidates: Vec<String>,
+    jre_mapping: HashMap<u32, String>,
+    jre_preference: JrePreference,
     game_root_dir: path::PathBuf,
+    game_dir: path::PathBuf,
     assets_dir: path::PathBuf,
+    assets_objects_dir: path::PathBuf,
+    excluded_asset_prefixes: Vec<String>,
+    asset_index_override: Option<String>,
     libraries_dir: path::PathBuf,
+    libraries_overlay_dir: Option<path::PathBuf>,
     manager: versions::VersionManager,
     launcher_name_version: (String, String),
-    auth_info: yggdrasil::AuthInfo,
+    auth_sources: Vec<(String, yggdrasil::AuthInfo)>,
+    active_auth_source: usize,
+    access_token_provider: Option<SecretProvider>,
+    session_format: SessionFormat,
+    auth_uuid_format: UuidFormat,
+    session_uuid_format: UuidFormat,
+    client_id: Option<String>,
     min_max_memory_mib: (f32, f32),
+    max_memory_percent: Option<f32>,
+    total_memory_mib: Option<f32>,
+    stack_size_kib: Option<u32>,
     window_resolution: (u32, u32),
+    primary_jar_override: Option<path::PathBuf>,
+    fallback_main_class: Option<String>,
+    entrypoint_wrapper: Option<(path::PathBuf, String)>,
+    natives_classifier: Option<String>,
+    strip_native_versions: bool,
+    library_classifiers: HashMap<String, String>,
+    relative_classpath: bool,
+    command_line_limit: Option<usize>,
+    classpath_wildcard_dirs: Vec<path::PathBuf>,
+    classpath_separator: String,
+    classpath_jar: Option<path::PathBuf>,
+    argfile: Option<Option<path::PathBuf>>,
+    keep_argfile: bool,
+    disable_chat: bool,
+    map_to_resources: bool,
+    proxy: Option<(String, u16)>,
+    proxy_credentials: Option<(String, String)>,
+    telemetry_host: Option<String>,
+    auth_server: Option<String>,
+    auth_server_metadata: Option<String>,
+    authlib_injector: Option<path::PathBuf>,
+    debug_port: Option<u16>,
+    debug_suspend: bool,
+    exit_on_out_of_memory: bool,
+    probe_gc_flags: bool,
+    gc_flag_support: Mutex<HashMap<(String, String), bool>>,
+    crash_reports_dir: Option<path::PathBuf>,
+    create_game_dirs: bool,
+    verify_natives: bool,
+    hasher: Arc<dyn versions::FileHasher>,
+    file_encoding: Option<String>,
+    sandbox_command: Vec<String>,
+    sandbox_available: Mutex<Option<bool>>,
+    version_kind_jvm_options: HashMap<versions::VersionKind, Vec<String>>,
+    extra_jvm_args: Vec<String>,
+    main_class_adjacent_jvm_args: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct LaunchArguments {
     java_main_class: String,
     java_program_path: String,
+    game_directory: path::PathBuf,
     jvm_options: Vec<JvmOption>,
     game_options: Vec<GameOption>,
     game_native_path: path::PathBuf,
     game_natives: versions::NativeCollection,
+    sandbox_command: Vec<String>,
+    crash_reports_dir: Option<path::PathBuf>,
+    create_game_dirs: bool,
+    verify_natives: bool,
+    argfile: Option<path::PathBuf>,
+    keep_argfile: bool,
+    classpath_jar: Option<(path::PathBuf, Vec<path::PathBuf>)>,
+    access_token_provider: Option<SecretProvider>,
+    secrets: Vec<String>,
+    consumed_placeholders: Vec<String>,
+}
+
+struct RotatingLog {
+    path: path::PathBuf,
+    max_bytes: u64,
+    kept: u32,
+    file: fs::File,
+    written: u64,
+}
+
+#[derive(Clone)]
+pub struct SecretProvider(Arc<dyn Fn() -> String + Send + Sync>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadinessCategory {
+    Jar,
+    Libraries,
+    Assets,
+    Natives,
+}
+
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    total: usize,
+    missing: Vec<(ReadinessCategory, path::PathBuf)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstallReportEntry {
+    path: path::PathBuf,
+    size: u64,
+    sha1: String,
+    verified: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedDownload {
+    url: String,
+    path: path::PathBuf,
+    size: Option<u64>,
+    sha1: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstallReport {
+    entries: Vec<InstallReportEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InstallProgress {
+    files_done: usize,
+    files_total: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+#[cfg(feature = "progress-stream")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    Progress(InstallProgress),
+    Downloaded(path::PathBuf),
+}
+
+#[cfg(feature = "progress-stream")]
+pub struct ProgressStream(mpsc::UnboundedReceiver<Result<ProgressEvent, versions::Error>>);
+
+#[derive(Default)]
+pub struct LaunchQueue {
+    entries: Vec<(String, LaunchArguments)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    max_restarts: usize,
+    backoff: Duration,
+    max_backoff: Duration,
+    min_uptime: Duration,
+    max_immediate_crashes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryRecommendation {
+    memory_mib: f32,
+    fallback_reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct GameProcess {
+    child: Option<Child>,
+    argfile: Option<path::PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct SupervisedExit {
+    status: ExitStatus,
+    restarts: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModReport {
+    mod_files: Vec<String>,
+    loaded_mod_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstanceConfig {
+    java: Option<String>,
+    min_memory_mib: Option<f32>,
+    max_memory_mib: Option<f32>,
+    max_memory_percent: Option<f32>,
+    jvm_args: Vec<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawInstanceConfig {
+    java: Option<String>,
+    min_memory: Option<f32>,
+    max_memory: Option<MemoryValue>,
+    jvm_args: Vec<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MemoryValue {
+    MiB(f32),
+    Percent(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LaunchSnapshot {
+    os: String,
+    arch: String,
+    java_program_path: String,
+    java_version: Option<String>,
+    working_directory: path::PathBuf,
+    environment: BTreeMap<String, String>,
+    args: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LaunchPlan {
+    java_program_path: String,
+    java_version: Option<String>,
+    classpath: Vec<String>,
+    classpath_jar: Option<path::PathBuf>,
+    resources: Vec<(path::PathBuf, path::PathBuf)>,
+    sandbox_command: Vec<String>,
+    jvm_args: Vec<String>,
+    main_class: String,
+    game_args: Vec<String>,
+    natives_directory: path::PathBuf,
+    natives: Vec<(path::PathBuf, Vec<String>)>,
+    strip_native_versions: bool,
+    environment: BTreeMap<String, String>,
+    working_directory: path::PathBuf,
+}
+
+pub const LAUNCHER_NAME: &str = "RMCLL";
+
+pub const LAUNCHER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const ACCESS_TOKEN_PLACEHOLDER: &str = "${auth_access_token}";
+
+const FALLBACK_MAX_MEMORY_MIB: f32 = 2048f32;
+
+const MIN_STACK_SIZE_KIB: u32 = 160;
+
+const MAX_STACK_SIZE_KIB: u32 = 1024 * 1024;
+
+const LOG_ROTATION_BYTES: u64 = 16 * 1024 * 1024;
+
+const LOG_ROTATION_KEPT: u32 = 3;
+
+const MODERN_GAME_OPTIONS: &[(&str, &str)] = &[
+    ("--userType", "user_type"),
+    ("--versionType", "version_type"),
+    ("--uuid", "auth_uuid"),
+    ("--accessToken", "auth_access_token"),
+    ("--userProperties", "user_properties"),
+    ("--assetIndex", "assets_index_name"),
+];
+
+const PROBLEMATIC_JVM_FLAGS: &[(&str, &str)] = &[
+    ("-XX:+AggressiveOpts", "removed since java 11, the jvm refuses to start"),
+    ("-XX:+UseConcMarkSweepGC", "removed since java 14, the jvm refuses to start"),
+    ("-XX:+CMSIncrementalMode", "removed since java 9, the jvm refuses to start"),
+    ("-Xincgc", "removed since java 9, the jvm refuses to start"),
+    ("-XX:+UseFastAccessorMethods", "removed since java 9, the jvm refuses to start"),
+    ("-XX:+UseLargePagesInMetaspace", "removed since java 16, the jvm refuses to start"),
+    ("-XX:+AggressiveHeap", "sizes the heap from physical memory and ignores the configured memory"),
+    ("-XX:+UseStringCache", "removed since java 8, the jvm refuses to start"),
+];
+
+// flags snapshots get by default, along with the java major version from which the jvm accepts them
+const SNAPSHOT_JVM_FLAGS: &[(&str, u32)] = &[
+    ("--enable-preview", 12),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionFormat {
+    Token,
+    AccessToken,
+    Offline,
+    Template(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JrePreference {
+    First,
+    NewestPatch,
+    Vendor(String),
+    Pinned(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UuidFormat {
+    Simple,
+    Hyphenated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotChange {
+    JavaProgram(String, String),
+    JavaVersion(Option<String>, Option<String>),
+    WorkingDirectory(path::PathBuf, path::PathBuf),
+    Memory(String, Option<String>, Option<String>),
+    ArgumentRemoved(String),
+    ArgumentAdded(String),
 }
 
 pub fn builder() -> MinecraftLauncherBuilder {
@@ -59,18 +441,520 @@ pub fn create(game_dir: path::PathBuf,
     builder().root_dir(game_dir.as_path()).auth(game_auth_info).build()
 }
 
-#[cfg(target_os = "windows")]
 pub fn find_jre() -> Vec<String> {
+    let mut candidates = list_jre_candidates();
+    if let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+        candidates.extend(sdk_manager_jre_candidates(path::Path::new(&home)));
+    }
+    candidates.into_iter().filter(|program| is_java(program)).collect()
+}
+
+pub fn sdk_manager_jre_candidates(home: &path::Path) -> Vec<String> {
+    let java = if cfg!(target_os = "windows") { "java.exe" } else { "java" };
+    let patterns: &[&[&str]] = &[
+        &[".sdkman", "candidates", "java", "*", "bin"],
+        &[".asdf", "installs", "java", "*", "bin"],
+        &[".jabba", "jdk", "*", "bin"],
+        &[".jabba", "jdk", "*", "Contents", "Home", "bin"],
+        &[".jdks", "*", "bin"],
+        &[".local", "share", "JetBrains", "Toolbox", "apps", "*", "ch-0", "*", "jbr", "bin"],
+        &["Library", "Application Support", "JetBrains", "Toolbox", "apps", "*", "ch-0", "*", "jbr", "Contents", "Home", "bin"],
+    ];
+    let mut result = Vec::new();
+    for pattern in patterns.iter() {
+        let mut dirs = vec![home.to_path_buf()];
+        for component in pattern.iter() {
+            dirs = dirs.into_iter().flat_map(|dir| -> Vec<path::PathBuf> {
+                if *component != "*" { return vec![dir.join(component)]; }
+                let mut children: Vec<_> = fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path()).collect();
+                children.sort();
+                children
+            }).collect();
+        }
+        for dir in dirs {
+            let program = dir.join(java);
+            if program.is_file() { result.extend(program.into_os_string().into_string().ok()) }
+        }
+    }
+    result
+}
+
+#[cfg(windows)]
+pub fn available_disk_space(path: &path::Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(directory: *const u16, available: *mut u64, total: *mut u64, free: *mut u64) -> i32;
+    }
+    let directory: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // the bytes available to the current user, which respects disk quotas unlike the free bytes
+    match unsafe { GetDiskFreeSpaceExW(directory.as_ptr(), &mut available, ::std::ptr::null_mut(), ::std::ptr::null_mut()) } {
+        0 => Result::Err(io::Error::last_os_error()),
+        _ => Result::Ok(available)
+    }
+}
+
+#[cfg(unix)]
+pub fn available_disk_space(path: &path::Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: ::libc::statvfs = unsafe { ::std::mem::zeroed() };
+    if unsafe { ::libc::statvfs(path.as_ptr(), &mut stat) } != 0 { return Result::Err(io::Error::last_os_error()); }
+    // blocks available to unprivileged users are counted in units of the fragment size
+    Result::Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_total_memory_mib() -> io::Result<f32> {
+    let meminfo = fs::read_to_string("/proc/meminfo")?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"));
+    match line.and_then(|line| line.split_whitespace().nth(1)).map(str::parse::<f32>) {
+        Some(Result::Ok(kib)) => Result::Ok(kib / 1024f32),
+        _ => Result::Err(io::Error::other("no valid MemTotal in /proc/meminfo"))
+    }
+}
+
+#[cfg(windows)]
+pub fn read_total_memory_mib() -> io::Result<f32> {
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct MemoryStatusEx {
+        length: u32,
+        memory_load: u32,
+        total_phys: u64,
+        avail_phys: u64,
+        total_page_file: u64,
+        avail_page_file: u64,
+        total_virtual: u64,
+        avail_virtual: u64,
+        avail_extended_virtual: u64,
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GlobalMemoryStatusEx(buffer: *mut MemoryStatusEx) -> i32;
+    }
+    let mut status: MemoryStatusEx = unsafe { ::std::mem::zeroed() };
+    // the structure is only filled in once its own size is given
+    status.length = ::std::mem::size_of::<MemoryStatusEx>() as u32;
+    match unsafe { GlobalMemoryStatusEx(&mut status) } {
+        0 => Result::Err(io::Error::last_os_error()),
+        _ => Result::Ok(status.total_phys as f32 / (1024f32 * 1024f32))
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn read_total_memory_mib() -> io::Result<f32> {
+    let mut bytes = 0u64;
+    let mut size = ::std::mem::size_of::<u64>();
+    let name = b"hw.memsize\0";
+    let bytes_ptr = &mut bytes as *mut u64 as *mut ::libc::c_void;
+    if unsafe { ::libc::sysctlbyname(name.as_ptr() as *const _, bytes_ptr, &mut size, ::std::ptr::null_mut(), 0) } != 0 {
+        return Result::Err(io::Error::last_os_error());
+    }
+    Result::Ok(bytes as f32 / (1024f32 * 1024f32))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn read_total_memory_mib() -> io::Result<f32> {
+    // a percentage of the memory then falls back to a fixed maximum, see max_memory_of_percent
+    Result::Err(io::Error::new(io::ErrorKind::Unsupported, "reading the total memory is not supported on this platform"))
+}
+
+pub fn total_memory_mib() -> Option<f32> {
+    read_total_memory_mib().ok()
+}
+
+pub fn recommend_max_memory(mods_dir: &path::Path) -> MemoryRecommendation {
+    recommend_max_memory_with(mods_dir, read_total_memory_mib)
+}
+
+pub fn recommend_max_memory_with<F: FnOnce() -> io::Result<f32>>(mods_dir: &path::Path, read_total_memory_mib: F) -> MemoryRecommendation {
+    // a missing mods directory simply means a vanilla game
+    let mod_count = if mods_dir.is_dir() { count_mods(mods_dir) } else { Result::Ok(0) };
+    match (mod_count, read_total_memory_mib()) {
+        (Result::Ok(mod_count), Result::Ok(total)) => MemoryRecommendation {
+            memory_mib: recommended_max_memory_of(mod_count, Some(total)),
+            fallback_reason: None,
+        },
+        (Result::Err(e), _) => MemoryRecommendation {
+            memory_mib: FALLBACK_MAX_MEMORY_MIB,
+            fallback_reason: Some(format!("cannot count mods in {}: {}", mods_dir.display(), e)),
+        },
+        (_, Result::Err(e)) => MemoryRecommendation {
+            memory_mib: FALLBACK_MAX_MEMORY_MIB,
+            fallback_reason: Some(format!("cannot determine the total memory: {}", e)),
+        }
+    }
+}
+
+pub fn recommended_max_memory(mods_dir: &path::Path) -> io::Result<f32> {
+    Result::Ok(recommended_max_memory_of(count_mods(mods_dir)?, total_memory_mib()))
+}
+
+pub fn count_mods(mods_dir: &path::Path) -> io::Result<usize> {
+    let mut result = 0;
+    for entry in fs::read_dir(mods_dir)? {
+        let path_buf = entry?.path();
+        match path_buf.extension().and_then(|ext| ext.to_str()) {
+            Some("jar") | Some("zip") if path_buf.is_file() => result += 1,
+            _ => ()
+        }
+    }
+    Result::Ok(result)
+}
+
+pub fn recommended_max_memory_of(mod_count: usize, total_memory_mib: Option<f32>) -> f32 {
+    // roughly 2 GiB for vanilla plus 64 MiB per mod, leaving a quarter of the memory to the system
+    let recommended = 2048f32 + 64f32 * mod_count as f32;
+    match total_memory_mib {
+        Some(total) if recommended > total * 0.75 => (total * 0.75).max(1024f32),
+        _ => recommended
+    }
+}
+
+fn env_var<T: str::FromStr, F: Fn(&T) -> bool>(lookup: &EnvLookup, key: &str, is_valid: F) -> Option<T> {
+    let value = lookup(key)?;
+    let parsed = value.trim().parse::<T>().ok().filter(|parsed| is_valid(parsed));
+    if parsed.is_none() { warn!("Ignoring invalid value of {}: {:?}", key, value) }
+    parsed
+}
+
+pub fn read_options_resolution(game_dir: &path::Path) -> Option<(u32, u32)> {
+    let options = fs::read_to_string(game_dir.join("options.txt")).ok()?;
+    let value = |key: &str| options.lines().filter_map(|line| line.strip_prefix(key)?.strip_prefix(':')?.trim().parse::<u32>().ok()).next();
+    // the game writes zero when the resolution has never been overridden
+    Some((value("overrideWidth")?, value("overrideHeight")?)).filter(|&(width, height)| width > 0 && height > 0)
+}
+
+fn default_classpath_jar_path(game_dir: &path::Path, version_id: &str) -> path::PathBuf {
+    game_dir.join(".rmcll").join(format!("{}-classpath.jar", version_id))
+}
+
+fn default_argfile_path(game_dir: &path::Path, version_id: &str) -> path::PathBuf {
+    // sandboxes may hide the temporary directory, while the game directory is always visible to the jvm
+    game_dir.join(".rmcll").join(format!("{}-{}.args", version_id, process::id()))
+}
+
+fn copy_resources(copies: &[(path::PathBuf, path::PathBuf)]) -> Result<Vec<path::PathBuf>, versions::Error> {
+    let mut result = Vec::new();
+    for (object_path, resource_path) in copies.iter() {
+        if !object_path.is_file() || resource_path.is_file() { continue; }
+        if let Some(parent) = resource_path.parent() { fs::create_dir_all(parent)?; }
+        fs::copy(object_path, resource_path.as_path())?;
+        result.push(resource_path.clone());
+    }
+    Result::Ok(result)
+}
+
+fn is_secret_env_key(key: &str) -> bool {
+    let upper_key = key.to_uppercase();
+    ["TOKEN", "SECRET", "PASSWORD", "KEY"].iter().any(|word| upper_key.contains(word))
+}
+
+pub fn read_or_create_client_id(root_dir: &path::Path) -> String {
+    let path_buf = root_dir.join(".rmcll/client_id");
+    if let Ok(client_id) = fs::read_to_string(path_buf.as_path()) {
+        let client_id = client_id.trim();
+        if !client_id.is_empty() { return client_id.to_owned(); }
+    }
+    let client_id = Uuid::new_v4().hyphenated().to_string();
+    // an unsaved id is still usable, it just changes on the next launch
+    let saved = path_buf.parent().map_or(Result::Ok(()), fs::create_dir_all).and_then(|_| {
+        fs::write(path_buf.as_path(), client_id.as_bytes())
+    });
+    if let Result::Err(e) = saved {
+        warn!("Failed to save the client id to {}: {}", path_buf.display(), e);
+    }
+    client_id
+}
+
+pub fn read_instance_config(path: &path::Path) -> Result<InstanceConfig, versions::Error> {
+    // a missing file is the same as an empty one, so every instance may point to its own
+    if !path.is_file() { return Result::Ok(InstanceConfig::default()); }
+    let raw: RawInstanceConfig = toml::from_str(&fs::read_to_string(path)?).map_err(|e| {
+        versions::Error::from(io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+    })?;
+    let (max_memory_mib, max_memory_percent) = match raw.max_memory {
+        Some(MemoryValue::MiB(memory_mib)) => (Some(memory_mib), None),
+        Some(MemoryValue::Percent(ref percent)) => (None, percent.strip_suffix('%').and_then(|percent| percent.trim().parse().ok())),
+        None => (None, None)
+    };
+    Result::Ok(InstanceConfig {
+        java: raw.java,
+        min_memory_mib: raw.min_memory.filter(|memory_mib| *memory_mib > 0f32),
+        max_memory_mib: max_memory_mib.filter(|memory_mib| *memory_mib > 0f32),
+        max_memory_percent,
+        jvm_args: raw.jvm_args,
+        width: raw.width.filter(|width| *width > 0),
+        height: raw.height.filter(|height| *height > 0),
+    })
+}
+
+pub fn read_java_version_file(game_dir: &path::Path) -> Option<String> {
+    // .java-version is used by jenv and asdf, .sdkmanrc by the sdk manager
+    let java_version = fs::read_to_string(game_dir.join(".java-version")).ok().and_then(|content| {
+        content.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#')).map(str::to_owned)
+    });
+    java_version.or_else(|| {
+        let content = fs::read_to_string(game_dir.join(".sdkmanrc")).ok()?;
+        content.lines().filter_map(|line| line.split_once('=')).find(|&(key, _)| key.trim() == "java").map(|(_, value)| value.trim().to_owned())
+    }).filter(|version| !version.is_empty())
+}
+
+pub fn select_jre_of_version(candidates: &[String], requested: &str) -> Option<String> {
+    // vendor names such as temurin-17.0.2 or 17.0.2-tem are ignored
+    let requested = requested.split('-').find(|part| part.starts_with(|c: char| c.is_ascii_digit()))?;
+    let requested_key = java_version_key(requested);
+    candidates.iter().find(|program| match java_version(program) {
+        Some(ref version) if requested_key.len() == 1 => java_major_version(version) == requested_key.first().cloned(),
+        Some(ref version) => java_version_key(version).starts_with(&requested_key),
+        None => false
+    }).cloned()
+}
+
+pub fn write_options_resolution(game_dir: &path::Path, resolution: (u32, u32)) -> Result<bool, versions::Error> {
+    if read_options_resolution(game_dir) == Some(resolution) { return Result::Ok(false); }
+    let path_buf = game_dir.join("options.txt");
+    let options = fs::read_to_string(path_buf.as_path()).unwrap_or_default();
+    let mut lines: Vec<String> = options.lines().filter(|line| {
+        !line.starts_with("overrideWidth:") && !line.starts_with("overrideHeight:")
+    }).map(String::from).collect();
+    lines.push(format!("overrideWidth:{}", resolution.0));
+    lines.push(format!("overrideHeight:{}", resolution.1));
+    fs::write(path_buf, lines.join("\n") + "\n")?;
+    Result::Ok(true)
+}
+
+pub fn missing_vc_redist_libraries(system_dir: &path::Path) -> Vec<String> {
+    ["vcruntime140.dll", "msvcp140.dll"].iter().filter(|dll| !system_dir.join(dll).is_file()).map(|dll| (*dll).to_owned()).collect()
+}
+
+#[cfg(target_os = "windows")]
+pub fn check_vc_redist() -> Result<(), versions::Error> {
+    let system_root = env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into());
+    let missing = missing_vc_redist_libraries(path::Path::new(&system_root).join("System32").as_path());
+    if missing.is_empty() { return Result::Ok(()); }
+    Result::Err(versions::Error::MissingVcRedist(missing))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn check_vc_redist() -> Result<(), versions::Error> {
+    Result::Ok(())
+}
+
+pub fn jvm_pointer_width(program: &str) -> Option<u32> {
+    let output = Command::new(program).arg("-version").output().ok()?;
+    let string = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !string.contains(" VM") { return None; }
+    // 32-bit jvms print "Client VM" or "Server VM" without mentioning the pointer width
+    Some(if string.contains("64-Bit") { 64 } else { 32 })
+}
+
+pub fn check_jvm_pointer_width(jvm_bits: u32, native_arch: &str) -> Result<(), versions::Error> {
+    let native_bits = match native_arch {
+        "32" => 32,
+        "64" | "arm64" => 64,
+        _ => return Result::Ok(())
+    };
+    if jvm_bits == native_bits { return Result::Ok(()); }
+    Result::Err(versions::Error::JvmArchMismatch(jvm_bits, native_bits))
+}
+
+#[cfg(target_os = "windows")]
+pub fn check_windows_jvm_arch(program: &str) -> Result<(), versions::Error> {
+    match jvm_pointer_width(program) {
+        Some(jvm_bits) => check_jvm_pointer_width(jvm_bits, versions::native_arch()),
+        None => Result::Ok(())
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn check_windows_jvm_arch(_: &str) -> Result<(), versions::Error> {
+    Result::Ok(())
+}
+
+pub fn collapse_wildcard_dirs(entries: Vec<path::PathBuf>, dirs: &[path::PathBuf]) -> Vec<path::PathBuf> {
+    // the jvm expands dir/* to all the jars directly inside the directory since java 6
+    let canonical = |path: &path::Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dirs: Vec<_> = dirs.iter().map(|dir| canonical(dir)).collect();
+    let parents: Vec<_> = entries.iter().map(|entry| entry.parent().map(canonical).filter(|parent| dirs.contains(parent))).collect();
+    let jar_names = |dir: &path::Path| -> Option<HashSet<OsString>> {
+        let is_jar = |path: &path::PathBuf| path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("jar"));
+        let paths = fs::read_dir(dir).ok()?.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>().ok()?;
+        Some(paths.into_iter().filter(is_jar).filter_map(|path| path.file_name().map(|name| name.to_owned())).collect())
+    };
+    // a directory holding jars which are not on the classpath would add them to it, so it is kept enumerated
+    let collapsible: Vec<_> = dirs.iter().filter(|&dir| {
+        let listed: HashSet<_> = entries.iter().zip(parents.iter()).filter(|&(_, parent)| parent.as_ref() == Some(dir))
+            .filter_map(|(entry, _)| entry.file_name().map(|name| name.to_owned())).collect();
+        !listed.is_empty() && jar_names(dir.as_path()) == Some(listed)
+    }).collect();
+    let mut result: Vec<path::PathBuf> = Vec::new();
+    for (entry, parent) in entries.into_iter().zip(parents) {
+        let wildcard = parent.filter(|parent| collapsible.contains(&parent)).map(|parent| parent.join("*"));
+        match wildcard {
+            Some(wildcard) => if !result.contains(&wildcard) { result.push(wildcard) },
+            None => result.push(entry)
+        }
+    }
+    result
+}
+
+pub fn merge_jvm_option(options: &mut Vec<JvmOption>, option: JvmOption) {
+    let key = option.key();
+    options.retain(|o| o.key() != key);
+    options.push(option);
+}
+
+pub fn move_jvm_options_before_main_class(options: &mut Vec<JvmOption>, names: &[String]) {
+    let mut moved = Vec::new();
+    for name in names {
+        if let Some(index) = options.iter().position(|option| option.0 == *name || option.key() == name) {
+            // values of separated options such as "-cp <classpath>" are moved along with them
+            let end = (index + 1..options.len()).find(|&i| options[i].0.starts_with('-')).unwrap_or(options.len());
+            moved.extend(options.drain(index..end));
+        }
+    }
+    options.extend(moved);
+}
+
+pub fn read_jre_mapping(path: &path::Path) -> Result<HashMap<u32, String>, versions::Error> {
+    let mapping: serde_json::Value = serde_json::from_reader(fs::File::open(path)?)?;
+    Result::Ok(mapping.as_object().iter().flat_map(|mapping| mapping.iter()).filter_map(|(major, program)| {
+        match (major.trim().parse::<u32>(), program.as_str()) {
+            (Result::Ok(major), Some(program)) => Some((major, program.to_owned())),
+            _ => None
+        }
+    }).collect())
+}
+
+pub fn select_jre(candidates: &[String], required_major_version: Option<u32>) -> Option<String> {
+    select_jre_with(candidates, required_major_version, &JrePreference::First)
+}
+
+pub fn select_jre_with(candidates: &[String], required_major_version: Option<u32>, preference: &JrePreference) -> Option<String> {
+    let required = required_major_version.unwrap_or(8);
+    let mut compatible = Vec::new();
+    for program in candidates {
+        if let Some((version, output)) = java_version_output(program) {
+            match java_major_version(&version) {
+                Some(major) if major >= required => compatible.push((major, version, output, program)),
+                _ => ()
+            }
+        }
+    }
+    // the preference only breaks ties between runtimes of the closest major version
+    let closest = compatible.iter().map(|&(major, _, _, _)| major).min()?;
+    let mut tied: Vec<_> = compatible.into_iter().filter(|&(major, _, _, _)| major == closest).collect();
+    let index = match *preference {
+        JrePreference::First => None,
+        JrePreference::NewestPatch => (0..tied.len()).max_by_key(|&i| java_version_key(&tied[i].1)),
+        JrePreference::Vendor(ref vendor) => tied.iter().position(|t| t.2.to_lowercase().contains(&vendor.to_lowercase())),
+        JrePreference::Pinned(ref pinned) => tied.iter().position(|t| t.3 == pinned)
+    };
+    Some(tied.swap_remove(index.unwrap_or(0)).3.clone())
+}
+
+fn java_version_key(version: &str) -> Vec<u32> {
+    // e.g. 1.8.0_312 and 17.0.2+8 are compared number by number
+    version.split(|c: char| !c.is_ascii_digit()).filter(|part| !part.is_empty()).filter_map(|part| part.parse().ok()).collect()
+}
+
+pub fn java_major_version(version: &str) -> Option<u32> {
+    let version = version.strip_prefix("1.").unwrap_or(version);
+    version.split(|c: char| !c.is_ascii_digit()).next().and_then(|major| major.parse().ok())
+}
+
+pub fn is_java(program: &str) -> bool {
+    java_version(program).is_some()
+}
+
+pub fn java_version(program: &str) -> Option<String> {
+    java_version_output(program).map(|(version, _)| version)
+}
+
+fn java_version_output(program: &str) -> Option<(String, String)> {
+    let output = Command::new(program).arg("-version").output().ok()?;
+    let string = String::from_utf8_lossy(&output.stderr).into_owned();
+    if !output.status.success() || !string.contains(" VM") { return None; }
+    let version = string.lines().filter_map(|line| {
+        let mut parts = line.splitn(3, '"');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(prefix), Some(version), Some(_)) if prefix.ends_with(" version ") => Some(version.to_owned()),
+            _ => None
+        }
+    }).next()?;
+    Some((version, string))
+}
+
+pub fn java_supports_flag(program: &str, flag: &str) -> bool {
+    match Command::new(program).arg(flag).arg("-version").output() {
+        Result::Ok(output) => output.status.success() && !String::from_utf8_lossy(&output.stderr).contains("Unrecognized"),
+        Result::Err(_) => false
+    }
+}
+
+fn is_gc_selection_flag(arg: &str) -> bool {
+    arg.starts_with("-XX:+Use") && arg.ends_with("GC")
+}
+
+pub fn problematic_jvm_args(args: &[String]) -> Vec<(String, &'static str)> {
+    args.iter().filter_map(|arg| {
+        PROBLEMATIC_JVM_FLAGS.iter().find(|&&(flag, _)| arg == flag).map(|&(_, reason)| (arg.clone(), reason))
+    }).collect()
+}
+
+pub fn loaded_mod_ids(log: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut in_mod_list = false;
+    for line in log.lines() {
+        if let Some(index) = line.find("Loading ").filter(|_| line.contains(" mods:")) {
+            // fabric and quilt print the list on the following lines, while early fabric versions use a single one
+            let rest = &line[index..];
+            let inline = rest.split_once(" mods:").map(|parts| parts.1).unwrap_or("").trim();
+            result.extend(inline.split(", ").filter_map(|entry| entry.split('@').next()).filter(|id| !id.is_empty()).map(String::from));
+            in_mod_list = inline.is_empty();
+            continue;
+        }
+        if in_mod_list {
+            let entry = line.trim_start().trim_start_matches(['|', '-', ' ']);
+            if entry.len() == line.trim_start().len() || entry.is_empty() {
+                in_mod_list = false;
+            } else if let Some(id) = entry.split_whitespace().next() {
+                result.push(id.to_owned());
+            }
+        }
+    }
+    result
+}
+
+pub fn conflicting_jvm_options(options: &[JvmOption]) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let collectors: Vec<_> = options.iter().map(|option| option.0.as_str()).filter(|arg| is_gc_selection_flag(arg)).collect();
+    for (i, first) in collectors.iter().enumerate() {
+        for second in collectors[i + 1..].iter() {
+            // the parallel collector is split into young and old generation flags which work together
+            let is_parallel_pair = [*first, *second].iter().all(|arg| arg.starts_with("-XX:+UseParallel"));
+            if first != second && !is_parallel_pair { result.push(((*first).to_owned(), (*second).to_owned())) }
+        }
+    }
+    result
+}
+
+#[cfg(target_os = "windows")]
+fn list_jre_candidates() -> Vec<String> {
     Vec::new() // TODO
 }
 
 #[cfg(target_os = "macos")]
-pub fn find_jre() -> Vec<String> {
+fn list_jre_candidates() -> Vec<String> {
     Vec::new() // TODO: I cannot afford a mac
 }
 
 #[cfg(target_os = "linux")]
-pub fn find_jre() -> Vec<String> {
+fn list_jre_candidates() -> Vec<String> {
     let program = "update-alternatives";
     if let Result::Ok(output) = Command::new(program).arg("--list").arg("java").output() {
         if let Result::Ok(string) = String::from_utf8(output.stdout) {
@@ -92,26 +976,102 @@ impl MinecraftLauncherBuilder {
         self
     }
 
+    pub fn profile_game_dir(mut self, dir: &path::Path) -> Self {
+        self.profile_game_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn config_file(mut self, path: &path::Path) -> Result<Self, versions::Error> {
+        self.instance_config = Some(read_instance_config(path)?);
+        Result::Ok(self)
+    }
+
     pub fn assets_dir(mut self, dir: &path::Path) -> Self {
         self.assets_dir = Some(dir.to_path_buf());
         self
     }
 
+    pub fn assets_objects_dir(mut self, dir: &path::Path) -> Self {
+        self.assets_objects_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn exclude_assets(mut self, prefixes: &[&str]) -> Self {
+        self.excluded_asset_prefixes.extend(prefixes.iter().map(|prefix| (*prefix).to_owned()));
+        self
+    }
+
+    pub fn asset_index(mut self, id: &str) -> Self {
+        self.asset_index_override = Some(id.to_owned());
+        self
+    }
+
     pub fn libraries_dir(mut self, dir: &path::Path) -> Self {
         self.libraries_dir = Some(dir.to_path_buf());
         self
     }
 
+    pub fn libraries_overlay_dir(mut self, dir: &path::Path) -> Self {
+        self.libraries_overlay_dir = Some(dir.to_path_buf());
+        self
+    }
+
     pub fn jre(mut self, path: &path::Path) -> Self {
         self.program_path = path.to_path_buf().into_os_string().into_string().ok();
         self
     }
 
+    pub fn jre_mapping(mut self, mapping: HashMap<u32, String>) -> Self {
+        self.jre_mapping.extend(mapping);
+        self
+    }
+
+    pub fn jre_preference(mut self, preference: JrePreference) -> Self {
+        self.jre_preference = Some(preference);
+        self
+    }
+
+    pub fn jre_candidates(mut self, paths: &[&path::Path]) -> Self {
+        let paths = paths.iter().filter_map(|path| path.to_path_buf().into_os_string().into_string().ok());
+        self.jre_candidates.extend(paths);
+        self
+    }
+
     pub fn auth(mut self, auth: yggdrasil::AuthInfo) -> Self {
         self.auth_info = Some(auth);
         self
     }
 
+    pub fn auth_source(mut self, name: &str, auth: yggdrasil::AuthInfo) -> Self {
+        self.auth_sources.push((name.to_owned(), auth));
+        self
+    }
+
+    pub fn access_token_provider<F: Fn() -> String + Send + Sync + 'static>(mut self, provider: F) -> Self {
+        self.access_token_provider = Some(SecretProvider(Arc::new(provider)));
+        self
+    }
+
+    pub fn session_format(mut self, format: SessionFormat) -> Self {
+        self.session_format = Some(format);
+        self
+    }
+
+    pub fn auth_uuid_format(mut self, format: UuidFormat) -> Self {
+        self.auth_uuid_format = Some(format);
+        self
+    }
+
+    pub fn session_uuid_format(mut self, format: UuidFormat) -> Self {
+        self.session_uuid_format = Some(format);
+        self
+    }
+
+    pub fn client_id(mut self, client_id: &str) -> Self {
+        self.client_id = Some(client_id.to_owned());
+        self
+    }
+
     pub fn launcher(mut self, name: &str, version: &str) -> Self {
         self.launcher_name_version = Some((name.to_owned(), version.to_owned()));
         self
@@ -127,58 +1087,393 @@ impl MinecraftLauncherBuilder {
         self
     }
 
-    pub fn resolution(mut self, width: u32, height: u32) -> Self {
+    pub fn max_memory_percent(mut self, percent: f32) -> Self {
+        self.max_memory_percent = Some(percent);
+        self
+    }
+
+    pub fn total_memory(mut self, memory_mib: f32) -> Self {
+        self.total_memory_mib = Some(memory_mib);
+        self
+    }
+
+    pub fn stack_size(mut self, size_kib: u32) -> Self {
+        self.stack_size_kib = Some(size_kib);
+        self
+    }
+
+    pub fn resolution(mut self, width: u32, height: u32) -> Self {
         self.window_resolution = Some((width, height));
         self
     }
 
+    pub fn primary_jar(mut self, path: &path::Path) -> Self {
+        self.primary_jar_override = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn fallback_main_class(mut self, main_class: &str) -> Self {
+        self.fallback_main_class = Some(main_class.to_owned());
+        self
+    }
+
+    pub fn entrypoint_wrapper(mut self, jar: &path::Path, main_class: &str) -> Self {
+        self.entrypoint_wrapper = Some((jar.to_path_buf(), main_class.to_owned()));
+        self
+    }
+
+    pub fn natives_classifier(mut self, classifier: &str) -> Self {
+        self.natives_classifier = Some(classifier.to_owned());
+        self
+    }
+
+    pub fn strip_native_versions(mut self, strip_versions: bool) -> Self {
+        self.strip_native_versions = strip_versions;
+        self
+    }
+
+    pub fn library_classifier(mut self, group_artifact: &str, classifier: &str) -> Self {
+        self.library_classifiers.insert(group_artifact.to_owned(), classifier.to_owned());
+        self
+    }
+
+    pub fn relative_classpath(mut self, relative: bool) -> Self {
+        self.relative_classpath = relative;
+        self
+    }
+
+    pub fn command_line_limit(mut self, limit: usize) -> Self {
+        self.command_line_limit = Some(limit);
+        self
+    }
+
+    pub fn classpath_wildcard_dirs(mut self, dirs: &[&path::Path]) -> Self {
+        self.classpath_wildcard_dirs.extend(dirs.iter().map(|dir| dir.to_path_buf()));
+        self
+    }
+
+    pub fn classpath_separator(mut self, separator: &str) -> Self {
+        self.classpath_separator = Some(separator.to_owned());
+        self
+    }
+
+    pub fn classpath_jar(mut self, path: &path::Path) -> Self {
+        self.classpath_jar = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn argfile(mut self, path: Option<&path::Path>) -> Self {
+        self.argfile = Some(path.map(path::Path::to_path_buf));
+        self
+    }
+
+    pub fn keep_argfile(mut self, keep: bool) -> Self {
+        self.keep_argfile = keep;
+        self
+    }
+
+    pub fn disable_chat(mut self, disabled: bool) -> Self {
+        self.disable_chat = disabled;
+        self
+    }
+
+    pub fn map_to_resources(mut self, map_to_resources: bool) -> Self {
+        self.map_to_resources = map_to_resources;
+        self
+    }
+
+    pub fn proxy(mut self, host: &str, port: u16) -> Self {
+        self.proxy = Some((host.to_owned(), port));
+        self
+    }
+
+    pub fn proxy_credentials(mut self, user: &str, password: &str) -> Self {
+        self.proxy_credentials = Some((user.to_owned(), password.to_owned()));
+        self
+    }
+
+    pub fn telemetry_host(mut self, host: &str) -> Self {
+        self.telemetry_host = Some(host.to_owned());
+        self
+    }
+
+    pub fn auth_server(mut self, api_root: &str) -> Self {
+        self.auth_server = Some(api_root.to_owned());
+        self
+    }
+
+    pub fn auth_server_metadata(mut self, metadata: &serde_json::Value) -> Self {
+        self.auth_server_metadata = Some(metadata.to_string());
+        self
+    }
+
+    pub fn authlib_injector(mut self, agent_jar: &path::Path) -> Self {
+        self.authlib_injector = Some(agent_jar.to_path_buf());
+        self
+    }
+
+    pub fn debug_port(mut self, port: u16) -> Self {
+        self.debug_port = Some(port);
+        self
+    }
+
+    pub fn debug_suspend(mut self, suspend: bool) -> Self {
+        self.debug_suspend = suspend;
+        self
+    }
+
+    pub fn exit_on_out_of_memory(mut self, exit: bool) -> Self {
+        self.exit_on_out_of_memory = exit;
+        self
+    }
+
+    pub fn probe_gc_flags(mut self, probe: bool) -> Self {
+        self.probe_gc_flags = probe;
+        self
+    }
+
+    pub fn crash_reports_dir(mut self, dir: &path::Path) -> Self {
+        self.crash_reports_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn create_game_dirs(mut self, create: bool) -> Self {
+        self.create_game_dirs = Some(create);
+        self
+    }
+
+    pub fn verify_natives(mut self, verify: bool) -> Self {
+        self.verify_natives = verify;
+        self
+    }
+
+    pub fn env_lookup<F: Fn(&str) -> Option<String> + 'static>(mut self, lookup: F) -> Self {
+        self.env_lookup = Some(Box::new(lookup));
+        self
+    }
+
+    pub fn hasher<H: versions::FileHasher + 'static>(mut self, hasher: H) -> Self {
+        self.hasher = Some(Arc::new(hasher));
+        self
+    }
+
+    pub fn file_encoding(mut self, encoding: Option<&str>) -> Self {
+        self.file_encoding = Some(encoding.map(str::to_owned));
+        self
+    }
+
+    pub fn sandbox(mut self, program: &str, args: &[&str]) -> Self {
+        self.sandbox_command = vec![program.to_owned()];
+        self.sandbox_command.extend(args.iter().map(|arg| (*arg).to_owned()));
+        self
+    }
+
+    pub fn bubblewrap(self) -> Self {
+        self.sandbox("bwrap", &["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp",
+                                "--bind", "${game_directory}", "${game_directory}", "--unshare-all", "--share-net", "--"])
+    }
+
+    pub fn firejail(self) -> Self {
+        self.sandbox("firejail", &["--quiet", "--whitelist=${game_directory}", "--"])
+    }
+
+    pub fn version_kind_jvm_options(mut self, kind: versions::VersionKind, options: &[&str]) -> Self {
+        let entry = self.version_kind_jvm_options.entry(kind).or_default();
+        entry.extend(options.iter().map(|option| (*option).to_owned()));
+        self
+    }
+
+    pub fn jvm_args(mut self, args: &[&str]) -> Self {
+        self.extra_jvm_args.extend(args.iter().map(|arg| (*arg).to_owned()));
+        self
+    }
+
+    pub fn jvm_args_before_main_class(mut self, args: &[&str]) -> Self {
+        self.main_class_adjacent_jvm_args.extend(args.iter().map(|arg| (*arg).to_owned()));
+        self
+    }
+
     pub fn build(self) -> MinecraftLauncher {
         let root_dir = self.game_root_dir.expect("game root dir not specified");
+        let game_dir = self.profile_game_dir.unwrap_or_else(|| root_dir.clone());
+        // values of the builder take precedence over the config file
+        let config = self.instance_config.unwrap_or_default();
+        let program_path = self.program_path.or_else(|| config.java.clone());
+        let assets_dir = self.assets_dir.unwrap_or_else(|| root_dir.as_path().join("assets/"));
+        let jre_mapping = self.jre_mapping;
+        let mut auth_sources = self.auth_sources;
+        if let Some(auth_info) = self.auth_info {
+            // the one specified by auth() is active by default
+            auth_sources.insert(0, ("default".to_owned(), auth_info));
+        }
+        assert!(!auth_sources.is_empty(), "auth info not specified");
+        let saved_resolution = read_options_resolution(game_dir.as_path());
+        let env_lookup = self.env_lookup.unwrap_or_else(|| Box::new(|key| env::var(key).ok()));
+        // a percentage only applies if no absolute value of a higher precedence is specified
+        let builder_max_memory_percent = self.max_memory_percent;
+        let max_memory_mib = self.max_memory_mib.or_else(|| {
+            if builder_max_memory_percent.is_some() { return None; }
+            env_var(env_lookup.as_ref(), "RMCLL_MAX_MEMORY", |memory_mib: &f32| *memory_mib > 0f32).or(config.max_memory_mib)
+        });
+        let max_memory_percent = if max_memory_mib.is_some() { None } else { builder_max_memory_percent.or(config.max_memory_percent) };
         MinecraftLauncher {
-            program_path: self.program_path.unwrap_or_else(|| find_jre().pop().expect("jre not found")),
-            assets_dir: self.assets_dir.unwrap_or_else(|| root_dir.as_path().join("assets/")),
+            program_path,
+            jre_candidates: self.jre_candidates,
+            jre_mapping,
+            jre_preference: self.jre_preference.unwrap_or(JrePreference::First),
+            assets_objects_dir: self.assets_objects_dir.unwrap_or_else(|| assets_dir.join("objects/")),
+            excluded_asset_prefixes: self.excluded_asset_prefixes,
+            asset_index_override: self.asset_index_override,
+            assets_dir,
             libraries_dir: self.libraries_dir.unwrap_or_else(|| root_dir.as_path().join("libraries/")),
+            libraries_overlay_dir: self.libraries_overlay_dir,
             manager: versions::VersionManager::new(root_dir.as_path().join("versions/").as_path()),
+            game_dir,
             game_root_dir: root_dir,
-            launcher_name_version: self.launcher_name_version.unwrap_or(("RMCLL".to_owned(), "0.1.0".to_owned())),
-            auth_info: self.auth_info.expect("auth info not specified"),
-            min_max_memory_mib: (self.min_memory_mib.unwrap_or(128f32), self.max_memory_mib.unwrap_or(0f32)),
-            window_resolution: self.window_resolution.unwrap_or((854, 480)),
+            launcher_name_version: self.launcher_name_version.unwrap_or((LAUNCHER_NAME.to_owned(), LAUNCHER_VERSION.to_owned())),
+            auth_sources,
+            active_auth_source: 0,
+            access_token_provider: self.access_token_provider,
+            session_format: self.session_format.unwrap_or(SessionFormat::Token),
+            auth_uuid_format: self.auth_uuid_format.unwrap_or(UuidFormat::Simple),
+            session_uuid_format: self.session_uuid_format.unwrap_or(UuidFormat::Simple),
+            client_id: self.client_id,
+            min_max_memory_mib: (self.min_memory_mib.or(config.min_memory_mib).unwrap_or(128f32), max_memory_mib.unwrap_or(0f32)),
+            max_memory_percent,
+            total_memory_mib: self.total_memory_mib,
+            stack_size_kib: self.stack_size_kib,
+            window_resolution: self.window_resolution.unwrap_or_else(|| {
+                (env_var(env_lookup.as_ref(), "RMCLL_WIDTH", |width: &u32| *width > 0).or(config.width).or(saved_resolution.map(|r| r.0)).unwrap_or(854),
+                 env_var(env_lookup.as_ref(), "RMCLL_HEIGHT", |height: &u32| *height > 0).or(config.height).or(saved_resolution.map(|r| r.1)).unwrap_or(480))
+            }),
+            primary_jar_override: self.primary_jar_override,
+            fallback_main_class: self.fallback_main_class,
+            entrypoint_wrapper: self.entrypoint_wrapper,
+            natives_classifier: self.natives_classifier,
+            strip_native_versions: self.strip_native_versions,
+            library_classifiers: self.library_classifiers,
+            relative_classpath: self.relative_classpath,
+            command_line_limit: self.command_line_limit,
+            classpath_wildcard_dirs: self.classpath_wildcard_dirs,
+            classpath_separator: self.classpath_separator.unwrap_or_else(|| versions::CLASSPATH_SEPARATOR.to_owned()),
+            classpath_jar: self.classpath_jar,
+            argfile: self.argfile,
+            keep_argfile: self.keep_argfile,
+            disable_chat: self.disable_chat,
+            map_to_resources: self.map_to_resources,
+            proxy: self.proxy,
+            proxy_credentials: self.proxy_credentials,
+            telemetry_host: self.telemetry_host,
+            auth_server: self.auth_server,
+            auth_server_metadata: self.auth_server_metadata,
+            authlib_injector: self.authlib_injector,
+            debug_port: self.debug_port,
+            debug_suspend: self.debug_suspend,
+            exit_on_out_of_memory: self.exit_on_out_of_memory,
+            probe_gc_flags: self.probe_gc_flags,
+            gc_flag_support: Mutex::new(HashMap::new()),
+            crash_reports_dir: self.crash_reports_dir,
+            create_game_dirs: self.create_game_dirs.unwrap_or(true),
+            verify_natives: self.verify_natives,
+            hasher: self.hasher.unwrap_or_else(|| Arc::new(versions::BuiltinHasher)),
+            file_encoding: self.file_encoding.unwrap_or_else(|| Some("UTF-8".to_owned())),
+            sandbox_command: self.sandbox_command,
+            sandbox_available: Mutex::new(None),
+            version_kind_jvm_options: self.version_kind_jvm_options,
+            extra_jvm_args: config.jvm_args.into_iter().chain(self.extra_jvm_args).collect(),
+            main_class_adjacent_jvm_args: self.main_class_adjacent_jvm_args,
         }
     }
 }
 
 impl MinecraftLauncher {
+    pub fn launcher_name_version(&self) -> (&str, &str) {
+        (&self.launcher_name_version.0, &self.launcher_name_version.1)
+    }
+
+    pub fn auth_info(&self) -> &yggdrasil::AuthInfo {
+        &self.auth_sources[self.active_auth_source].1
+    }
+
+    pub fn set_active_auth_source(&mut self, name: &str) -> bool {
+        match self.auth_sources.iter().position(|(source, _)| source == name) {
+            Some(index) => {
+                self.active_auth_source = index;
+                true
+            }
+            None => false
+        }
+    }
+
     pub fn generate_argument_map(&self,
                                  version: &versions::MinecraftVersion) -> HashMap<String, String> {
+        let mut map = self.argument_map(version);
+        // values depending on a lazily provided token are only known when the command is assembled
+        map.retain(|_, value| !value.contains(ACCESS_TOKEN_PLACEHOLDER));
+        map
+    }
+
+    fn argument_map(&self, version: &versions::MinecraftVersion) -> HashMap<String, String> {
+        self.argument_map_with(version, self.access_token_provider.is_some())
+    }
+
+    fn argument_map_with(&self, version: &versions::MinecraftVersion, defer_access_token: bool) -> HashMap<String, String> {
         let mut map: HashMap<String, String> = HashMap::new();
-        let name = self.auth_info.user_profile().name();
-        let uuid = self.auth_info.user_profile().uuid().simple();
-        let access_token = self.auth_info.access_token().simple();
+        let name = self.auth_info().user_profile().name();
+        let uuid = self.auth_uuid_format.format(self.auth_info().user_profile().uuid());
+        // some authentication shims expect different formats of the same uuid on the command line and in the session
+        let session_uuid = self.session_uuid_format.format(self.auth_info().user_profile().uuid());
+        let access_token = if defer_access_token {
+            ACCESS_TOKEN_PLACEHOLDER // resolved when the command is assembled
+        } else {
+            self.auth_info().access_token()
+        };
         map.insert("auth_access_token".to_owned(),
-                   format!("{}", access_token));
+                   access_token.to_owned());
         map.insert("user_properties".to_owned(),
                    "{}".to_owned()); // TODO
         map.insert("user_property_map".to_owned(),
                    "{}".to_owned()); // TODO
-        map.insert("auth_session".to_owned(),
-                   format!("token:{}:{}", access_token, uuid));
+        map.insert("auth_session".to_owned(), match self.session_format {
+            SessionFormat::Token => format!("token:{}:{}", access_token, session_uuid),
+            SessionFormat::AccessToken => access_token.to_owned(),
+            SessionFormat::Offline => "-".to_owned(),
+            SessionFormat::Template(ref template) => {
+                let (access_token, uuid, name) = (access_token.to_owned(), session_uuid, name.clone());
+                parsing::substitute(template, &parsing::ParameterStrategy::map(move |key| match key.as_str() {
+                    "auth_access_token" => access_token.clone(),
+                    "auth_uuid" => uuid.clone(),
+                    "auth_player_name" => name.clone(),
+                    _ => String::new()
+                }))
+            }
+        });
         map.insert("auth_player_name".to_owned(),
                    name.clone());
         map.insert("auth_uuid".to_owned(),
-                   format!("{}", uuid));
+                   uuid);
         map.insert("user_type".to_owned(),
-                   "legacy".to_owned());
+                   self.auth_info().account_type().as_str().to_owned());
         map.insert("profile_name".to_owned(),
                    name.clone());
         map.insert("version_name".to_owned(),
                    version.id().to_owned());
         map.insert("game_directory".to_owned(),
-                   self.game_root_dir.to_str().unwrap_or("").to_owned());
+                   self.game_dir.to_str().unwrap_or("").to_owned());
         map.insert("assets_root".to_owned(),
                    self.assets_dir.to_str().unwrap_or("").to_owned());
         map.insert("assets_index_name".to_owned(),
-                   version.asset_index(&self.manager).map(|i| i.id().to_owned()).unwrap_or_else(String::new));
+                   self.asset_index_of(version).map(|i| i.id().to_owned()).unwrap_or_else(String::new));
+        // modern versions read assets_root, while older ones read game_assets from virtual or resources
+        map.insert("game_assets".to_owned(),
+                   self.asset_index_of(version).and_then(|i| {
+                       let path_buf = match i.layout(self.assets_dir.as_path()) {
+                           Result::Ok(versions::AssetLayout::Resources) => self.game_dir.join("resources"),
+                           Result::Ok(versions::AssetLayout::Objects) => self.assets_dir.clone(),
+                           _ => i.virtual_path(self.assets_dir.as_path())
+                       };
+                       path_buf.to_str().map(String::from)
+                   }).unwrap_or_default());
         map.insert("version_type".to_owned(),
                    version.version_type().to_owned());
         map.insert("resolution_width".to_owned(),
@@ -192,109 +1487,4080 @@ impl MinecraftLauncher {
         map.insert("launcher_version".to_owned(),
                    self.launcher_name_version.1.clone());
         map.insert("natives_directory".to_owned(),
-                   self.manager.get_natives_path(version.id()).to_str().unwrap_or("").to_owned());
+                   self.natives_directory(version.id()).to_str().unwrap_or("").to_owned());
         map.insert("primary_jar".to_owned(),
-                   version.version_jar_path(&self.manager).ok().and_then(|p| p.to_str().map(String::from)).unwrap_or_else(String::new));
+                   self.primary_jar(version).ok().and_then(|p| p.to_str().map(String::from)).unwrap_or_else(String::new));
         map.insert("classpath".to_owned(),
-                   version.classpath(self.libraries_dir.as_path(), &self.manager).unwrap_or_else(|_| String::new()));
+                   self.classpath(version).unwrap_or_else(|_| String::new()));
         map.insert("classpath_separator".to_owned(),
-                   ":".to_owned());
+                   self.classpath_separator.clone());
+        map.insert("library_directory".to_owned(),
+                   self.libraries_dir.to_str().unwrap_or("").to_owned());
+        let forge_version = match version.mod_loader(&self.manager) {
+            Some(("forge", loader_version)) | Some(("neoforge", loader_version)) => loader_version,
+            _ => String::new()
+        };
+        map.insert("forge_version".to_owned(),
+                   forge_version);
+        map.insert("mc_version".to_owned(),
+                   version.base_version_id(&self.manager));
+        map.insert("mcp_version".to_owned(),
+                   version.mcp_version(&self.manager).unwrap_or_default());
+        map.insert("auth_xuid".to_owned(),
+                   String::new()); // TODO
+        // the id is persisted on first use, so versions without ${clientid} never touch the disk for it
+        if version.required_placeholders(&self.manager).map(|keys| keys.contains("clientid")).unwrap_or(false) {
+            map.insert("clientid".to_owned(),
+                       self.client_id());
+        }
         map
     }
 
-    pub fn to_arguments(&self, version_id: &str) -> Result<LaunchArguments, versions::Error> {
-        let java_program_path = self.program_path.clone();
-        let minecraft_version = self.manager.version_of(version_id)?;
-        let java_main_class = minecraft_version.main_class(&self.manager).unwrap_or_else(String::new);
-        let game_natives = minecraft_version.to_native_collection(&self.manager, self.libraries_dir.as_path())?;
-        let mut jvm_options = vec![
-            JvmOption::new("-XX:+UseG1GC".to_owned()),
-            JvmOption::new("-XX:-UseAdaptiveSizePolicy".to_owned()),
-            JvmOption::new("-XX:-OmitStackTraceInFastThrow".to_owned()),
-            JvmOption::new("-Dfml.ignoreInvalidMinecraftCertificates=true".to_owned()),
-            JvmOption::new("-Dfml.ignorePatchDiscrepancies=true".to_owned()),
-        ];
-        let (min_mib, max_mib) = self.min_max_memory_mib;
-        if min_mib > 0f32 { jvm_options.push(JvmOption::new(format!("-Xmn{}m", min_mib))) }
-        if max_mib > 0f32 { jvm_options.push(JvmOption::new(format!("-Xmx{}m", max_mib))) }
-        let mut game_options = Vec::new();
-        let map = self.generate_argument_map(&minecraft_version);
-        let game_native_path = path::PathBuf::from(map.get("natives_directory").unwrap());
-        let strategy = parsing::ParameterStrategy::map(move |s| {
-            let result = match map.get(&s) {
-                Some(ref string) => (*string).clone(),
-                None => String::new()
-            };
-            result
-        });
-        minecraft_version.collect_game_arguments(&self.manager, &mut game_options, &strategy)?;
-        minecraft_version.collect_jvm_arguments(&self.manager, &mut jvm_options, &strategy)?;
-        Result::Ok(LaunchArguments {
-            game_natives,
-            game_native_path,
-            game_options,
-            jvm_options,
-            java_main_class,
-            java_program_path,
-        })
+    pub fn client_id(&self) -> String {
+        self.client_id.clone().unwrap_or_else(|| read_or_create_client_id(self.game_root_dir.as_path()))
     }
-}
 
-impl LaunchArguments {
-    pub fn start(&self) -> Result<Child, versions::Error> {
-        self.extract_natives()?;
-        self.spawn_new_process()
+    pub fn missing_placeholders(&self, version: &versions::MinecraftVersion) -> Result<Vec<String>, versions::Error> {
+        let map = self.argument_map(version);
+        let mut result: Vec<String> = version.required_placeholders(&self.manager)?.into_iter().filter(|key| {
+            !map.contains_key(key)
+        }).collect();
+        result.sort();
+        Result::Ok(result)
     }
 
-    pub fn spawn_new_process(&self) -> Result<Child, versions::Error> {
-        Command::new(self.program()).args(self.args()).spawn().map_err(versions::Error::from)
+    pub fn asset_index_of(&self, version: &versions::MinecraftVersion) -> Option<versions::AssetDownloadInfo> {
+        match self.asset_index_override {
+            // the overridden index is looked up locally, since its download info is not known
+            Some(ref id) => Some(versions::AssetDownloadInfo::new(id.clone())),
+            None => version.asset_index(&self.manager)
+        }
     }
 
-    pub fn extract_natives(&self) -> Result<Vec<String>, versions::Error> {
-        self.game_natives.extract_to(self.game_native_path.as_path())
+    fn included_asset_objects(&self, asset_index: &versions::AssetDownloadInfo) -> Result<Vec<(String, String)>, versions::Error> {
+        let mut objects = asset_index.objects(self.assets_dir.as_path())?;
+        // the game runs without the excluded objects, e.g. it simply stays silent without sounds
+        objects.retain(|(name, _)| !self.excluded_asset_prefixes.iter().any(|prefix| name.starts_with(prefix.as_str())));
+        Result::Ok(objects)
     }
 
-    pub fn program(&self) -> String {
-        self.java_program_path.clone()
+    pub fn asset_downloads(&self, version_id: &str) -> Result<Vec<(String, path::PathBuf)>, versions::Error> {
+        let version = self.manager.version_of(version_id)?;
+        let asset_index = match self.asset_index_of(&version) {
+            Some(asset_index) => asset_index,
+            None => return Result::Ok(Vec::new())
+        };
+        let mut result = Vec::new();
+        for (_, hash) in self.included_asset_objects(&asset_index)? {
+            let path_buf = self.asset_object_path(&hash);
+            if !path_buf.is_file() {
+                result.push((versions::AssetDownloadInfo::object_url(versions::RESOURCES_HOST, &hash), path_buf));
+            }
+        }
+        Result::Ok(result)
     }
 
-    pub fn args(&self) -> Vec<String> {
+    pub fn natives_directory(&self, version_id: &str) -> path::PathBuf {
+        self.manager.get_natives_path(version_id)
+    }
+
+    pub fn asset_object_path(&self, hash: &str) -> path::PathBuf {
+        versions::AssetDownloadInfo::object_path(self.assets_objects_dir.as_path(), hash)
+    }
+
+    pub fn primary_jar(&self, version: &versions::MinecraftVersion) -> Result<path::PathBuf, versions::Error> {
+        match self.primary_jar_override {
+            Some(ref path_buf) => Result::Ok(path_buf.clone()),
+            None => version.version_jar_path(&self.manager)
+        }
+    }
+
+    pub fn embedded_version_mismatch(&self, version: &versions::MinecraftVersion) -> Result<Option<String>, versions::Error> {
+        let primary_jar = self.primary_jar(version)?;
+        // only client jars since 18w47b carry a version.json
+        let embedded_id = if primary_jar.is_file() { versions::jar_embedded_version(primary_jar.as_path())? } else { None };
+        Result::Ok(embedded_id.filter(|id| *id != version.base_version_id(&self.manager)))
+    }
+
+    pub fn main_class(&self, version: &versions::MinecraftVersion) -> Result<String, versions::Error> {
+        if let Some(main_class) = version.main_class(&self.manager).or_else(|| self.fallback_main_class.clone()) {
+            return Result::Ok(main_class);
+        }
+        let primary_jar = self.primary_jar(version)?;
+        let manifest_main_class = if primary_jar.is_file() { versions::jar_main_class(primary_jar.as_path())? } else { None };
+        manifest_main_class.ok_or_else(|| versions::Error::NoMainClass(version.id().to_owned()))
+    }
+
+    fn overlay_path_of(&self, library_path: &path::Path) -> Option<path::PathBuf> {
+        let overlay_dir = self.libraries_overlay_dir.as_ref()?;
+        library_path.strip_prefix(self.libraries_dir.as_path()).ok().map(|relative| overlay_dir.join(relative))
+    }
+
+    pub fn resolve_library_path(&self, library_path: &path::Path) -> path::PathBuf {
+        match self.overlay_path_of(library_path) {
+            Some(ref overlay_path) if overlay_path.is_file() => overlay_path.clone(),
+            _ => library_path.to_path_buf()
+        }
+    }
+
+    pub fn library_write_path(&self, library_path: &path::Path) -> path::PathBuf {
+        // a shared libraries directory may be read-only, so writes go to the overlay of the instance instead
+        let is_read_only = fs::metadata(self.libraries_dir.as_path()).map(|metadata| metadata.permissions().readonly()).unwrap_or(false);
+        match self.overlay_path_of(library_path) {
+            Some(overlay_path) if is_read_only || overlay_path.is_file() => overlay_path,
+            _ => library_path.to_path_buf()
+        }
+    }
+
+    pub fn writable_library(&self, library_path: &path::Path) -> Result<path::PathBuf, versions::Error> {
+        let path_buf = self.library_write_path(library_path);
+        if path_buf.as_path() != library_path && !path_buf.is_file() && library_path.is_file() {
+            // copy on write, so that the library can be patched in place
+            if let Some(parent) = path_buf.parent() { fs::create_dir_all(parent)?; }
+            fs::copy(library_path, path_buf.as_path())?;
+        }
+        Result::Ok(path_buf)
+    }
+
+    pub fn main_class_layers(&self, version_id: &str) -> Result<Vec<(String, String)>, versions::Error> {
+        Result::Ok(self.manager.version_of(version_id)?.main_class_layers(&self.manager))
+    }
+
+    pub fn classpath_entries(&self, version: &versions::MinecraftVersion) -> Result<Vec<path::PathBuf>, versions::Error> {
+        let libraries_dir = self.libraries_dir.as_path();
+        let mut entries = Vec::new();
+        for path_buf in version.library_paths_with_classifiers(libraries_dir, &self.library_classifiers, &self.manager)? {
+            entries.push(fs::canonicalize(self.resolve_library_path(path_buf.as_path()))?);
+        }
+        entries.push(self.primary_jar(version)?);
+        if let Some((ref jar, _)) = self.entrypoint_wrapper {
+            entries.insert(0, jar.clone());
+        }
+        Result::Ok(entries)
+    }
+
+    pub fn save_resolution(&self) -> Result<bool, versions::Error> {
+        write_options_resolution(self.game_dir.as_path(), self.window_resolution)
+    }
+
+    pub fn java_program_path(&self, version: &versions::MinecraftVersion) -> Result<String, versions::Error> {
+        let java_major_version = version.java_major_version(&self.manager);
+        // an explicitly chosen java overrides the mapping, which is only a default per major version
+        if let Some(ref program) = self.program_path { return Result::Ok(program.clone()); }
+        if let Some(program) = self.jre_mapping.get(&java_major_version.unwrap_or(8)) { return Result::Ok(program.clone()); }
+        if let Some(program) = self.toolchain_program_path() { return Result::Ok(program); }
+        let selected = if version.requires_64bit(&self.manager) {
+            let candidates: Vec<_> = self.jre_candidates.iter().filter(|program| jvm_pointer_width(program) == Some(64)).cloned().collect();
+            select_jre_with(&candidates, java_major_version, &self.jre_preference)
+        } else {
+            select_jre_with(&self.jre_candidates, java_major_version, &self.jre_preference)
+        };
+        match selected.or_else(|| self.fallback_program_path()) {
+            Some(program) => Result::Ok(program),
+            None => Result::Err(versions::Error::NoJavaFound(java_major_version.unwrap_or(8)))
+        }
+    }
+
+    fn toolchain_program_path(&self) -> Option<String> {
+        // the file is read on every computation, so that editing it needs no new launcher
+        let requested = read_java_version_file(self.game_dir.as_path())?;
+        let candidates = if self.jre_candidates.is_empty() { find_jre() } else { self.jre_candidates.clone() };
+        let selected = select_jre_of_version(&candidates, &requested);
+        if selected.is_none() { warn!("No installed java matches version {} required by {}", requested, self.game_dir.display()) }
+        selected
+    }
+
+    fn fallback_program_path(&self) -> Option<String> {
+        let mapped = self.jre_mapping.iter().max_by_key(|&(major, _)| *major).map(|(_, program)| program.clone());
+        self.jre_candidates.first().cloned().or(mapped).or_else(|| find_jre().pop())
+    }
+
+    pub fn check_java(&self, version_id: &str) -> Result<String, versions::Error> {
+        let version = self.manager.version_of(version_id)?;
+        let required = version.java_major_version(&self.manager).unwrap_or(8);
+        let java_program_path = self.java_program_path(&version)?;
+        match java_version(&java_program_path).as_deref().and_then(java_major_version) {
+            Some(major) if major >= required => Result::Ok(java_program_path),
+            Some(major) => Result::Err(versions::Error::IncompatibleJava(java_program_path, major, required)),
+            None => Result::Err(versions::Error::NoJavaFound(required))
+        }
+    }
+
+    pub fn smoke_check(&self, version_id: &str) -> Result<(), versions::Error> {
+        let version = self.manager.version_of(version_id)?;
+        let java_program_path = self.java_program_path(&version)?;
+        // the jvm silently ignores missing classpath entries, so they are checked here instead
+        let mut entries = version.library_paths_with_classifiers(self.libraries_dir.as_path(), &self.library_classifiers, &self.manager)?;
+        entries.push(self.primary_jar(&version)?);
+        if let Some(missing) = entries.into_iter().find(|entry| !entry.is_file()) {
+            return Result::Err(versions::Error::FileUnavailableError(missing.into_boxed_path()));
+        }
+        self.write_classpath_jar(&version)?;
+        let mut command = Command::new(java_program_path);
+        if self.game_dir.is_dir() { command.current_dir(self.game_dir.as_path()); }
+        let output = command.arg("-cp").arg(self.classpath(&version)?).arg("-version").output()?;
+        if output.status.success() {
+            Result::Ok(())
+        } else {
+            let message = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            Result::Err(versions::Error::from(io::Error::other(message)))
+        }
+    }
+
+    pub fn copy_assets_to_resources(&self, version: &versions::MinecraftVersion) -> Result<Vec<path::PathBuf>, versions::Error> {
+        copy_resources(&self.resource_copies(version)?)
+    }
+
+    fn resource_copies(&self, version: &versions::MinecraftVersion) -> Result<Vec<(path::PathBuf, path::PathBuf)>, versions::Error> {
         let mut result = Vec::new();
-        for option in self.jvm_options.iter() {
-            match option {
-                &JvmOption(ref name) => {
-                    result.push(name.clone());
-                }
+        let asset_index = match self.asset_index_of(version) {
+            Some(ref asset_index) if asset_index.maps_to_resources(self.assets_dir.as_path())? => asset_index.clone(),
+            _ => return Result::Ok(result)
+        };
+        let resources_dir = self.game_dir.join("resources");
+        for (name, hash) in self.included_asset_objects(&asset_index)? {
+            let object_path = versions::AssetDownloadInfo::object_path(self.assets_objects_dir.as_path(), &hash);
+            result.push((object_path, resources_dir.join(name.as_str())));
+        }
+        Result::Ok(result)
+    }
+
+    pub fn write_classpath_jar(&self, version: &versions::MinecraftVersion) -> Result<Option<path::PathBuf>, versions::Error> {
+        match self.classpath_jar_of(version)? {
+            Some((jar_path, entries)) => {
+                versions::write_classpath_jar(jar_path.as_path(), &entries)?;
+                Result::Ok(Some(jar_path))
             }
+            None => Result::Ok(None)
         }
-        result.push(self.java_main_class.clone());
-        for option in self.game_options.iter() {
-            match option {
-                &GameOption(ref name, Some(ref arg)) => {
-                    result.push(name.clone());
-                    result.push(arg.clone());
-                }
-                &GameOption(ref name, None) => {
-                    result.push(name.clone());
-                }
+    }
+
+    fn classpath_jar_of(&self, version: &versions::MinecraftVersion) -> Result<Option<(path::PathBuf, Vec<path::PathBuf>)>, versions::Error> {
+        match self.classpath_jar {
+            Some(ref jar_path) => Result::Ok(Some((jar_path.clone(), self.classpath_jar_entries(version)?))),
+            None => Result::Ok(None)
+        }
+    }
+
+    fn classpath_jar_entries(&self, version: &versions::MinecraftVersion) -> Result<Vec<path::PathBuf>, versions::Error> {
+        // the manifest is read relative to the jar, so every entry is made absolute
+        let base_dir = env::current_dir()?;
+        Result::Ok(self.classpath_entries(version)?.iter().map(|path_buf| {
+            versions::absolute_path(path_buf.as_path(), base_dir.as_path())
+        }).collect())
+    }
+
+    pub fn classpath(&self, version: &versions::MinecraftVersion) -> Result<String, versions::Error> {
+        self.classpath_with(version, self.relative_classpath)
+    }
+
+    fn classpath_with(&self, version: &versions::MinecraftVersion, relative: bool) -> Result<String, versions::Error> {
+        let mut entries = match self.classpath_jar {
+            Some(ref path_buf) => vec![path_buf.clone()],
+            None => collapse_wildcard_dirs(self.classpath_entries(version)?, &self.classpath_wildcard_dirs)
+        };
+        if relative {
+            let base_dir = self.game_dir.as_path();
+            entries = entries.iter().map(|path_buf| versions::relative_path(path_buf, base_dir)).collect();
+        }
+        versions::join_classpath(entries, self.classpath_separator.as_str())
+    }
+
+    fn sandbox_command(&self, game_directory: &path::Path) -> Vec<String> {
+        match self.sandbox_command.first() {
+            Some(program) if !self.sandbox_available(program) => {
+                warn!("Sandbox {} is not available, launching without it", program);
+                Vec::new()
+            }
+            _ => {
+                let game_directory = game_directory.to_str().unwrap_or("").to_owned();
+                let strategy = parsing::ParameterStrategy::map(move |s| {
+                    if s == "game_directory" { game_directory.clone() } else { format!("${{{}}}", s) }
+                });
+                self.sandbox_command.iter().map(|arg| parsing::substitute(arg, &strategy)).collect()
             }
         }
-        result
     }
-}
 
-impl JvmOption {
-    pub fn new(arg: String) -> JvmOption {
-        JvmOption(arg)
+    fn sandbox_available(&self, program: &str) -> bool {
+        // the sandbox is probed only once, instead of on every launch
+        let mut cache = self.sandbox_available.lock().unwrap();
+        *cache.get_or_insert_with(|| Command::new(program).arg("--version").output().is_ok())
     }
-}
 
-impl GameOption {
-    pub fn new_pair(name: String, arg: String) -> GameOption {
-        GameOption(name, Some(arg))
+    pub fn launch(&self, version_id: &str) -> Result<GameProcess, versions::Error> {
+        self.to_arguments(version_id)?.start()
     }
 
-    pub fn new_single(name: String) -> GameOption {
-        GameOption(name, None)
+    pub fn import_bundle(&self, bundle: &path::Path, version_id: &str) -> Result<InstallReport, versions::Error> {
+        versions::extract_bundle(bundle, self.game_root_dir.as_path())?;
+        if let Some((_, path_buf)) = self.readiness(version_id)?.missing().first() {
+            return Result::Err(versions::Error::FileUnavailableError(path_buf.clone().into_boxed_path()));
+        }
+        self.install_report(version_id)
+    }
+
+    pub fn install_report(&self, version_id: &str) -> Result<InstallReport, versions::Error> {
+        let version = self.manager.version_of(version_id)?;
+        let client_download = version.client_download(&self.manager);
+        let mut files = vec![(self.primary_jar(&version)?, client_download.as_ref().and_then(|d| d.sha1()).map(String::from))];
+        for lib in version.libraries(&self.manager)? {
+            if let Some(path_buf) = lib.classpath_with_overrides(self.libraries_dir.as_path(), &self.library_classifiers) {
+                files.push((path_buf, lib.download_info_default().and_then(|d| d.sha1()).map(String::from)));
+            }
+        }
+        let mut entries = Vec::new();
+        for (path_buf, expected_sha1) in files {
+            if !path_buf.is_file() {
+                return Result::Err(versions::Error::FileUnavailableError(path_buf.into_boxed_path()));
+            }
+            let size = path_buf.metadata()?.len();
+            let sha1 = self.hasher.sha1(path_buf.as_path())?;
+            let verified = expected_sha1.map(|expected| expected == sha1);
+            entries.push(InstallReportEntry { path: path_buf, size, sha1, verified });
+        }
+        Result::Ok(InstallReport { entries })
+    }
+
+    pub fn install_version_dry_run(&self, version_id: &str) -> Result<Vec<PlannedDownload>, versions::Error> {
+        let version = self.manager.version_of(version_id)?;
+        let mut result = Vec::new();
+        let mut plan = |info: &versions::DownloadInfo, path_buf: path::PathBuf| if !path_buf.is_file() {
+            let sha1 = info.sha1().map(String::from);
+            result.push(PlannedDownload { url: info.url().to_owned(), path: path_buf, size: info.size(), sha1 });
+        };
+        if let Some(info) = version.client_download(&self.manager) {
+            plan(&info, self.primary_jar(&version)?);
+        }
+        for lib in version.libraries(&self.manager)? {
+            let path_buf = lib.classpath_with_overrides(self.libraries_dir.as_path(), &self.library_classifiers);
+            if let (Some(path_buf), Some(info)) = (path_buf, lib.download_info_default()) {
+                if !self.resolve_library_path(path_buf.as_path()).is_file() { plan(info, self.library_write_path(path_buf.as_path())) }
+            }
+        }
+        if let Some(asset_index) = self.asset_index_of(&version) {
+            let index_path = asset_index.index_path(self.assets_dir.as_path());
+            if index_path.is_file() {
+                let sizes = asset_index.object_sizes(self.assets_dir.as_path())?;
+                for (_, hash) in self.included_asset_objects(&asset_index)? {
+                    let url = versions::AssetDownloadInfo::object_url(versions::RESOURCES_HOST, &hash);
+                    let info = versions::DownloadInfo::PreHashed { size: sizes.get(&hash).map_or(-1, |&size| size as i32), url, sha1: hash.clone() };
+                    plan(&info, self.asset_object_path(&hash));
+                }
+            } else {
+                // the objects are only known once the index itself has been downloaded
+                plan(&versions::DownloadInfo::from(asset_index.clone()), index_path);
+            }
+        }
+        Result::Ok(result)
+    }
+
+    pub fn check_disk_space(&self, version_id: &str) -> Result<(), versions::Error> {
+        let required: u64 = self.install_version_dry_run(version_id)?.iter().filter_map(|d| d.size).sum();
+        // the root directory may not exist before the first install, so the free space of its volume is queried instead
+        let available = match self.game_root_dir.ancestors().find(|dir| dir.is_dir()).map(available_disk_space) {
+            Some(Result::Ok(available)) => available,
+            _ => return Result::Ok(())
+        };
+        if required > available { Result::Err(versions::Error::InsufficientDiskSpace(required, available)) } else { Result::Ok(()) }
+    }
+
+    pub fn install_version<P: FnMut(&InstallProgress)>(&self, version_id: &str, progress: P) -> Result<InstallProgress, versions::Error> {
+        self.install_version_cancellable(version_id, progress, &AtomicBool::new(false))
+    }
+
+    pub fn install_version_cancellable<P>(&self,
+                                          version_id: &str,
+                                          progress: P,
+                                          cancelled: &AtomicBool) -> Result<InstallProgress, versions::Error>
+        where P: FnMut(&InstallProgress) {
+        self.check_disk_space(version_id)?;
+        let fetch = |download: &PlannedDownload, on_bytes: &mut dyn FnMut(u64)| self.fetch_download(download, on_bytes, cancelled);
+        self.install_with(version_id, fetch, progress, cancelled)
+    }
+
+    #[cfg(feature = "progress-stream")]
+    pub fn into_install_stream(self, version_id: &str) -> ProgressStream {
+        let cancelled = AtomicBool::new(false);
+        self.install_stream(version_id, true, move |launcher, download, on_bytes| launcher.fetch_download(download, on_bytes, &cancelled))
+    }
+
+    #[cfg(feature = "progress-stream")]
+    pub fn into_install_stream_with<F>(self, version_id: &str, mut fetch: F) -> ProgressStream
+        where F: FnMut(&PlannedDownload, &mut dyn FnMut(u64)) -> Result<(), versions::Error> + Send + 'static {
+        self.install_stream(version_id, false, move |_, download, on_bytes| fetch(download, on_bytes))
+    }
+
+    #[cfg(feature = "progress-stream")]
+    fn install_stream<F>(self, version_id: &str, check_disk_space: bool, mut fetch: F) -> ProgressStream
+        where F: FnMut(&MinecraftLauncher, &PlannedDownload, &mut dyn FnMut(u64)) -> Result<(), versions::Error> + Send + 'static {
+        let (sender, receiver) = mpsc::unbounded();
+        let version_id = version_id.to_owned();
+        // downloads block, so they run on a thread of their own while the stream is polled by the frontend
+        thread::spawn(move || {
+            let progress_sender = sender.clone();
+            let checked = if check_disk_space { self.check_disk_space(&version_id) } else { Result::Ok(()) };
+            let result = checked.and_then(|_| self.install_version_with(&version_id, |download, on_bytes| {
+                fetch(&self, download, on_bytes)?;
+                // nobody observes the install once the stream is dropped, so it stops before the next file
+                let event = ProgressEvent::Downloaded(download.path.clone());
+                sender.unbounded_send(Result::Ok(event)).map_err(|_| versions::Error::Cancelled)
+            }, |progress| {
+                let _ = progress_sender.unbounded_send(Result::Ok(ProgressEvent::Progress(*progress)));
+            }));
+            if let Result::Err(e) = result { let _ = sender.unbounded_send(Result::Err(e)); }
+        });
+        ProgressStream(receiver)
+    }
+
+    fn fetch_download(&self,
+                      download: &PlannedDownload,
+                      on_bytes: &mut dyn FnMut(u64),
+                      cancelled: &AtomicBool) -> Result<(), versions::Error> {
+        let hasher = &self.hasher;
+        let result = ::requests::req_download_cancellable(download.url(), download.path(), on_bytes, |partial| match download.sha1 {
+            Some(ref expected) => {
+                let actual = hasher.sha1(partial).map_err(|e| io::Error::other(e.user_message()))?;
+                if actual.eq_ignore_ascii_case(expected) { return Result::Ok(()); }
+                let message = format!("{} has sha1 {} instead of {}", download.url, actual, expected);
+                Result::Err(::requests::Error::from(io::Error::other(message)))
+            }
+            None => Result::Ok(())
+        }, cancelled);
+        result.map(|_| ()).map_err(|e| match e {
+            ::requests::Error::Cancelled => versions::Error::Cancelled,
+            e => versions::Error::from(io::Error::other(e.to_string()))
+        })
+    }
+
+    pub fn install_version_with<F, P>(&self, version_id: &str, fetch: F, progress: P) -> Result<InstallProgress, versions::Error>
+        where F: FnMut(&PlannedDownload, &mut dyn FnMut(u64)) -> Result<(), versions::Error>, P: FnMut(&InstallProgress) {
+        self.install_with(version_id, fetch, progress, &AtomicBool::new(false))
+    }
+
+    fn install_with<F, P>(&self, version_id: &str, mut fetch: F, progress: P, cancelled: &AtomicBool) -> Result<InstallProgress, versions::Error>
+        where F: FnMut(&PlannedDownload, &mut dyn FnMut(u64)) -> Result<(), versions::Error>, P: FnMut(&InstallProgress) {
+        let mut fetched = Vec::new();
+        let result = self.install_into(version_id, &mut fetch, progress, cancelled, &mut fetched);
+        if let Result::Err(versions::Error::Cancelled) = result {
+            // a cancelled install is undone, so that no file of it is mistaken for a finished one
+            for path_buf in fetched.iter() { let _ = fs::remove_file(path_buf); }
+        }
+        result
+    }
+
+    fn install_into<F, P>(&self,
+                          version_id: &str,
+                          fetch: &mut F,
+                          mut progress: P,
+                          cancelled: &AtomicBool,
+                          fetched_paths: &mut Vec<path::PathBuf>) -> Result<InstallProgress, versions::Error>
+        where F: FnMut(&PlannedDownload, &mut dyn FnMut(u64)) -> Result<(), versions::Error>, P: FnMut(&InstallProgress) {
+        let mut state = InstallProgress::default();
+        let mut fetched = HashSet::new();
+        loop {
+            // asset objects are only planned once their index has been fetched, which grows the totals
+            let plan: Vec<_> = self.install_version_dry_run(version_id)?.into_iter().filter(|d| !fetched.contains(&d.path)).collect();
+            if plan.is_empty() { return Result::Ok(state); }
+            state.files_total += plan.len();
+            state.bytes_total += plan.iter().filter_map(|d| d.size).sum::<u64>();
+            progress(&state);
+            for download in plan {
+                if cancelled.load(Ordering::SeqCst) { return Result::Err(versions::Error::Cancelled); }
+                if let Some(parent) = download.path.parent() { fs::create_dir_all(parent)?; }
+                let mut received = 0;
+                fetch(&download, &mut |bytes| {
+                    received += bytes;
+                    state.bytes_done += bytes;
+                    progress(&state);
+                })?;
+                if download.size.is_none() { state.bytes_total += received; }
+                state.files_done += 1;
+                progress(&state);
+                fetched_paths.push(download.path.clone());
+                fetched.insert(download.path);
+            }
+        }
+    }
+
+    pub fn verify_installation(&self, version_id: &str, threads: usize) -> Result<Vec<path::PathBuf>, versions::Error> {
+        self.verify_installation_cancellable(version_id, threads, Arc::new(AtomicBool::new(false)))
+    }
+
+    pub fn verify_installation_cancellable(&self,
+                                           version_id: &str,
+                                           threads: usize,
+                                           cancelled: Arc<AtomicBool>) -> Result<Vec<path::PathBuf>, versions::Error> {
+        let version = self.manager.version_of(version_id)?;
+        let mut files = Vec::new();
+        if let Some(sha1) = version.client_download(&self.manager).as_ref().and_then(|d| d.sha1()) {
+            files.push((self.primary_jar(&version)?, sha1.to_owned()));
+        }
+        for lib in version.libraries(&self.manager)? {
+            let path_buf = lib.classpath_with_overrides(self.libraries_dir.as_path(), &self.library_classifiers);
+            if let (Some(path_buf), Some(sha1)) = (path_buf, lib.download_info_default().and_then(|d| d.sha1())) {
+                files.push((path_buf, sha1.to_owned()));
+            }
+        }
+        if let Some(asset_index) = self.asset_index_of(&version) {
+            if asset_index.index_path(self.assets_dir.as_path()).is_file() {
+                for (_, hash) in self.included_asset_objects(&asset_index)? {
+                    files.push((self.asset_object_path(&hash), hash));
+                }
+            }
+        }
+        versions::verify_sha1_parallel_cancellable(files, threads, self.hasher.clone(), cancelled)
+    }
+
+    pub fn readiness(&self, version_id: &str) -> Result<Readiness, versions::Error> {
+        let version = self.manager.version_of(version_id)?;
+        let mut items = vec![(ReadinessCategory::Jar, self.primary_jar(&version)?)];
+        for path_buf in version.library_paths_with_classifiers(self.libraries_dir.as_path(), &self.library_classifiers, &self.manager)? {
+            items.push((ReadinessCategory::Libraries, path_buf));
+        }
+        if let Some(asset_index) = self.asset_index_of(&version) {
+            let index_path = asset_index.index_path(self.assets_dir.as_path());
+            if index_path.exists() {
+                for (_, hash) in self.included_asset_objects(&asset_index)? {
+                    items.push((ReadinessCategory::Assets, self.asset_object_path(&hash)));
+                }
+            }
+            items.push((ReadinessCategory::Assets, index_path));
+        }
+        let natives = version.to_native_collection_with_classifiers(&self.manager, self.libraries_dir.as_path(), &self.library_classifiers)?;
+        for path in natives.paths() {
+            items.push((ReadinessCategory::Natives, path.to_path_buf()));
+        }
+        let total = items.len();
+        let missing = items.into_iter().filter(|item| !item.1.is_file()).collect();
+        Result::Ok(Readiness { total, missing })
+    }
+
+    pub fn unique_asset_objects(&self, version_id: &str) -> Result<Vec<String>, versions::Error> {
+        let object_hashes = |version: &versions::MinecraftVersion| -> Result<HashSet<String>, versions::Error> {
+            match self.asset_index_of(version) {
+                Some(ref asset_index) if asset_index.index_path(self.assets_dir.as_path()).is_file() => {
+                    Result::Ok(asset_index.object_hashes(self.assets_dir.as_path())?.into_iter().collect())
+                }
+                _ => Result::Ok(HashSet::new())
+            }
+        };
+        let mut result = object_hashes(&self.manager.version_of(version_id)?)?;
+        for id in self.manager.version_ids()? {
+            if id == version_id { continue; }
+            // versions which cannot be parsed are not launchable either, so they do not keep objects alive
+            if let Result::Ok(version) = self.manager.version_of(&id) {
+                let shared = object_hashes(&version)?;
+                result.retain(|hash| !shared.contains(hash));
+            }
+        }
+        let mut result: Vec<_> = result.into_iter().collect();
+        result.sort();
+        Result::Ok(result)
+    }
+
+    pub fn relaunch_changes(&self, version_id: &str, previous: &LaunchSnapshot) -> Result<Vec<SnapshotChange>, versions::Error> {
+        Result::Ok(self.to_arguments(version_id)?.capture_environment().changes_since(previous))
+    }
+
+    fn memory_jvm_options(&self) -> Vec<JvmOption> {
+        let mut jvm_options = vec![
+            JvmOption::new("-XX:+UseG1GC".to_owned(), OptionSource::Default),
+            JvmOption::new("-XX:-UseAdaptiveSizePolicy".to_owned(), OptionSource::Default),
+            JvmOption::new("-XX:-OmitStackTraceInFastThrow".to_owned(), OptionSource::Default),
+        ];
+        let (min_mib, max_mib) = self.min_max_memory_mib;
+        let max_mib = self.max_memory_percent.map_or(max_mib, |percent| self.max_memory_of_percent(percent));
+        if min_mib > 0f32 { jvm_options.push(JvmOption::new(format!("-Xmn{}m", min_mib), OptionSource::User)) }
+        if max_mib > 0f32 { jvm_options.push(JvmOption::new(format!("-Xmx{}m", max_mib), OptionSource::User)) }
+        if let Some(size_kib) = self.stack_size_kib {
+            jvm_options.push(JvmOption::new(format!("-Xss{}k", size_kib), OptionSource::User));
+        }
+        jvm_options
+    }
+
+    fn common_jvm_options(&self, version: &versions::MinecraftVersion, java_program_path: &str) -> Vec<JvmOption> {
+        let mut jvm_options = self.memory_jvm_options();
+        jvm_options.push(JvmOption::new("-Dfml.ignoreInvalidMinecraftCertificates=true".to_owned(), OptionSource::Default));
+        jvm_options.push(JvmOption::new("-Dfml.ignorePatchDiscrepancies=true".to_owned(), OptionSource::Default));
+        if let Some(ref encoding) = self.file_encoding {
+            // java before 18 defaults to the platform encoding, which garbles logs and configs
+            jvm_options.push(JvmOption::new(format!("-Dfile.encoding={}", encoding), OptionSource::Default));
+        }
+        let version_kind_options = self.version_kind_jvm_options.get(&version.version_kind());
+        if version.version_kind() == versions::VersionKind::Snapshot {
+            // snapshots may be compiled against preview features of the java they require
+            let java_major_version = version.java_major_version(&self.manager).unwrap_or(8);
+            for &(flag, since) in SNAPSHOT_JVM_FLAGS.iter() {
+                let is_configured = version_kind_options.is_some_and(|options| options.iter().any(|option| option == flag));
+                if java_major_version >= since && !is_configured {
+                    jvm_options.push(JvmOption::new(flag.to_owned(), OptionSource::Default));
+                }
+            }
+        }
+        if let Some(options) = version_kind_options {
+            jvm_options.extend(options.iter().map(|option| JvmOption::new(option.clone(), OptionSource::User)));
+        }
+        if let Some(ref host) = self.telemetry_host {
+            jvm_options.push(JvmOption::new(format!("-Dminecraft.api.telemetry.host={}", host), OptionSource::User));
+        }
+        match (&self.auth_server, &self.authlib_injector) {
+            (Some(api_root), Some(agent_jar)) => {
+                let agent = format!("-javaagent:{}={}", agent_jar.display(), api_root);
+                jvm_options.push(JvmOption::new(agent, OptionSource::User));
+            }
+            // the agent may be loaded by other means, such as an extra jvm option
+            (Some(api_root), None) => {
+                jvm_options.push(JvmOption::new(format!("-Dauthlibinjector.yggdrasil={}", api_root), OptionSource::User));
+            }
+            (None, Some(agent_jar)) => warn!("No auth server specified for {}, skipping it", agent_jar.display()),
+            (None, None) => ()
+        }
+        if let (Some(_), Some(metadata)) = (&self.auth_server, &self.auth_server_metadata) {
+            // saves authlib-injector from fetching the metadata again on every launch
+            let prefetched = format!("-Dauthlibinjector.yggdrasil.prefetched={}", BASE64.encode(metadata.as_bytes()));
+            jvm_options.push(JvmOption::new(prefetched, OptionSource::User));
+        }
+        if let Some(port) = self.debug_port {
+            let suspend = if self.debug_suspend { "y" } else { "n" };
+            let agent = format!("-agentlib:jdwp=transport=dt_socket,server=y,suspend={},address={}", suspend, port);
+            jvm_options.push(JvmOption::new(agent, OptionSource::User));
+        }
+        self.finish_jvm_options(jvm_options, java_program_path)
+    }
+
+    fn finish_jvm_options(&self, mut jvm_options: Vec<JvmOption>, java_program_path: &str) -> Vec<JvmOption> {
+        if self.exit_on_out_of_memory {
+            // a game which runs out of memory may hang forever instead of crashing
+            jvm_options.push(JvmOption::new("-XX:+ExitOnOutOfMemoryError".to_owned(), OptionSource::User));
+        }
+        for (arg, reason) in problematic_jvm_args(&self.extra_jvm_args) {
+            warn!("Problematic jvm option {}: {}", arg, reason);
+        }
+        for arg in self.extra_jvm_args.iter() {
+            merge_jvm_option(&mut jvm_options, JvmOption::new(arg.clone(), OptionSource::User));
+        }
+        if self.probe_gc_flags {
+            // collectors such as zgc or shenandoah are not available on java 8, the defaults are kept instead
+            jvm_options.retain(|option| {
+                option.1 != OptionSource::User || !is_gc_selection_flag(&option.0) || self.supports_jvm_flag(java_program_path, &option.0)
+            });
+        }
+        if jvm_options.iter().any(|option| option.1 != OptionSource::Default && is_gc_selection_flag(&option.0)) {
+            jvm_options.retain(|option| option.1 != OptionSource::Default || !is_gc_selection_flag(&option.0));
+        }
+        for (first, second) in conflicting_jvm_options(&jvm_options) {
+            warn!("Conflicting jvm options {} and {}, the jvm may refuse to start", first, second);
+        }
+        jvm_options
+    }
+
+    fn supports_jvm_flag(&self, java_program_path: &str, flag: &str) -> bool {
+        // each runtime is probed only once per flag, instead of on every launch
+        let key = (java_program_path.to_owned(), flag.to_owned());
+        let mut cache = self.gc_flag_support.lock().unwrap();
+        *cache.entry(key).or_insert_with(|| java_supports_flag(java_program_path, flag))
+    }
+
+    pub fn to_server_arguments(&self, version_id: &str, server_jar: &path::Path) -> Result<LaunchArguments, versions::Error> {
+        self.check_stack_size()?;
+        self.check_memory_percent()?;
+        let minecraft_version = self.manager.version_of(version_id)?;
+        let java_program_path = self.java_program_path(&minecraft_version)?;
+        let server_jar = fs::canonicalize(server_jar)?;
+        let java_main_class = versions::jar_main_class(server_jar.as_path())?
+            .ok_or_else(|| versions::Error::NoMainClass(version_id.to_owned()))?;
+        // servers neither authenticate players through the launcher nor load client mods, so only memory and gc are configured
+        let mut jvm_options = self.finish_jvm_options(self.memory_jvm_options(), &java_program_path);
+        jvm_options.push(JvmOption::new("-cp".to_owned(), OptionSource::Default));
+        jvm_options.push(JvmOption::new(server_jar.to_str().unwrap_or("").to_owned(), OptionSource::Default));
+        Result::Ok(LaunchArguments {
+            access_token_provider: None,
+            crash_reports_dir: self.crash_reports_dir.clone(),
+            create_game_dirs: self.create_game_dirs,
+            verify_natives: self.verify_natives,
+            argfile: None,
+            keep_argfile: false,
+            classpath_jar: None,
+            secrets: Vec::new(),
+            consumed_placeholders: Vec::new(),
+            sandbox_command: self.sandbox_command(self.game_dir.as_path()),
+            game_natives: versions::NativeCollection::default(),
+            game_native_path: self.natives_directory(minecraft_version.id()),
+            game_options: vec![GameOption::new_single("nogui".to_owned(), OptionSource::Default)],
+            jvm_options,
+            java_main_class,
+            java_program_path,
+            game_directory: self.game_dir.clone(),
+        })
+    }
+
+    fn argfile_path(&self, version_id: &str) -> Option<path::PathBuf> {
+        match self.argfile {
+            Some(Some(ref path_buf)) => Some(self.game_dir.join(path_buf)),
+            Some(None) => Some(default_argfile_path(self.game_dir.as_path(), version_id)),
+            None => None
+        }
+    }
+
+    fn shorten_command_line(&self,
+                            version: &versions::MinecraftVersion,
+                            arguments: &mut LaunchArguments,
+                            limit: usize) -> Result<(), versions::Error> {
+        if arguments.command_line_length() <= limit { return Result::Ok(()); }
+        // the game is started in its directory, so entries inside it may be relative while the others stay absolute
+        let (classpath, relative_classpath) = (self.classpath(version)?, self.classpath_with(version, true)?);
+        for option in arguments.jvm_options.iter_mut().filter(|option| option.0 == classpath) {
+            option.0 = relative_classpath.clone();
+        }
+        if arguments.command_line_length() > limit && arguments.argfile.is_none() {
+            if self.supports_argfiles(version) {
+                arguments.argfile = Some(default_argfile_path(self.game_dir.as_path(), version.id()));
+            } else {
+                self.move_classpath_into_jar(version, arguments, relative_classpath.as_str())?;
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn move_classpath_into_jar(&self,
+                               version: &versions::MinecraftVersion,
+                               arguments: &mut LaunchArguments,
+                               classpath: &str) -> Result<(), versions::Error> {
+        // java 8 does not read @argfiles, so the classpath is moved into the manifest of a jar instead
+        if arguments.classpath_jar.is_some() { return Result::Ok(()); }
+        let jar_path = default_classpath_jar_path(self.game_dir.as_path(), version.id());
+        let base_dir = env::current_dir()?;
+        let entries = self.classpath_jar_entries(version)?;
+        let jar = versions::absolute_path(jar_path.as_path(), base_dir.as_path()).to_str().unwrap_or("").to_owned();
+        for option in arguments.jvm_options.iter_mut().filter(|option| option.0 == classpath) {
+            option.0 = jar.clone();
+        }
+        arguments.classpath_jar = Some((jar_path, entries));
+        Result::Ok(())
+    }
+
+    fn supports_argfiles(&self, version: &versions::MinecraftVersion) -> bool {
+        // @argfiles are read since java 9, versions which do not require a newer one may run on java 8
+        version.java_major_version(&self.manager).unwrap_or(8) >= 9
+    }
+
+    fn max_memory_of_percent(&self, percent: f32) -> f32 {
+        match self.total_memory_mib.or_else(total_memory_mib) {
+            Some(total) => (total * percent / 100f32).floor(),
+            None => {
+                warn!("Cannot determine the total memory, using {} MiB instead of {}%", FALLBACK_MAX_MEMORY_MIB, percent);
+                FALLBACK_MAX_MEMORY_MIB
+            }
+        }
+    }
+
+    pub fn check_memory_percent(&self) -> Result<(), versions::Error> {
+        match self.max_memory_percent {
+            Some(percent) if !(percent > 0f32 && percent <= 100f32) => Result::Err(versions::Error::InvalidMemoryPercentage(percent)),
+            _ => Result::Ok(())
+        }
+    }
+
+    pub fn check_stack_size(&self) -> Result<(), versions::Error> {
+        match self.stack_size_kib {
+            // the jvm refuses to start below its minimum of about 160k, and caps thread stacks at 1g
+            Some(size_kib) if !(MIN_STACK_SIZE_KIB..=MAX_STACK_SIZE_KIB).contains(&size_kib) => {
+                Result::Err(versions::Error::InvalidStackSize(size_kib))
+            }
+            _ => Result::Ok(())
+        }
+    }
+
+    pub fn to_arguments(&self, version_id: &str) -> Result<LaunchArguments, versions::Error> {
+        let _cache_scope = self.manager.cache_scope();
+        let launch_arguments = self.compute_arguments(version_id)?;
+        let minecraft_version = self.manager.version_of(version_id)?;
+        if self.map_to_resources {
+            // clients before 1.6 read the assets from the resources directory of the game
+            self.copy_assets_to_resources(&minecraft_version)?;
+        }
+        Result::Ok(launch_arguments)
+    }
+
+    pub fn launch_plan(&self, version_id: &str) -> Result<LaunchPlan, versions::Error> {
+        let _cache_scope = self.manager.cache_scope();
+        // the token is kept as a placeholder, so that the plan can be stored without credentials
+        let arguments = self.compute_arguments_with(version_id, true)?;
+        let minecraft_version = self.manager.version_of(version_id)?;
+        let jvm_args: Vec<String> = arguments.jvm_options.iter().flat_map(JvmOption::to_args).collect();
+        let (classpath_jar, classpath) = match arguments.classpath_jar {
+            Some((ref jar_path, ref entries)) => (Some(jar_path.clone()), entries.clone()),
+            None => (None, collapse_wildcard_dirs(self.classpath_entries(&minecraft_version)?, &self.classpath_wildcard_dirs))
+        };
+        let resources = if self.map_to_resources { self.resource_copies(&minecraft_version)? } else { Vec::new() };
+        let environment = env::vars().filter(|(key, _)| !is_secret_env_key(key)).collect();
+        let mut sandbox_command = arguments.sandbox_command.clone();
+        if !sandbox_command.is_empty() { sandbox_command.push(arguments.java_program_path.clone()); }
+        Result::Ok(LaunchPlan {
+            java_version: java_version(&arguments.java_program_path),
+            java_program_path: arguments.java_program_path.clone(),
+            classpath: classpath.iter().map(|path_buf| path_buf.to_str().unwrap_or("").to_owned()).collect(),
+            classpath_jar,
+            resources,
+            sandbox_command,
+            jvm_args,
+            main_class: arguments.java_main_class.clone(),
+            game_args: arguments.game_options.iter().flat_map(GameOption::to_args).collect(),
+            natives_directory: arguments.game_native_path.clone(),
+            natives: arguments.game_natives.libraries(),
+            strip_native_versions: arguments.game_natives.strips_versions(),
+            environment,
+            working_directory: arguments.game_directory.clone(),
+        })
+    }
+
+    pub fn compute_arguments(&self, version_id: &str) -> Result<LaunchArguments, versions::Error> {
+        self.compute_arguments_with(version_id, self.access_token_provider.is_some())
+    }
+
+    fn compute_arguments_with(&self, version_id: &str, defer_access_token: bool) -> Result<LaunchArguments, versions::Error> {
+        // every version of the inheritance chain is read only once during a launch
+        let _cache_scope = self.manager.cache_scope();
+        // nothing is written to disk here, natives are extracted when the game is started
+        self.check_stack_size()?;
+        self.check_memory_percent()?;
+        let game_directory = self.game_dir.clone();
+        let minecraft_version = self.manager.version_of(version_id)?;
+        let java_program_path = self.java_program_path(&minecraft_version)?;
+        if let Some(asset_index) = self.asset_index_of(&minecraft_version) {
+            if asset_index.index_path(self.assets_dir.as_path()).exists() {
+                asset_index.verify_index(self.assets_dir.as_path())?;
+            }
+        }
+        let java_main_class = self.main_class(&minecraft_version)?;
+        if let Result::Ok(Some(embedded_id)) = self.embedded_version_mismatch(&minecraft_version) {
+            warn!("The jar of {} is actually version {}, the game may crash", minecraft_version.id(), embedded_id);
+        }
+        let game_natives = match self.natives_classifier {
+            Some(ref classifier) => {
+                let libraries_dir = self.libraries_dir.as_path();
+                minecraft_version.to_native_collection_with_classifier(&self.manager, libraries_dir, classifier)?
+            }
+            None => {
+                let libraries_dir = self.libraries_dir.as_path();
+                minecraft_version.to_native_collection_with_classifiers(&self.manager, libraries_dir, &self.library_classifiers)?
+            }
+        }.strip_versions(self.strip_native_versions);
+        let mut jvm_options = self.common_jvm_options(&minecraft_version, &java_program_path);
+        let missing_placeholders = self.missing_placeholders(&minecraft_version)?;
+        if !missing_placeholders.is_empty() {
+            return Result::Err(versions::Error::MissingPlaceholders(missing_placeholders));
+        }
+        let mut game_options = Vec::new();
+        let map = self.argument_map_with(&minecraft_version, defer_access_token);
+        let game_native_path = path::PathBuf::from(map.get("natives_directory").unwrap());
+        let mut jvm_map = map.clone();
+        // the jvm runs inside the game directory, but some of them (e.g. forge) require absolute paths
+        let base_dir = env::current_dir()?.join(game_directory.as_path());
+        for key in ["natives_directory", "library_directory", "game_directory", "assets_root", "primary_jar"].iter() {
+            if let Some(value) = jvm_map.get_mut(*key) {
+                let path_buf = versions::absolute_path(path::Path::new(value.as_str()), base_dir.as_path());
+                *value = path_buf.to_str().unwrap_or("").to_owned();
+            }
+        }
+        let consumed = Rc::new(RefCell::new(BTreeSet::new()));
+        let (game_consumed, jvm_consumed) = (consumed.clone(), consumed.clone());
+        let strategy = parsing::ParameterStrategy::map(move |s| {
+            game_consumed.borrow_mut().insert(s.clone());
+            let result = match map.get(&s) {
+                Some(ref string) => (*string).clone(),
+                None => String::new()
+            };
+            result
+        });
+        let jvm_strategy = parsing::ParameterStrategy::map(move |s| {
+            let result = jvm_map.get(&s).cloned().unwrap_or_default();
+            jvm_consumed.borrow_mut().insert(s);
+            result
+        });
+        minecraft_version.collect_game_arguments(&self.manager, &mut game_options, &strategy)?;
+        // old clients abort on options they do not know, so these are only passed if the template asks for their values
+        let placeholders = minecraft_version.required_placeholders(&self.manager)?;
+        game_options.retain(|option| MODERN_GAME_OPTIONS.iter().all(|&(name, key)| option.0 != name || placeholders.contains(key)));
+        let java_main_class = match self.entrypoint_wrapper {
+            Some((_, ref wrapper_main_class)) => {
+                // the wrapper receives the real main class followed by the game arguments
+                game_options.insert(0, GameOption::new_single(java_main_class, OptionSource::User));
+                wrapper_main_class.clone()
+            }
+            None => java_main_class
+        };
+        minecraft_version.collect_jvm_arguments(&self.manager, &mut jvm_options, &jvm_strategy)?;
+        let (ref launcher_name, ref launcher_version) = self.launcher_name_version;
+        for &(key, value) in [("-Dminecraft.launcher.brand=", launcher_name), ("-Dminecraft.launcher.version=", launcher_version)].iter() {
+            // some loader profiles replace the jvm arguments without keeping the brand properties
+            if !jvm_options.iter().any(|option| option.0.starts_with(key)) {
+                jvm_options.push(JvmOption::new(format!("{}{}", key, value), OptionSource::Default));
+            }
+        }
+        move_jvm_options_before_main_class(&mut jvm_options, &self.main_class_adjacent_jvm_args);
+        if self.disable_chat && minecraft_version.accepts_named_options(&self.manager)? {
+            // honored since 1.16.4, older clients simply ignore unrecognized options
+            game_options.push(GameOption::new_single("--disableChat".to_owned(), OptionSource::User));
+        }
+        let mut secrets = if defer_access_token { Vec::new() } else { vec![self.auth_info().access_token().to_owned()] };
+        if let Some((ref host, port)) = self.proxy {
+            // positional templates predate the proxy options which were added along with named ones in 1.6
+            if minecraft_version.accepts_named_options(&self.manager)? {
+                game_options.push(GameOption::new_pair("--proxyHost".to_owned(), host.clone(), OptionSource::User));
+                game_options.push(GameOption::new_pair("--proxyPort".to_owned(), port.to_string(), OptionSource::User));
+                if let Some((ref user, ref password)) = self.proxy_credentials {
+                    game_options.push(GameOption::new_pair("--proxyUser".to_owned(), user.clone(), OptionSource::User));
+                    game_options.push(GameOption::new_pair("--proxyPass".to_owned(), password.clone(), OptionSource::User));
+                    secrets.push(password.clone());
+                }
+            }
+        }
+        let sandbox_command = self.sandbox_command(game_directory.as_path());
+        let mut arguments = LaunchArguments {
+            access_token_provider: self.access_token_provider.clone(),
+            crash_reports_dir: self.crash_reports_dir.clone(),
+            create_game_dirs: self.create_game_dirs,
+            verify_natives: self.verify_natives,
+            argfile: self.argfile_path(minecraft_version.id()),
+            keep_argfile: self.keep_argfile,
+            // the jar is only written when the game is started
+            classpath_jar: self.classpath_jar_of(&minecraft_version)?,
+            consumed_placeholders: consumed.borrow().iter().cloned().collect(),
+            secrets,
+            sandbox_command,
+            game_natives,
+            game_native_path,
+            game_options,
+            jvm_options,
+            java_main_class,
+            java_program_path,
+            game_directory,
+        };
+        if arguments.argfile.is_some() && !self.supports_argfiles(&minecraft_version) {
+            arguments.argfile = None;
+            let classpath = self.classpath(&minecraft_version)?;
+            self.move_classpath_into_jar(&minecraft_version, &mut arguments, classpath.as_str())?;
+        }
+        if let Some(limit) = self.command_line_limit {
+            self.shorten_command_line(&minecraft_version, &mut arguments, limit)?;
+        }
+        Result::Ok(arguments)
+    }
+}
+
+impl fmt::Debug for SecretProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretProvider")
+    }
+}
+
+impl LaunchArguments {
+    pub fn start(&self) -> Result<GameProcess, versions::Error> {
+        self.prepare_start()?;
+        self.spawn_new_process()
+    }
+
+    fn prepare_start(&self) -> Result<(), versions::Error> {
+        self.game_natives.check_conflicts()?;
+        check_vc_redist()?;
+        check_windows_jvm_arch(&self.java_program_path)?;
+        self.create_game_directories()?;
+        self.write_classpath_jar()?;
+        self.extract_natives()?;
+        if self.verify_natives {
+            let invalid_natives = self.invalid_natives()?;
+            if !invalid_natives.is_empty() { return Result::Err(versions::Error::InvalidNatives(invalid_natives)); }
+        }
+        self.link_crash_reports_dir()
+    }
+
+    pub fn invalid_natives(&self) -> Result<Vec<path::PathBuf>, versions::Error> {
+        // catches corrupt downloads or natives of another platform before the jvm fails to load them
+        if !self.game_native_path.is_dir() { return Result::Ok(Vec::new()); }
+        versions::invalid_natives(self.game_native_path.as_path())
+    }
+
+    pub fn create_game_directories(&self) -> Result<(), versions::Error> {
+        if !self.create_game_dirs { return Result::Ok(()); }
+        // the game fails to write saves or resource packs into directories which are missing
+        for sub_dir in ["", "saves", "resourcepacks"].iter() {
+            fs::create_dir_all(self.game_directory.join(sub_dir))?;
+        }
+        Result::Ok(())
+    }
+
+    pub fn is_game_dir_case_insensitive(&self) -> Result<bool, versions::Error> {
+        versions::is_case_insensitive(self.game_directory.as_path())
+    }
+
+    pub fn crash_reports_dir(&self) -> path::PathBuf {
+        self.crash_reports_dir.clone().unwrap_or_else(|| self.game_directory.join("crash-reports"))
+    }
+
+    pub fn latest_crash_report(&self) -> Option<path::PathBuf> {
+        let entries = fs::read_dir(self.crash_reports_dir()).into_iter().flatten().flatten();
+        let reports = entries.filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())));
+        reports.filter(|report| report.1.is_file()).max().map(|report| report.1)
+    }
+
+    pub fn link_crash_reports_dir(&self) -> Result<(), versions::Error> {
+        // the game always writes into crash-reports under its directory, so redirect it with a link
+        let target = match self.crash_reports_dir {
+            Some(ref target) => target.clone(),
+            None => return Result::Ok(())
+        };
+        let link = self.game_directory.join("crash-reports");
+        match fs::symlink_metadata(link.as_path()) {
+            Result::Ok(ref metadata) if metadata.file_type().is_symlink() => fs::remove_file(link.as_path())?,
+            Result::Ok(_) => {
+                warn!("Keeping existing {}, crash reports will not be redirected", link.display());
+                return Result::Ok(());
+            }
+            Result::Err(_) => ()
+        }
+        fs::create_dir_all(target.as_path())?;
+        let target = versions::absolute_path(target.as_path(), env::current_dir()?.as_path());
+        #[cfg(unix)]
+        ::std::os::unix::fs::symlink(target, link)?;
+        #[cfg(windows)]
+        ::std::os::windows::fs::symlink_dir(target, link)?;
+        Result::Ok(())
+    }
+
+    pub fn mod_report(&self) -> ModReport {
+        let entries = fs::read_dir(self.game_directory.join("mods")).into_iter().flatten().flatten();
+        let mut mod_files: Vec<String> = entries.map(|entry| entry.path()).filter(|path_buf| {
+            path_buf.is_file() && path_buf.extension().and_then(|ext| ext.to_str()) == Some("jar")
+        }).filter_map(|path_buf| path_buf.file_name().and_then(|name| name.to_str()).map(String::from)).collect();
+        mod_files.sort();
+        let log = fs::read_to_string(self.game_directory.join("logs/latest.log")).unwrap_or_default();
+        ModReport { mod_files, loaded_mod_ids: loaded_mod_ids(&log) }
+    }
+
+    pub fn start_with_log_file(&self, path: &path::Path) -> Result<GameProcess, versions::Error> {
+        self.start_with_rotating_log(path, LOG_ROTATION_BYTES, LOG_ROTATION_KEPT)
+    }
+
+    pub fn start_with_rotating_log(&self, path: &path::Path, max_bytes: u64, kept: u32) -> Result<GameProcess, versions::Error> {
+        self.prepare_start()?;
+        let log = Arc::new(Mutex::new(RotatingLog::open(self.game_directory.join(path), max_bytes, kept)?));
+        let mut child = self.spawn_new_process_with(|program| {
+            let mut command = Command::new(program);
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+            command
+        })?;
+        let stdout = child.stdout.take().map(|stdout| Box::new(stdout) as Box<dyn io::Read + Send>);
+        let stderr = child.stderr.take().map(|stderr| Box::new(stderr) as Box<dyn io::Read + Send>);
+        for output in stdout.into_iter().chain(stderr) {
+            let log = log.clone();
+            thread::spawn(move || {
+                let mut reader = io::BufReader::new(output);
+                let mut line = Vec::new();
+                while let Result::Ok(n) = io::BufRead::read_until(&mut reader, b'\n', &mut line) {
+                    if n == 0 || log.lock().map(|mut log| log.append(&line)).is_err() { break; }
+                    line.clear();
+                }
+            });
+        }
+        Result::Ok(child)
+    }
+
+    pub fn start_with_pid(&self) -> Result<(GameProcess, u32), versions::Error> {
+        let child = self.start()?;
+        let pid = child.id();
+        Result::Ok((child, pid))
+    }
+
+    pub fn start_and_wait(&self) -> Result<ExitStatus, versions::Error> {
+        // the argfile is removed along with the process handle
+        let status = self.start()?.wait()?;
+        Result::Ok(status)
+    }
+
+    pub fn start_supervised(&self, policy: &RestartPolicy) -> Result<SupervisedExit, versions::Error> {
+        let mut restarts = 0;
+        let mut immediate_crashes = 0;
+        let mut backoff = policy.backoff.min(policy.max_backoff);
+        loop {
+            let started = Instant::now();
+            let status = self.start_and_wait()?;
+            if status.success() || restarts >= policy.max_restarts {
+                return Result::Ok(SupervisedExit { status, restarts });
+            }
+            if started.elapsed() < policy.min_uptime {
+                immediate_crashes += 1;
+                if immediate_crashes >= policy.max_immediate_crashes {
+                    return Result::Ok(SupervisedExit { status, restarts });
+                }
+            } else {
+                immediate_crashes = 0;
+            }
+            info!("Game exited abnormally ({}), restarting in {:?}", status, backoff);
+            thread::sleep(backoff);
+            backoff = policy.next_backoff(backoff);
+            restarts += 1;
+        }
+    }
+
+    pub fn spawn_new_process(&self) -> Result<GameProcess, versions::Error> {
+        self.spawn_new_process_with(Command::new)
+    }
+
+    pub fn spawn_new_process_with<F: FnOnce(String) -> Command>(&self, command_factory: F) -> Result<GameProcess, versions::Error> {
+        let mut command = command_factory(self.program());
+        command.current_dir(self.game_directory.as_path());
+        match self.access_token_provider {
+            Some(SecretProvider(ref provider)) => {
+                let access_token = provider();
+                let args: Vec<String> = self.args().iter().map(|arg| {
+                    arg.replace(ACCESS_TOKEN_PLACEHOLDER, access_token.as_str())
+                }).collect();
+                command.args(self.write_argfile(args)?);
+            }
+            None => {
+                command.args(self.write_argfile(self.args())?);
+            }
+        }
+        let child = command.spawn()?;
+        let argfile = if self.keep_argfile { None } else { self.argfile.clone() };
+        Result::Ok(GameProcess { child: Some(child), argfile })
+    }
+
+    fn write_argfile(&self, args: Vec<String>) -> Result<Vec<String>, versions::Error> {
+        let path_buf = match self.argfile {
+            Some(ref path_buf) => path_buf,
+            None => return Result::Ok(args)
+        };
+        // only jvm options and the main class are moved, so that no credential of the game options is written to disk
+        let start = self.sandbox_command.len();
+        let end = start + self.jvm_options.len() + 1;
+        let content: String = args[start..end].iter().map(|arg| {
+            format!("\"{}\"\n", arg.replace('\\', "\\\\").replace('"', "\\\""))
+        }).collect();
+        if let Some(parent) = path_buf.parent() { fs::create_dir_all(parent)?; }
+        fs::write(path_buf.as_path(), content)?;
+        let mut result = args[..start].to_vec();
+        result.push(format!("@{}", path_buf.display()));
+        result.extend(args[end..].iter().cloned());
+        Result::Ok(result)
+    }
+
+    pub fn argfile(&self) -> Option<&path::Path> {
+        self.argfile.as_deref()
+    }
+
+    pub fn classpath_jar(&self) -> Option<&path::Path> {
+        self.classpath_jar.as_ref().map(|(jar_path, _)| jar_path.as_path())
+    }
+
+    fn write_classpath_jar(&self) -> Result<(), versions::Error> {
+        match self.classpath_jar {
+            Some((ref jar_path, ref entries)) => versions::write_classpath_jar(jar_path.as_path(), entries),
+            None => Result::Ok(())
+        }
+    }
+
+    pub fn consumed_placeholders(&self) -> &[String] {
+        &self.consumed_placeholders
+    }
+
+    pub fn remove_argfile(&self) -> Result<(), versions::Error> {
+        match self.argfile {
+            // the jvm only reads the file on startup, but it cannot be removed before the game exits on windows
+            Some(ref path_buf) if !self.keep_argfile && path_buf.is_file() => Result::Ok(fs::remove_file(path_buf)?),
+            _ => Result::Ok(())
+        }
+    }
+
+    pub fn needs_natives_extraction(&self) -> bool {
+        !self.game_natives.is_empty()
+    }
+
+    pub fn extract_natives(&self) -> Result<Vec<String>, versions::Error> {
+        if !self.needs_natives_extraction() { return Result::Ok(Vec::new()); }
+        self.game_natives.extract_to(self.game_native_path.as_path())
+    }
+
+    pub fn extract_natives_cancellable(&self, cancelled: &AtomicBool) -> Result<Vec<String>, versions::Error> {
+        if !self.needs_natives_extraction() { return Result::Ok(Vec::new()); }
+        self.game_natives.extract_to_cancellable(self.game_native_path.as_path(), cancelled)
+    }
+
+    pub fn natives_cleanup_plan(&self) -> Vec<path::PathBuf> {
+        let mut result = Vec::new();
+        let mut dirs = vec![self.game_native_path.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir.as_path()).into_iter().flatten().flatten() {
+                let path_buf = entry.path();
+                if path_buf.is_dir() { dirs.push(path_buf) } else { result.push(path_buf) }
+            }
+        }
+        result.sort();
+        result
+    }
+
+    pub fn cleanup_natives(&self) -> Result<Vec<path::PathBuf>, versions::Error> {
+        let plan = self.natives_cleanup_plan();
+        for path_buf in plan.iter() {
+            fs::remove_file(path_buf.as_path())?;
+        }
+        Result::Ok(plan)
+    }
+
+    pub fn capture_environment(&self) -> LaunchSnapshot {
+        let redact = |string: &str| self.secrets.iter().fold(string.to_owned(), |s, secret| {
+            s.replace(secret.as_str(), "<redacted>")
+        });
+        let environment = env::vars().map(|(key, value)| {
+            let value = if is_secret_env_key(&key) { "<redacted>".to_owned() } else { redact(&value) };
+            (key, value)
+        }).collect();
+        LaunchSnapshot {
+            os: env::consts::OS.to_owned(),
+            arch: env::consts::ARCH.to_owned(),
+            java_program_path: self.java_program_path.clone(),
+            java_version: java_version(&self.java_program_path),
+            working_directory: self.game_directory.clone(),
+            args: self.args().iter().map(|arg| redact(arg)).collect(),
+            environment,
+        }
+    }
+
+    pub fn command_line_length(&self) -> usize {
+        // every argument is separated by a space, and those containing whitespace are quoted
+        let quoted_length = |arg: &str| arg.chars().count() + if arg.contains(char::is_whitespace) { 2 } else { 0 };
+        self.args().iter().fold(quoted_length(&self.program()), |length, arg| length + 1 + quoted_length(arg))
+    }
+
+    pub fn program(&self) -> String {
+        self.sandbox_command.first().cloned().unwrap_or_else(|| self.java_program_path.clone())
+    }
+
+    pub fn args_by_source(&self) -> HashMap<OptionSource, Vec<String>> {
+        let mut result: HashMap<OptionSource, Vec<String>> = HashMap::new();
+        for option in self.jvm_options.iter() {
+            result.entry(option.source()).or_default().extend(option.to_args());
+        }
+        for option in self.game_options.iter() {
+            result.entry(option.source()).or_default().extend(option.to_args());
+        }
+        result
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        let mut result = Vec::new();
+        if !self.sandbox_command.is_empty() {
+            result.extend(self.sandbox_command[1..].iter().cloned());
+            result.push(self.java_program_path.clone());
+        }
+        for option in self.jvm_options.iter() {
+            match option {
+                &JvmOption(ref name, _) => {
+                    result.push(name.clone());
+                }
+            }
+        }
+        result.push(self.java_main_class.clone());
+        for option in self.game_options.iter() {
+            match option {
+                &GameOption(ref name, Some(ref arg), _) => {
+                    result.push(name.clone());
+                    result.push(arg.clone());
+                }
+                &GameOption(ref name, None, _) => {
+                    result.push(name.clone());
+                }
+            }
+        }
+        result
+    }
+}
+
+impl LaunchPlan {
+    pub fn java_program_path(&self) -> &str {
+        &self.java_program_path
+    }
+
+    pub fn java_version(&self) -> Option<&str> {
+        self.java_version.as_deref()
+    }
+
+    pub fn classpath(&self) -> &Vec<String> {
+        &self.classpath
+    }
+
+    pub fn jvm_args(&self) -> &Vec<String> {
+        &self.jvm_args
+    }
+
+    pub fn main_class(&self) -> &str {
+        &self.main_class
+    }
+
+    pub fn game_args(&self) -> &Vec<String> {
+        &self.game_args
+    }
+
+    pub fn natives_directory(&self) -> &path::Path {
+        &self.natives_directory
+    }
+
+    pub fn environment(&self) -> &BTreeMap<String, String> {
+        &self.environment
+    }
+
+    pub fn working_directory(&self) -> &path::Path {
+        &self.working_directory
+    }
+
+    pub fn program(&self) -> String {
+        self.sandbox_command.first().cloned().unwrap_or_else(|| self.java_program_path.clone())
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        let prefix = self.sandbox_command.iter().skip(1).cloned();
+        let main_class = Some(self.main_class.clone());
+        prefix.chain(self.jvm_args.iter().cloned()).chain(main_class).chain(self.game_args.iter().cloned()).collect()
+    }
+
+    pub fn execute<F: FnOnce() -> String>(&self, access_token: F) -> Result<Child, versions::Error> {
+        // the plan may be executed long after being resolved, so nothing is written to disk before execution
+        let natives = versions::NativeCollection::new(self.natives.clone()).strip_versions(self.strip_native_versions);
+        if !natives.is_empty() {
+            natives.check_conflicts()?;
+            natives.extract_to(self.natives_directory.as_path())?;
+        }
+        if let Some(ref jar_path) = self.classpath_jar {
+            let entries: Vec<path::PathBuf> = self.classpath.iter().map(path::PathBuf::from).collect();
+            versions::write_classpath_jar(jar_path.as_path(), &entries)?;
+        }
+        copy_resources(&self.resources)?;
+        fs::create_dir_all(self.working_directory.as_path())?;
+        let mut args = self.args();
+        if args.iter().any(|arg| arg.contains(ACCESS_TOKEN_PLACEHOLDER)) {
+            let access_token = access_token();
+            for arg in args.iter_mut() { *arg = arg.replace(ACCESS_TOKEN_PLACEHOLDER, access_token.as_str()) }
+        }
+        let mut command = Command::new(self.program());
+        command.current_dir(self.working_directory.as_path()).envs(self.environment.iter()).args(args);
+        command.spawn().map_err(versions::Error::from)
+    }
+}
+
+impl InstallReportEntry {
+    pub fn path(&self) -> &path::Path {
+        &self.path
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn sha1(&self) -> &str {
+        &self.sha1
+    }
+
+    pub fn verified(&self) -> Option<bool> {
+        self.verified
+    }
+}
+
+impl PlannedDownload {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn path(&self) -> &path::Path {
+        &self.path
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn sha1(&self) -> Option<&str> {
+        self.sha1.as_deref()
+    }
+}
+
+impl InstallProgress {
+    pub fn files_done(&self) -> usize {
+        self.files_done
+    }
+
+    pub fn files_total(&self) -> usize {
+        self.files_total
+    }
+
+    pub fn bytes_done(&self) -> u64 {
+        self.bytes_done
+    }
+
+    pub fn bytes_total(&self) -> u64 {
+        self.bytes_total
+    }
+}
+
+#[cfg(feature = "progress-stream")]
+impl Stream for ProgressStream {
+    type Item = ProgressEvent;
+    type Error = versions::Error;
+
+    fn poll(&mut self) -> Poll<Option<ProgressEvent>, versions::Error> {
+        match self.0.poll() {
+            Result::Ok(Async::Ready(Some(Result::Ok(event)))) => Result::Ok(Async::Ready(Some(event))),
+            Result::Ok(Async::Ready(Some(Result::Err(e)))) => Result::Err(e),
+            Result::Ok(Async::Ready(None)) | Result::Err(()) => Result::Ok(Async::Ready(None)),
+            Result::Ok(Async::NotReady) => Result::Ok(Async::NotReady)
+        }
+    }
+}
+
+impl InstallReport {
+    pub fn entries(&self) -> &Vec<InstallReportEntry> {
+        &self.entries
+    }
+
+    pub fn is_verified(&self) -> bool {
+        self.entries.iter().all(|entry| entry.verified != Some(false))
+    }
+}
+
+impl Readiness {
+    pub fn percent(&self) -> f32 {
+        if self.total == 0 { 100f32 } else { 100f32 * (self.total - self.missing.len()) as f32 / self.total as f32 }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn missing(&self) -> &Vec<(ReadinessCategory, path::PathBuf)> {
+        &self.missing
+    }
+
+    pub fn missing_categories(&self) -> Vec<ReadinessCategory> {
+        let mut result = Vec::new();
+        for &(category, _) in self.missing.iter() {
+            if !result.contains(&category) { result.push(category) }
+        }
+        result
+    }
+}
+
+impl LaunchQueue {
+    pub fn new() -> LaunchQueue {
+        Default::default()
+    }
+
+    pub fn push(mut self, name: &str, args: LaunchArguments) -> Self {
+        self.entries.push((name.to_owned(), args));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn run(&self) -> Vec<(String, Result<ExitStatus, versions::Error>)> {
+        // a failed launch is recorded without stopping the launches after it
+        self.entries.iter().map(|(name, args)| (name.clone(), args.start_and_wait())).collect()
+    }
+}
+
+impl UuidFormat {
+    pub fn format(&self, uuid: &Uuid) -> String {
+        match *self {
+            UuidFormat::Simple => uuid.simple().to_string(),
+            UuidFormat::Hyphenated => uuid.hyphenated().to_string()
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn new(max_restarts: usize) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            min_uptime: Duration::from_secs(10),
+            max_immediate_crashes: 3,
+        }
+    }
+
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn min_uptime(mut self, min_uptime: Duration) -> Self {
+        self.min_uptime = min_uptime;
+        self
+    }
+
+    pub fn max_immediate_crashes(mut self, max_immediate_crashes: usize) -> Self {
+        self.max_immediate_crashes = max_immediate_crashes;
+        self
+    }
+
+    fn next_backoff(&self, backoff: Duration) -> Duration {
+        backoff.checked_mul(2).unwrap_or(self.max_backoff).min(self.max_backoff)
+    }
+}
+
+impl MemoryRecommendation {
+    pub fn memory_mib(&self) -> f32 {
+        self.memory_mib
+    }
+
+    pub fn fallback_reason(&self) -> Option<&str> {
+        self.fallback_reason.as_deref()
+    }
+}
+
+impl GameProcess {
+    pub fn wait_with_output(mut self) -> io::Result<Output> {
+        self.child.take().expect("present until dropped").wait_with_output()
+    }
+}
+
+impl ops::Deref for GameProcess {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        self.child.as_ref().expect("present until dropped")
+    }
+}
+
+impl ops::DerefMut for GameProcess {
+    fn deref_mut(&mut self) -> &mut Child {
+        self.child.as_mut().expect("present until dropped")
+    }
+}
+
+impl Drop for GameProcess {
+    fn drop(&mut self) {
+        let argfile = match self.argfile.take() {
+            Some(argfile) => argfile,
+            None => return
+        };
+        if let Some(mut child) = self.child.take() {
+            // the jvm may not have read the file yet, and windows refuses to remove it while the game runs
+            if let Result::Ok(None) = child.try_wait() {
+                thread::spawn(move || {
+                    let _ = child.wait();
+                    let _ = fs::remove_file(argfile);
+                });
+                return;
+            }
+        }
+        let _ = fs::remove_file(argfile);
+    }
+}
+
+impl SupervisedExit {
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+}
+
+impl InstanceConfig {
+    pub fn java(&self) -> Option<&str> {
+        self.java.as_deref()
+    }
+
+    pub fn min_memory_mib(&self) -> Option<f32> {
+        self.min_memory_mib
+    }
+
+    pub fn max_memory_mib(&self) -> Option<f32> {
+        self.max_memory_mib
+    }
+
+    pub fn max_memory_percent(&self) -> Option<f32> {
+        self.max_memory_percent
+    }
+
+    pub fn jvm_args(&self) -> &[String] {
+        &self.jvm_args
+    }
+
+    pub fn resolution(&self) -> (Option<u32>, Option<u32>) {
+        (self.width, self.height)
+    }
+}
+
+impl ModReport {
+    pub fn mod_files(&self) -> &[String] {
+        &self.mod_files
+    }
+
+    pub fn loaded_mod_ids(&self) -> &[String] {
+        &self.loaded_mod_ids
+    }
+}
+
+impl LaunchSnapshot {
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    pub fn java_program_path(&self) -> &str {
+        &self.java_program_path
+    }
+
+    pub fn java_version(&self) -> Option<&str> {
+        self.java_version.as_deref()
+    }
+
+    pub fn working_directory(&self) -> &path::Path {
+        &self.working_directory
+    }
+
+    pub fn environment(&self) -> &BTreeMap<String, String> {
+        &self.environment
+    }
+
+    pub fn args(&self) -> &Vec<String> {
+        &self.args
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn changes_since(&self, previous: &LaunchSnapshot) -> Vec<SnapshotChange> {
+        let mut result = Vec::new();
+        if previous.java_program_path != self.java_program_path {
+            result.push(SnapshotChange::JavaProgram(previous.java_program_path.clone(), self.java_program_path.clone()));
+        }
+        if previous.java_version != self.java_version {
+            result.push(SnapshotChange::JavaVersion(previous.java_version.clone(), self.java_version.clone()));
+        }
+        if previous.working_directory != self.working_directory {
+            result.push(SnapshotChange::WorkingDirectory(previous.working_directory.clone(), self.working_directory.clone()));
+        }
+        let memory_prefixes = ["-Xmx", "-Xms"];
+        for prefix in memory_prefixes.iter() {
+            let memory_of = |args: &Vec<String>| args.iter().rev().filter_map(|arg| arg.strip_prefix(prefix)).map(String::from).next();
+            let (old, new) = (memory_of(&previous.args), memory_of(&self.args));
+            if old != new { result.push(SnapshotChange::Memory((*prefix).to_owned(), old, new)) }
+        }
+        let is_memory = |arg: &&String| memory_prefixes.iter().any(|prefix| arg.starts_with(prefix));
+        for arg in previous.args.iter().filter(|arg| !is_memory(arg) && !self.args.contains(arg)) {
+            result.push(SnapshotChange::ArgumentRemoved(arg.clone()));
+        }
+        for arg in self.args.iter().filter(|arg| !is_memory(arg) && !previous.args.contains(arg)) {
+            result.push(SnapshotChange::ArgumentAdded(arg.clone()));
+        }
+        result
+    }
+}
+
+impl RotatingLog {
+    fn open(path: path::PathBuf, max_bytes: u64, kept: u32) -> io::Result<RotatingLog> {
+        if let Some(parent) = path.parent() { fs::create_dir_all(parent)?; }
+        let file = fs::OpenOptions::new().create(true).append(true).open(path.as_path())?;
+        let written = file.metadata()?.len();
+        Result::Ok(RotatingLog { path, max_bytes, kept, file, written })
+    }
+
+    fn rotated_path(&self, index: u32) -> path::PathBuf {
+        let mut name = self.path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        name.push(format!(".{}", index));
+        self.path.with_file_name(name)
+    }
+
+    fn append(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            // game.log.1 is the most recent one, and the oldest one is dropped
+            for index in (1..self.kept).rev() {
+                let _ = fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+            }
+            if self.kept > 0 { fs::rename(self.path.as_path(), self.rotated_path(1))?; }
+            self.file = fs::File::create(self.path.as_path())?;
+            self.written = 0;
+        }
+        io::Write::write_all(&mut self.file, line)?;
+        self.written += line.len() as u64;
+        Result::Ok(())
+    }
+}
+
+impl JvmOption {
+    pub fn new(arg: String, source: OptionSource) -> JvmOption {
+        JvmOption(arg, source)
+    }
+
+    pub fn source(&self) -> OptionSource {
+        self.1
+    }
+
+    pub fn key(&self) -> &str {
+        let arg = self.0.as_str();
+        for prefix in &["-Xmx", "-Xms", "-Xmn", "-Xss"] {
+            if arg.starts_with(prefix) { return prefix; }
+        }
+        if arg.starts_with("-XX:+") || arg.starts_with("-XX:-") {
+            return &arg[5..];
+        }
+        if arg.starts_with("-XX:") || arg.starts_with("-D") {
+            return arg.split('=').next().unwrap_or(arg);
+        }
+        arg
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        vec![self.0.clone()]
+    }
+}
+
+impl GameOption {
+    pub fn new_pair(name: String, arg: String, source: OptionSource) -> GameOption {
+        GameOption(name, Some(arg), source)
+    }
+
+    pub fn new_single(name: String, source: OptionSource) -> GameOption {
+        GameOption(name, None, source)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn source(&self) -> OptionSource {
+        self.2
+    }
+
+    pub fn with_source(self, source: OptionSource) -> GameOption {
+        GameOption(self.0, self.1, source)
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        match self.1 {
+            Some(ref arg) => vec![self.0.clone(), arg.clone()],
+            None => vec![self.0.clone()]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use tests::TempDir;
+
+    fn test_launcher(root_dir: &path::Path) -> MinecraftLauncherBuilder {
+        let profile = yggdrasil::Profile::new(Uuid::new_v4(), "Steve".to_owned(), HashMap::new());
+        builder().root_dir(root_dir).jre(path::Path::new("java")).auth(yggdrasil::AuthInfo::new(Uuid::new_v4(), profile))
+    }
+
+    #[cfg(unix)]
+    fn fake_program(name: &str, script: &str) -> String {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+        let path = env::temp_dir().join(format!("rmcll-test-{}", name));
+        fs::write(path.as_path(), format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(path.as_path(), fs::Permissions::from_mode(0o755)).unwrap();
+        path.into_os_string().into_string().unwrap()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_java_binary_is_rejected() {
+        let wrapper = fake_program("fake-java", "echo 'java 1.2.3 (not a jvm)'");
+        assert!(!is_java(&wrapper));
+        let jvm = fake_program("real-java", concat!(
+            "echo 'openjdk version \"17.0.2\" 2022-01-18' >&2\n",
+            "echo 'OpenJDK 64-Bit Server VM (build 17.0.2+8, mixed mode)' >&2"));
+        assert_eq!(java_version(&jvm), Some("17.0.2".to_owned()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn available_disk_space_of_temp_dir() {
+        let available = available_disk_space(env::temp_dir().as_path()).unwrap();
+        assert!(available > 0);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn unsupported_flag_is_detected() {
+        let java_8 = fake_program("java-8-without-zgc", concat!(
+            "case \"$1\" in -XX:+UseZGC) echo 'Unrecognized VM option UseZGC' >&2; exit 1;; esac\n",
+            "echo 'openjdk version \"1.8.0_301\"' >&2"));
+        assert!(!java_supports_flag(&java_8, "-XX:+UseZGC"));
+        assert!(java_supports_flag(&java_8, "-XX:+UseG1GC"));
+    }
+
+    #[test]
+    fn memory_and_resolution_fall_back_to_environment() {
+        let root = TempDir::new("environment");
+        let launcher = test_launcher(root.as_path())
+            .env_lookup(|key| match key {
+                "RMCLL_MAX_MEMORY" => Some("2048".to_owned()),
+                "RMCLL_WIDTH" => Some("1280".to_owned()),
+                "RMCLL_HEIGHT" => Some("-720".to_owned()),
+                _ => None
+            }).build();
+        assert_eq!(launcher.min_max_memory_mib.1, 2048f32);
+        assert_eq!(launcher.window_resolution, (1280, 480));
+    }
+
+    #[test]
+    fn asset_objects_can_live_on_another_volume() {
+        let temp_dir = TempDir::new("assets-volume");
+        let (root, objects) = (temp_dir.join("games/minecraft"), temp_dir.join("mnt/objects"));
+        let launcher = test_launcher(root.as_path()).assets_objects_dir(objects.as_path()).build();
+        let hash = "bdf48ef6b5d0d23bbb02e17d04865216179f510a";
+        assert_eq!(launcher.asset_object_path(hash), objects.join("bd").join(hash));
+        assert_eq!(launcher.assets_dir, root.join("assets/"));
+    }
+
+    #[test]
+    fn partial_install_readiness() {
+        use std::fs;
+        let root = TempDir::new("readiness");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "", "assets": "1.12",
+            "libraries": [{ "name": "com.mojang:patchy:1.1" }, { "name": "oshi-project:oshi-core:1.1" }]
+        }"#).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.jar"), b"").unwrap();
+        fs::create_dir_all(root.join("libraries/com/mojang/patchy/1.1")).unwrap();
+        fs::write(root.join("libraries/com/mojang/patchy/1.1/patchy-1.1.jar"), b"").unwrap();
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/1.12.json"), r#"{ "objects": {
+            "icons/icon_16x16.png": { "hash": "bdf48ef6b5d0d23bbb02e17d04865216179f510a", "size": 3665 }
+        } }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let readiness = launcher.readiness("1.12.2").unwrap();
+        assert_eq!(readiness.percent(), 60f32);
+        assert_eq!(readiness.missing_categories(), vec![ReadinessCategory::Libraries, ReadinessCategory::Assets]);
+        assert!(!readiness.is_ready());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn launch_matches_two_step_flow() {
+        use std::fs;
+        let root = TempDir::new("launch");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        let record = root.join("args.txt");
+        let java = fake_program("recording-java", &format!("printf '%s\\n' \"$@\" > '{}'", record.display()));
+        let launcher = test_launcher(root.as_path()).jre(path::Path::new(&java)).build();
+        assert!(launcher.launch("1.12.2").unwrap().wait().unwrap().success());
+        let recorded: Vec<String> = fs::read_to_string(record).unwrap().lines().map(String::from).collect();
+        assert_eq!(recorded, launcher.to_arguments("1.12.2").unwrap().args());
+    }
+
+    #[test]
+    fn install_report_lists_jar_and_libraries() {
+        use std::fs;
+        let root = TempDir::new("install-report");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "downloads": { "client": { "size": 2, "url": "", "sha1": "bf21a9e8fbc5a3846fb05b4fa0859e0917b2202f" } },
+            "libraries": [{
+                "name": "com.mojang:patchy:1.1",
+                "downloads": { "artifact": { "size": 2, "url": "", "sha1": "0000000000000000000000000000000000000000" } }
+            }]
+        }"#).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.jar"), "{}").unwrap();
+        fs::create_dir_all(root.join("libraries/com/mojang/patchy/1.1")).unwrap();
+        fs::write(root.join("libraries/com/mojang/patchy/1.1/patchy-1.1.jar"), "{}").unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let report = launcher.install_report("1.12.2").unwrap();
+        let entries = report.entries();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].path().ends_with("1.12.2.jar") && entries[1].path().ends_with("patchy-1.1.jar"));
+        assert!(entries.iter().all(|e| e.size() == 2 && e.sha1() == "bf21a9e8fbc5a3846fb05b4fa0859e0917b2202f"));
+        assert_eq!(entries[0].verified(), Some(true));
+        assert_eq!(entries[1].verified(), Some(false));
+        assert!(!report.is_verified());
+    }
+
+    #[test]
+    fn packaged_natives_are_not_extracted() {
+        use std::fs;
+        let root = TempDir::new("packaged-natives");
+        fs::create_dir_all(root.join("versions/1.19")).unwrap();
+        fs::write(root.join("versions/1.19/1.19.json"), r#"{
+            "id": "1.19", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "libraries": [{ "name": "org.lwjgl:lwjgl:3.3.1" }, { "name": "org.lwjgl:lwjgl:3.3.1:natives-linux" }],
+            "arguments": { "jvm": ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}"] }
+        }"#).unwrap();
+        for jar in ["lwjgl-3.3.1.jar", "lwjgl-3.3.1-natives-linux.jar"].iter() {
+            fs::create_dir_all(root.join("libraries/org/lwjgl/lwjgl/3.3.1")).unwrap();
+            fs::write(root.join("libraries/org/lwjgl/lwjgl/3.3.1").join(jar), b"").unwrap();
+        }
+        let launcher = test_launcher(root.as_path()).build();
+        let args = launcher.to_arguments("1.19").unwrap();
+        assert!(!args.needs_natives_extraction());
+        assert!(args.extract_natives().unwrap().is_empty());
+        assert!(!args.game_native_path.exists());
+        assert!(args.args().iter().any(|arg| arg.starts_with("-Djava.library.path=")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn injected_command_receives_launch_args() {
+        use std::fs;
+        let root = TempDir::new("command-factory");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let args = launcher.to_arguments("1.12.2").unwrap();
+        let record = root.join("args.txt");
+        let mut program = None;
+        let mut child = args.spawn_new_process_with(|java| {
+            program = Some(java);
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(format!("printf '%s\\n' \"$@\" > '{}'", record.display())).arg("stub");
+            command
+        }).unwrap();
+        assert!(child.wait().unwrap().success());
+        assert_eq!(program, Some("java".to_owned()));
+        let recorded: Vec<String> = fs::read_to_string(record).unwrap().lines().map(String::from).collect();
+        assert_eq!(recorded, args.args());
+        let main_class = recorded.iter().position(|arg| arg == "net.minecraft.client.main.Main").unwrap();
+        assert_eq!(&recorded[main_class + 1..main_class + 3], &["--username", "Steve"]);
+    }
+
+    #[test]
+    fn recommended_memory_scales_with_mods() {
+        use std::fs;
+        let mods_dir = TempDir::new("mods");
+        fs::create_dir_all(mods_dir.join("config.jar")).unwrap();
+        fs::write(mods_dir.join("disabled.jar.disabled"), b"").unwrap();
+        for i in 0..16 { fs::write(mods_dir.join(format!("mod-{}.jar", i)), b"").unwrap() }
+        assert_eq!(count_mods(mods_dir.as_path()).unwrap(), 16);
+        assert_eq!(recommended_max_memory_of(0, None), 2048f32);
+        assert_eq!(recommended_max_memory_of(16, None), 3072f32);
+        assert!(recommended_max_memory_of(32, None) > recommended_max_memory_of(16, None));
+        assert_eq!(recommended_max_memory_of(64, Some(4096f32)), 3072f32);
+    }
+
+    #[test]
+    fn recommended_memory_falls_back_without_total_memory() {
+        use std::fs;
+        let mods_dir = TempDir::new("mods-fallback");
+        fs::create_dir_all(mods_dir.as_path()).unwrap();
+        for i in 0..64 { fs::write(mods_dir.join(format!("mod-{}.jar", i)), b"").unwrap() }
+        let failed = recommend_max_memory_with(mods_dir.as_path(), || Result::Err(io::Error::other("meminfo unreadable")));
+        assert_eq!(failed.memory_mib(), 2048f32);
+        assert!(failed.fallback_reason().unwrap().contains("meminfo unreadable"), "{:?}", failed);
+        let succeeded = recommend_max_memory_with(mods_dir.as_path(), || Result::Ok(16384f32));
+        assert_eq!(succeeded.memory_mib(), 6144f32);
+        assert_eq!(succeeded.fallback_reason(), None);
+        let vanilla = recommend_max_memory_with(mods_dir.join("missing").as_path(), || Result::Ok(16384f32));
+        assert_eq!(vanilla.memory_mib(), 2048f32);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sandbox_prefix_is_applied() {
+        use std::fs;
+        let root = TempDir::new("sandbox");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main"
+        }"#).unwrap();
+        let launcher_with = |program: &str| {
+            test_launcher(root.as_path()).sandbox(program, &["--bind", "${game_directory}", "--"]).build().to_arguments("1.12.2").unwrap()
+        };
+        let sandboxed = launcher_with("env");
+        assert_eq!(sandboxed.program(), "env");
+        assert_eq!(&sandboxed.args()[..4], &["--bind", root.to_str().unwrap(), "--", "java"]);
+        let unavailable = launcher_with("rmcll-no-such-sandbox");
+        assert_eq!(unavailable.program(), "java");
+        assert_ne!(unavailable.args()[0], "--bind");
+        let probes = root.join("probes.txt");
+        let _ = fs::remove_file(probes.as_path());
+        let sandbox = fake_program("probed-sandbox", &format!("[ \"$1\" = --version ] && echo probe >> {}", probes.display()));
+        let launcher = test_launcher(root.as_path()).sandbox(sandbox.as_str(), &["--"]).build();
+        for _ in 0..3 { assert_eq!(launcher.to_arguments("1.12.2").unwrap().program(), sandbox) }
+        assert_eq!(fs::read_to_string(probes).unwrap(), "probe\n");
+    }
+
+    #[test]
+    fn natives_cleanup_plan_lists_extracted_files() {
+        let root = TempDir::new("natives-cleanup");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main"
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let args = launcher.to_arguments("1.12.2").unwrap();
+        assert!(args.natives_cleanup_plan().is_empty());
+        fs::create_dir_all(args.game_native_path.join("META-INF")).unwrap();
+        fs::write(args.game_native_path.join("liblwjgl64.so"), b"").unwrap();
+        fs::write(args.game_native_path.join("META-INF/MANIFEST.MF"), b"").unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.jar"), b"").unwrap();
+        let plan = args.natives_cleanup_plan();
+        assert_eq!(plan, vec![args.game_native_path.join("META-INF/MANIFEST.MF"), args.game_native_path.join("liblwjgl64.so")]);
+        assert!(plan.iter().all(|path_buf| path_buf.exists()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sdk_manager_installations_are_found() {
+        let home = TempDir::new("sdk-home");
+        let layouts = [
+            ".sdkman/candidates/java/17.0.2-tem/bin",
+            ".asdf/installs/java/temurin-21.0.1/bin",
+            ".jabba/jdk/zulu@1.8.0/bin",
+            ".jdks/corretto-17.0.9/bin",
+            ".local/share/JetBrains/Toolbox/apps/IDEA-U/ch-0/233.11799.241/jbr/bin",
+        ];
+        for layout in layouts.iter() {
+            fs::create_dir_all(home.join(layout)).unwrap();
+            fs::write(home.join(layout).join("java"), b"").unwrap();
+        }
+        fs::create_dir_all(home.join(".sdkman/candidates/java/current-without-java/bin")).unwrap();
+        let expected: Vec<_> = layouts.iter().map(|layout| home.join(layout).join("java").into_os_string().into_string().unwrap()).collect();
+        assert_eq!(sdk_manager_jre_candidates(home.as_path()), expected);
+    }
+
+    #[test]
+    fn active_auth_source_is_switchable() {
+        use uuid::Uuid;
+        let root = TempDir::new("auth-sources");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": ""
+        }"#).unwrap();
+        let account_type = yggdrasil::AccountType::Msa;
+        let online = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef-3c37-49fd-aa49-938b674adae6", "eyJhbGciOi.jwt", account_type).unwrap();
+        let offline = yggdrasil::AuthInfo::new(Uuid::new_v4(), yggdrasil::Profile::new(Uuid::new_v4(), "Steve".to_owned(), HashMap::new()));
+        let mut launcher = test_launcher(root.as_path())
+            .auth_source("offline", offline).auth_source("online", online).build();
+        let version = launcher.manager.version_of("1.12.2").unwrap();
+        assert_eq!(launcher.generate_argument_map(&version)["auth_player_name"], "Steve");
+        assert!(launcher.set_active_auth_source("online"));
+        assert_eq!(launcher.generate_argument_map(&version)["auth_player_name"], "Alex");
+        assert!(!launcher.set_active_auth_source("missing"));
+        assert_eq!(launcher.generate_argument_map(&version)["auth_player_name"], "Alex");
+    }
+
+    #[test]
+    fn auth_info_from_parts_reaches_argument_map() {
+        let root = TempDir::new("auth-parts");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": ""
+        }"#).unwrap();
+        let account_type = yggdrasil::AccountType::Msa;
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef-3c37-49fd-aa49-938b674adae6", "eyJhbGciOi.jwt", account_type).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info).build();
+        let version = launcher.manager.version_of("1.12.2").unwrap();
+        let map = launcher.generate_argument_map(&version);
+        assert_eq!(map["auth_player_name"], "Alex");
+        assert_eq!(map["auth_uuid"], "853c80ef3c3749fdaa49938b674adae6");
+        assert_eq!(map["auth_access_token"], "eyJhbGciOi.jwt");
+        assert_eq!(map["user_type"], "msa");
+        match yggdrasil::AuthInfo::from_parts("Alex", "not-a-uuid", "token", account_type) {
+            Result::Err(::requests::Error::InvalidCredentials(ref message)) => assert!(message.contains("not-a-uuid")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn user_jvm_option_replaces_default() {
+        let mut options = vec![
+            JvmOption::new("-Xmx2048m".to_owned(), OptionSource::Default),
+            JvmOption::new("-XX:+UseG1GC".to_owned(), OptionSource::Default),
+            JvmOption::new("-Dfile.encoding=GBK".to_owned(), OptionSource::Default),
+        ];
+        merge_jvm_option(&mut options, JvmOption::new("-Xmx4096m".to_owned(), OptionSource::User));
+        merge_jvm_option(&mut options, JvmOption::new("-XX:-UseG1GC".to_owned(), OptionSource::User));
+        merge_jvm_option(&mut options, JvmOption::new("-Dfile.encoding=UTF-8".to_owned(), OptionSource::User));
+        let args: Vec<_> = options.iter().flat_map(|o| o.to_args()).collect();
+        assert_eq!(args, vec!["-Xmx4096m", "-XX:-UseG1GC", "-Dfile.encoding=UTF-8"]);
+    }
+
+    #[test]
+    fn mapped_jre_is_selected_by_major_version() {
+        use std::fs;
+        let root = TempDir::new("jre-mapping");
+        fs::create_dir_all(root.join("versions/1.18.2")).unwrap();
+        fs::write(root.join("versions/1.18.2/1.18.2.json"), r#"{
+            "id": "1.18.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "javaVersion": { "component": "java-runtime-gamma", "majorVersion": 17 }, "arguments": { "game": [], "jvm": [] }
+        }"#).unwrap();
+        fs::write(root.join("java.json"), r#"{ "8": "/jdk8/bin/java", "17": "/jdk17/bin/java", "next": "/jdk/bin/java" }"#).unwrap();
+        let mapping = read_jre_mapping(root.join("java.json").as_path()).unwrap();
+        assert_eq!(mapping.len(), 2);
+        let profile = yggdrasil::Profile::new(Uuid::new_v4(), "Steve".to_owned(), HashMap::new());
+        let auth_info = yggdrasil::AuthInfo::new(Uuid::new_v4(), profile);
+        let launcher = builder().root_dir(root.as_path()).jre_mapping(mapping.clone()).auth(auth_info).build();
+        assert_eq!(launcher.to_arguments("1.18.2").unwrap().program(), "/jdk17/bin/java");
+        let launcher = test_launcher(root.as_path()).jre_mapping(mapping).build();
+        assert_eq!(launcher.to_arguments("1.18.2").unwrap().program(), "java");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn matching_jre_candidate_is_selected() {
+        let java_script = |version: &str| format!(concat!(
+            "echo 'openjdk version \"{}\"' >&2\n",
+            "echo 'OpenJDK 64-Bit Server VM' >&2"), version);
+        let java_8 = fake_program("java-8", &java_script("1.8.0_301"));
+        let java_17 = fake_program("java-17", &java_script("17.0.2"));
+        let candidates = vec![java_8.clone(), java_17.clone()];
+        assert_eq!(select_jre(&candidates, Some(17)), Some(java_17.clone()));
+        assert_eq!(select_jre(&candidates, Some(16)), Some(java_17));
+        assert_eq!(select_jre(&candidates, None), Some(java_8));
+        assert_eq!(select_jre(&candidates, Some(21)), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn tied_jre_candidates_follow_preference() {
+        let java_script = |version: &str, vendor: &str| format!(concat!(
+            "echo 'openjdk version \"{}\"' >&2\n",
+            "echo 'OpenJDK Runtime Environment {} (build {})' >&2\n",
+            "echo 'OpenJDK 64-Bit Server VM' >&2"), version, vendor, version);
+        let temurin = fake_program("java-17-temurin", &java_script("17.0.2", "Temurin"));
+        let zulu = fake_program("java-17-zulu", &java_script("17.0.10", "Zulu17.48+15-CA"));
+        let java_21 = fake_program("java-21-tied", &java_script("21.0.1", "Temurin"));
+        let candidates = vec![java_21, temurin.clone(), zulu.clone()];
+        let select = |preference: JrePreference| select_jre_with(&candidates, Some(17), &preference);
+        assert_eq!(select(JrePreference::First), Some(temurin.clone()));
+        assert_eq!(select(JrePreference::NewestPatch), Some(zulu.clone()));
+        assert_eq!(select(JrePreference::Vendor("zulu".to_owned())), Some(zulu.clone()));
+        assert_eq!(select(JrePreference::Vendor("corretto".to_owned())), Some(temurin.clone()));
+        assert_eq!(select(JrePreference::Pinned(zulu.clone())), Some(zulu));
+        assert_eq!(select(JrePreference::Pinned("/opt/missing/java".to_owned())), Some(temurin));
+    }
+
+    #[test]
+    fn unprovided_placeholder_is_reported() {
+        use std::fs;
+        let root = TempDir::new("missing-placeholders");
+        fs::create_dir_all(root.join("versions/future")).unwrap();
+        fs::write(root.join("versions/future/future.json"), r#"{
+            "id": "future", "type": "snapshot", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "game": ["--quickPlayRealms", "${quick_play_realms}"], "jvm": ["-cp", "${classpath}"] }
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        match launcher.to_arguments("future") {
+            Result::Err(versions::Error::MissingPlaceholders(ref keys)) => assert_eq!(keys, &["quick_play_realms"]),
+            other => panic!("unexpected result: {:?}", other.map(|a| a.args())),
+        }
+    }
+
+    #[test]
+    fn relative_library_directory_becomes_absolute() {
+        use std::fs;
+        let root = TempDir::new("absolute-jvm-paths");
+        fs::create_dir_all(root.join("versions/modular")).unwrap();
+        fs::write(root.join("versions/modular/modular.json"), r#"{
+            "id": "modular", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "jvm": ["-DlibraryDirectory=${library_directory}", "-cp", "${classpath}"] }
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).libraries_dir(path::Path::new("shared/libraries")).build();
+        let args = launcher.to_arguments("modular").unwrap().args();
+        let expected = format!("-DlibraryDirectory={}", root.join("shared/libraries").to_str().unwrap());
+        assert!(args.contains(&expected), "{:?}", args);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn access_token_is_resolved_when_spawning() {
+        use std::fs;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let root = TempDir::new("lazy-token");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--accessToken ${auth_access_token}"
+        }"#).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let launcher = test_launcher(root.as_path())
+            .access_token_provider(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                "lazy-secret".to_owned()
+            }).build();
+        // the launcher may be handed to another thread along with its provider
+        let launcher = thread::spawn(move || launcher).join().unwrap();
+        let version = launcher.manager.version_of("1.12.2").unwrap();
+        assert!(!launcher.generate_argument_map(&version).contains_key("auth_access_token"));
+        let args = launcher.to_arguments("1.12.2").unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(args.args().iter().all(|arg| !arg.contains("lazy-secret")));
+        assert!(args.capture_environment().args.iter().all(|arg| !arg.contains("lazy-secret")));
+        let record = root.join("args.txt");
+        let mut child = args.spawn_new_process_with(|_| {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(format!("printf '%s\\n' \"$@\" > '{}'", record.display())).arg("stub");
+            command
+        }).unwrap();
+        assert!(child.wait().unwrap().success());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let recorded = fs::read_to_string(record).unwrap();
+        assert!(recorded.lines().any(|line| line == "lazy-secret"), "{}", recorded);
+    }
+
+    #[test]
+    fn entrypoint_wrapper_receives_real_main_class() {
+        use std::fs;
+        let root = TempDir::new("entrypoint-wrapper");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.jar"), b"").unwrap();
+        let wrapper = root.join("wrapper.jar");
+        fs::write(wrapper.as_path(), b"").unwrap();
+        let launcher = test_launcher(root.as_path())
+            .entrypoint_wrapper(wrapper.as_path(), "com.example.Wrapper").build();
+        let args = launcher.to_arguments("1.12.2").unwrap().args();
+        let main_class = args.iter().position(|arg| arg == "com.example.Wrapper").unwrap();
+        assert_eq!(&args[main_class + 1..main_class + 4], &["net.minecraft.client.main.Main", "--username", "Steve"]);
+        let classpath = &args[args.iter().position(|arg| arg == "-cp").unwrap() + 1];
+        assert!(classpath.starts_with(fs::canonicalize(wrapper).unwrap().to_str().unwrap()), "{}", classpath);
+    }
+
+    #[test]
+    fn server_arguments_have_no_auth() {
+        use std::fs;
+        use std::io::Write;
+        use zip::write::{FileOptions, ZipWriter};
+        let root = TempDir::new("server-arguments");
+        fs::create_dir_all(root.join("versions/1.20.1")).unwrap();
+        fs::write(root.join("versions/1.20.1/1.20.1.json"), r#"{
+            "id": "1.20.1", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "game": ["--username", "${auth_player_name}", "--accessToken", "${auth_access_token}"], "jvm": [] }
+        }"#).unwrap();
+        let server_jar = root.join("server.jar");
+        let mut zip = ZipWriter::new(fs::File::create(server_jar.as_path()).unwrap());
+        zip.start_file("META-INF/MANIFEST.MF", FileOptions::default()).unwrap();
+        zip.write_all(b"Manifest-Version: 1.0\r\nMain-Class: net.minecraft.bundler.Main\r\n").unwrap();
+        zip.finish().unwrap();
+        let launcher = test_launcher(root.as_path()).max_memory(4096f32).jvm_args(&["-XX:+UseZGC"])
+            .auth_server("https://example.com/api/yggdrasil").authlib_injector(root.join("authlib-injector.jar").as_path())
+            .auth_server_metadata(&json!({ "signaturePublickey": "key" })).telemetry_host("localhost").build();
+        let args = launcher.to_server_arguments("1.20.1", server_jar.as_path()).unwrap().args();
+        assert_eq!(&args[args.len() - 2..], &["net.minecraft.bundler.Main", "nogui"]);
+        assert!(args.contains(&"-Xmx4096m".to_owned()) && args.contains(&"-XX:+UseZGC".to_owned()), "{:?}", args);
+        assert!(!args.iter().any(|arg| arg == "--username" || arg == "--accessToken" || arg == "Steve"), "{:?}", args);
+        let client_only = ["-javaagent:", "-Dauthlibinjector.", "-Dminecraft.api.telemetry.host=", "-Dfml."];
+        assert!(!args.iter().any(|arg| client_only.iter().any(|prefix| arg.starts_with(prefix))), "{:?}", args);
+    }
+
+    #[test]
+    fn mismatching_embedded_version_is_reported() {
+        use std::fs;
+        use std::io::Write;
+        use zip::write::{FileOptions, ZipWriter};
+        let root = TempDir::new("embedded-version");
+        for id in &["1.20.1", "1.20.1-forge"] {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+        }
+        fs::write(root.join("versions/1.20.1/1.20.1.json"), r#"{
+            "id": "1.20.1", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main"
+        }"#).unwrap();
+        fs::write(root.join("versions/1.20.1-forge/1.20.1-forge.json"), r#"{
+            "id": "1.20.1-forge", "inheritsFrom": "1.20.1", "type": "release", "time": "", "releaseTime": ""
+        }"#).unwrap();
+        let write_jar = |embedded_id: &str| {
+            let mut zip = ZipWriter::new(fs::File::create(root.join("versions/1.20.1/1.20.1.jar")).unwrap());
+            zip.start_file("version.json", FileOptions::default()).unwrap();
+            zip.write_all(format!(r#"{{ "id": "{}", "name": "{}" }}"#, embedded_id, embedded_id).as_bytes()).unwrap();
+            zip.finish().unwrap();
+        };
+        let launcher = test_launcher(root.as_path()).build();
+        let version = launcher.manager.version_of("1.20.1-forge").unwrap();
+        write_jar("1.20.1");
+        assert_eq!(launcher.embedded_version_mismatch(&version).unwrap(), None);
+        write_jar("1.19.4");
+        assert_eq!(launcher.embedded_version_mismatch(&version).unwrap(), Some("1.19.4".to_owned()));
+    }
+
+    #[test]
+    fn main_class_falls_back_to_user_then_manifest() {
+        use std::fs;
+        use std::io::Write;
+        use zip::write::{FileOptions, ZipWriter};
+        let root = TempDir::new("main-class-fallback");
+        fs::create_dir_all(root.join("versions/custom")).unwrap();
+        fs::write(root.join("versions/custom/custom.json"), r#"{
+            "id": "custom", "type": "release", "time": "", "releaseTime": ""
+        }"#).unwrap();
+        let launcher = |fallback: Option<&str>| {
+            let builder = test_launcher(root.as_path())
+                ;
+            match fallback {
+                Some(main_class) => builder.fallback_main_class(main_class).build(),
+                None => builder.build()
+            }
+        };
+        let version = launcher(None).manager.version_of("custom").unwrap();
+        match launcher(None).main_class(&version) {
+            Result::Err(versions::Error::NoMainClass(ref id)) => assert_eq!(id, "custom"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut zip = ZipWriter::new(fs::File::create(root.join("versions/custom/custom.jar")).unwrap());
+        zip.start_file("META-INF/MANIFEST.MF", FileOptions::default()).unwrap();
+        zip.write_all(b"Manifest-Version: 1.0\r\nMain-Class: com.example.very.long.package.na\r\n me.Main\r\n").unwrap();
+        zip.finish().unwrap();
+        assert_eq!(launcher(None).main_class(&version).unwrap(), "com.example.very.long.package.name.Main");
+        assert_eq!(launcher(Some("com.example.User")).main_class(&version).unwrap(), "com.example.User");
+    }
+
+    #[test]
+    fn snapshot_changes_report_memory() {
+        let snapshot = |args: &[&str]| LaunchSnapshot {
+            os: "linux".to_owned(),
+            arch: "x86_64".to_owned(),
+            java_program_path: "java".to_owned(),
+            java_version: Some("17.0.2".to_owned()),
+            working_directory: path::PathBuf::from("."),
+            environment: BTreeMap::new(),
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+        };
+        let previous = snapshot(&["-Xms128m", "-Xmx2048m", "net.minecraft.client.main.Main"]);
+        let current = snapshot(&["-Xms128m", "-Xmx4096m", "net.minecraft.client.main.Main"]);
+        assert!(previous.changes_since(&previous).is_empty());
+        assert_eq!(current.changes_since(&previous), vec![
+            SnapshotChange::Memory("-Xmx".to_owned(), Some("2048m".to_owned()), Some("4096m".to_owned())),
+        ]);
+    }
+
+    #[test]
+    fn wildcard_dirs_replace_their_jars_on_classpath() {
+        use std::fs;
+        let root = TempDir::new("classpath-wildcard");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "libraries": [{ "name": "com.example:first:1.0" }, { "name": "com.example:first:1.0:extra" }, { "name": "com.example:second:1.0" }]
+        }"#).unwrap();
+        for library in ["first/1.0/first-1.0.jar", "first/1.0/first-1.0-extra.jar", "second/1.0/second-1.0.jar"].iter() {
+            let library_path = root.join("libraries/com/example").join(library);
+            fs::create_dir_all(library_path.parent().unwrap()).unwrap();
+            fs::write(library_path, b"").unwrap();
+        }
+        let wildcard_dir = root.join("libraries/com/example/first/1.0");
+        let classpath = |dirs: &[&path::Path]| {
+            let launcher = test_launcher(root.as_path()).classpath_wildcard_dirs(dirs).build();
+            let args = launcher.to_arguments("1.12.2").unwrap().args();
+            let entries = args[args.iter().position(|arg| arg == "-cp").unwrap() + 1].clone();
+            entries.split(versions::CLASSPATH_SEPARATOR).map(path::PathBuf::from).collect::<Vec<_>>()
+        };
+        let wildcard = fs::canonicalize(wildcard_dir.as_path()).unwrap().join("*");
+        let enumerated = classpath(&[]);
+        assert!(!enumerated.contains(&wildcard) && enumerated.len() == 4);
+        let collapsed = classpath(&[wildcard_dir.as_path()]);
+        assert_eq!(collapsed[0], wildcard);
+        assert_eq!(collapsed[1..], enumerated[2..]);
+        assert_eq!(classpath(&[root.join("libraries/com/example/second/../first/1.0").as_path()])[0], wildcard);
+        fs::write(wildcard_dir.join("unlisted-1.0.jar"), b"").unwrap();
+        assert_eq!(classpath(&[wildcard_dir.as_path()]), enumerated);
+        fs::remove_file(wildcard_dir.join("unlisted-1.0.jar")).unwrap();
+    }
+
+    #[test]
+    fn classpath_jar_lists_all_entries() {
+        use std::fs;
+        use std::io::Read;
+        use zip::read::ZipArchive;
+        let root = TempDir::new("classpath-jar");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "libraries": [{ "name": "com.example:first:1.0" }, { "name": "com.example:second-with-a-rather-long-name:1.0" }]
+        }"#).unwrap();
+        for library in ["first/1.0/first-1.0.jar", "second-with-a-rather-long-name/1.0/second-with-a-rather-long-name-1.0.jar"].iter() {
+            let library_path = root.join("libraries/com/example").join(library);
+            fs::create_dir_all(library_path.parent().unwrap()).unwrap();
+            fs::write(library_path, b"").unwrap();
+        }
+        let jar_path = root.join("cache/classpath.jar");
+        let launcher = test_launcher(root.as_path()).classpath_jar(jar_path.as_path()).build();
+        let arguments = launcher.to_arguments("1.12.2").unwrap();
+        assert!(!jar_path.exists());
+        arguments.write_classpath_jar().unwrap();
+        let args = arguments.args();
+        let cp = args.iter().position(|arg| arg == "-cp").unwrap();
+        assert_eq!(args[cp + 1], jar_path.to_str().unwrap());
+        let mut manifest = String::new();
+        let mut zip = ZipArchive::new(fs::File::open(jar_path).unwrap()).unwrap();
+        zip.by_name("META-INF/MANIFEST.MF").unwrap().read_to_string(&mut manifest).unwrap();
+        assert!(manifest.lines().all(|line| line.len() <= 72), "{}", manifest);
+        let manifest = manifest.replace("\r\n ", "");
+        let class_path = manifest.lines().find(|line| line.starts_with("Class-Path: ")).unwrap();
+        let version = launcher.manager.version_of("1.12.2").unwrap();
+        let entries = launcher.classpath_entries(&version).unwrap();
+        assert_eq!(class_path.split(' ').count(), entries.len() + 1);
+        for entry in entries {
+            let file_name = entry.file_name().unwrap().to_str().unwrap().to_owned();
+            assert!(class_path.split(' ').any(|url| url.starts_with("file:/") && url.ends_with(&file_name)), "{}", class_path);
+        }
+    }
+
+    #[test]
+    fn file_encoding_is_set_by_default() {
+        use std::fs;
+        let root = TempDir::new("file-encoding");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let launcher = |encoding: Option<Option<&str>>| {
+            let builder = test_launcher(root.as_path())
+                ;
+            match encoding {
+                Some(encoding) => builder.file_encoding(encoding).build(),
+                None => builder.build()
+            }
+        };
+        let args = launcher(None).to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&"-Dfile.encoding=UTF-8".to_owned()), "{:?}", args);
+        let args = launcher(Some(Some("GBK"))).to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&"-Dfile.encoding=GBK".to_owned()), "{:?}", args);
+        let args = launcher(Some(None)).to_arguments("1.12.2").unwrap().args();
+        assert!(args.iter().all(|arg| !arg.starts_with("-Dfile.encoding=")), "{:?}", args);
+    }
+
+    #[test]
+    fn conflicting_gc_flags_are_reported() {
+        let options = vec![
+            JvmOption::new("-XX:+UseG1GC".to_owned(), OptionSource::User),
+            JvmOption::new("-XX:+UseZGC".to_owned(), OptionSource::User),
+            JvmOption::new("-Dfile.encoding=UTF-8".to_owned(), OptionSource::Default),
+        ];
+        assert_eq!(conflicting_jvm_options(&options), vec![("-XX:+UseG1GC".to_owned(), "-XX:+UseZGC".to_owned())]);
+        let options = vec![
+            JvmOption::new("-XX:+UseParallelGC".to_owned(), OptionSource::User),
+            JvmOption::new("-XX:+UseParallelOldGC".to_owned(), OptionSource::User),
+        ];
+        assert!(conflicting_jvm_options(&options).is_empty());
+    }
+
+    #[test]
+    fn resolution_round_trips_through_options() {
+        use std::fs;
+        let root = TempDir::new("options-resolution");
+        fs::create_dir_all(root.as_path()).unwrap();
+        fs::write(root.join("options.txt"), "version:1343\noverrideWidth:0\noverrideHeight:0\nlang:en_us\n").unwrap();
+        assert_eq!(read_options_resolution(root.as_path()), None);
+        let launcher = |resolution: Option<(u32, u32)>| {
+            let builder = test_launcher(root.as_path()).env_lookup(|_| None)
+                ;
+            match resolution {
+                Some((width, height)) => builder.resolution(width, height).build(),
+                None => builder.build()
+            }
+        };
+        assert!(launcher(Some((1280, 720))).save_resolution().unwrap());
+        assert!(!launcher(Some((1280, 720))).save_resolution().unwrap());
+        assert_eq!(read_options_resolution(root.as_path()), Some((1280, 720)));
+        let options = fs::read_to_string(root.join("options.txt")).unwrap();
+        assert_eq!(options, "version:1343\nlang:en_us\noverrideWidth:1280\noverrideHeight:720\n");
+        assert_eq!(launcher(None).window_resolution, (1280, 720));
+    }
+
+    #[test]
+    fn loaded_mods_are_extracted_from_logs() {
+        let fabric = concat!(
+            "[12:00:00] [main/INFO]: Loading Minecraft 1.20.1 with Fabric Loader 0.14.22\n",
+            "[12:00:00] [main/INFO]: Loading 4 mods:\n",
+            "\t- fabric-api 0.86.1+1.20.1\n",
+            "\t   |-- fabric-api-base 0.4.29+b04edc7a77\n",
+            "\t- java 17\n",
+            "\t- sodium 0.5.1\n",
+            "[12:00:01] [main/INFO]: SpongePowered MIXIN Subsystem Version=0.8.5\n");
+        assert_eq!(loaded_mod_ids(fabric), vec!["fabric-api", "fabric-api-base", "java", "sodium"]);
+        let legacy_fabric = "[12:00:00] [main/INFO]: Loading 3 mods: fabric@0.3.0, fabricloader@0.4.0, minecraft@1.14\n";
+        assert_eq!(loaded_mod_ids(legacy_fabric), vec!["fabric", "fabricloader", "minecraft"]);
+        assert!(loaded_mod_ids("[12:00:00] [main/INFO]: Setting user: Steve\n").is_empty());
+    }
+
+    #[test]
+    fn known_bad_jvm_flags_are_reported() {
+        let args = vec!["-Xss2m".to_owned(), "-XX:+AggressiveOpts".to_owned(), "-XX:+UseConcMarkSweepGC".to_owned()];
+        let problems = problematic_jvm_args(&args);
+        assert_eq!(problems.iter().map(|problem| problem.0.as_str()).collect::<Vec<_>>(), vec!["-XX:+AggressiveOpts", "-XX:+UseConcMarkSweepGC"]);
+        assert!(problems.iter().all(|problem| !problem.1.is_empty()));
+        assert!(problematic_jvm_args(&["-XX:+UseG1GC".to_owned()]).is_empty());
+    }
+
+    #[test]
+    fn bundle_is_imported_and_verified() {
+        use std::fs;
+        use std::io::Write;
+        use sha1::Sha1;
+        use zip::write::{FileOptions, ZipWriter};
+        let root = TempDir::new("import-bundle");
+        fs::create_dir_all(root.as_path()).unwrap();
+        let client_jar: &[u8] = b"client jar";
+        let version_json = format!(r#"{{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "downloads": {{ "client": {{ "size": 10, "url": "", "sha1": "{}" }} }},
+            "libraries": [{{ "name": "com.example:lib:1.0" }}]
+        }}"#, Sha1::from(client_jar).digest());
+        let bundle = |path: path::PathBuf, entries: &[(&str, &[u8])]| {
+            let mut zip = ZipWriter::new(fs::File::create(path.as_path()).unwrap());
+            for &(name, content) in entries {
+                zip.start_file(name, FileOptions::default()).unwrap();
+                zip.write_all(content).unwrap();
+            }
+            zip.finish().unwrap();
+            path
+        };
+        let good = bundle(root.join("bundle.zip"), &[
+            ("versions/1.12.2/1.12.2.json", version_json.as_bytes()),
+            ("versions/1.12.2/1.12.2.jar", client_jar),
+            ("libraries/com/example/lib/1.0/lib-1.0.jar", b"library jar"),
+        ]);
+        let launcher = test_launcher(root.as_path()).build();
+        let report = launcher.import_bundle(good.as_path(), "1.12.2").unwrap();
+        assert!(report.is_verified());
+        assert_eq!(report.entries().len(), 2);
+        assert_eq!(fs::read(root.join("libraries/com/example/lib/1.0/lib-1.0.jar")).unwrap(), b"library jar");
+        let evil = bundle(root.join("evil-bundle.zip"), &[("../rmcll-test-escaped", b"")]);
+        match launcher.import_bundle(evil.as_path(), "1.12.2") {
+            Result::Err(versions::Error::UnsafeArchiveEntry(ref name)) => assert_eq!(name, "../rmcll-test-escaped"),
+            other => panic!("unexpected result: {:?}", other.map(|r| r.entries().len())),
+        }
+    }
+
+    #[test]
+    fn unique_asset_objects_exclude_shared_ones() {
+        use std::fs;
+        let root = TempDir::new("unique-assets");
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/old.json"), r#"{ "objects": {
+            "a.ogg": { "hash": "aaaa", "size": 1 }, "shared.ogg": { "hash": "ssss", "size": 1 }
+        } }"#).unwrap();
+        fs::write(root.join("assets/indexes/new.json"), r#"{ "objects": {
+            "shared.ogg": { "hash": "ssss", "size": 1 }, "b.ogg": { "hash": "bbbb", "size": 1 }
+        } }"#).unwrap();
+        for &(id, assets) in [("1.12.2", "old"), ("1.19", "new")].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "", "assets": "{}"
+            }}"#, id, assets)).unwrap();
+        }
+        let launcher = test_launcher(root.as_path()).build();
+        assert_eq!(launcher.unique_asset_objects("1.12.2").unwrap(), vec!["aaaa"]);
+        assert_eq!(launcher.unique_asset_objects("1.19").unwrap(), vec!["bbbb"]);
+    }
+
+    #[test]
+    fn proxy_arguments_are_passed_to_the_game() {
+        use std::fs;
+        let root = TempDir::new("proxy");
+        for &(id, template) in [("1.12.2", "--username ${auth_player_name}"), ("1.5.2", "${auth_player_name} ${auth_session}")].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "",
+                "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "{}"
+            }}"#, id, template)).unwrap();
+        }
+        let launcher = test_launcher(root.as_path())
+            .proxy("proxy.example.com", 3128).proxy_credentials("steve", "hunter2").build();
+        let arguments = launcher.to_arguments("1.12.2").unwrap();
+        let args = arguments.args();
+        let host = args.iter().position(|arg| arg == "--proxyHost").unwrap();
+        assert_eq!(&args[host..host + 8], &["--proxyHost", "proxy.example.com", "--proxyPort", "3128",
+                                            "--proxyUser", "steve", "--proxyPass", "hunter2"]);
+        assert!(arguments.capture_environment().args().iter().all(|arg| arg != "hunter2"));
+        let args = launcher.to_arguments("1.5.2").unwrap().args();
+        assert!(args.iter().all(|arg| !arg.starts_with("--proxy")), "{:?}", args);
+    }
+
+    #[test]
+    fn debug_port_adds_jdwp_agent() {
+        use std::fs;
+        let root = TempDir::new("jdwp");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let launcher = |suspend: bool| {
+            test_launcher(root.as_path()).debug_port(5005).debug_suspend(suspend).build()
+        };
+        let args = launcher(true).to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&"-agentlib:jdwp=transport=dt_socket,server=y,suspend=y,address=5005".to_owned()), "{:?}", args);
+        let args = launcher(false).to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&"-agentlib:jdwp=transport=dt_socket,server=y,suspend=n,address=5005".to_owned()), "{:?}", args);
+    }
+
+    #[test]
+    fn fml_arguments_are_resolved() {
+        use std::fs;
+        let root = TempDir::new("fml-arguments");
+        fs::create_dir_all(root.join("versions/1.20.1")).unwrap();
+        fs::create_dir_all(root.join("versions/1.20.1-forge-47.1.0")).unwrap();
+        fs::write(root.join("versions/1.20.1/1.20.1.json"), r#"{
+            "id": "1.20.1", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "game": ["--version", "${version_name}"], "jvm": [] }
+        }"#).unwrap();
+        fs::write(root.join("versions/1.20.1-forge-47.1.0/1.20.1-forge-47.1.0.json"), r#"{
+            "id": "1.20.1-forge-47.1.0", "inheritsFrom": "1.20.1", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+            "libraries": [{ "name": "net.minecraftforge:forge:1.20.1-47.1.0" }, { "name": "de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412" }],
+            "arguments": { "game": ["--launchTarget", "forgeclient", "--fml.forgeVersion", "${forge_version}",
+                                    "--fml.mcVersion", "${mc_version}", "--fml.mcpVersion", "${mcp_version}"] }
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let args = launcher.to_arguments("1.20.1-forge-47.1.0").unwrap().args();
+        let position = args.iter().position(|arg| arg == "--fml.forgeVersion").unwrap();
+        let expected = ["--fml.forgeVersion", "47.1.0", "--fml.mcVersion", "1.20.1", "--fml.mcpVersion", "20230612.114412"];
+        assert_eq!(&args[position..position + 6], &expected, "{:?}", args);
+    }
+
+    #[test]
+    fn command_line_length_counts_quoted_arguments() {
+        use std::fs;
+        let root = TempDir::new("command-line-length");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).jvm_args(&["-Dname=a b"]).build();
+        let arguments = launcher.to_arguments("1.12.2").unwrap();
+        let args = arguments.args();
+        let unquoted = "java".len() + args.iter().map(|arg| arg.len() + 1).sum::<usize>();
+        assert!(args.contains(&"-Dname=a b".to_owned()), "{:?}", args);
+        assert!(args.iter().all(|arg| arg == "-Dname=a b" || !arg.contains(' ')), "{:?}", args);
+        assert_eq!(arguments.command_line_length(), unquoted + 2);
+    }
+
+    #[test]
+    fn flagged_jvm_options_precede_main_class() {
+        use std::fs;
+        let root = TempDir::new("main-class-adjacent");
+        fs::create_dir_all(root.join("versions/1.13")).unwrap();
+        fs::write(root.join("versions/1.13/1.13.json"), r#"{
+            "id": "1.13", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "game": [], "jvm": ["-cp", "${classpath}", "-Dfoo=bar"] }
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path())
+            .jvm_args(&["-Xss2m"]).jvm_args_before_main_class(&["-cp", "-Dfoo"]).build();
+        let args = launcher.to_arguments("1.13").unwrap().args();
+        let main_class = args.iter().position(|arg| arg == "net.minecraft.client.main.Main").unwrap();
+        assert_eq!(args[main_class - 3], "-cp");
+        assert_eq!(args[main_class - 1], "-Dfoo=bar");
+        assert!(args[..main_class - 3].contains(&"-Xss2m".to_owned()), "{:?}", args);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn smoke_check_detects_broken_classpath() {
+        use std::fs;
+        let root = TempDir::new("smoke-check");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "libraries": [{ "name": "com.example:lib:1.0" }]
+        }"#).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.jar"), b"").unwrap();
+        let library = root.join("libraries/com/example/lib/1.0/lib-1.0.jar");
+        fs::create_dir_all(library.parent().unwrap()).unwrap();
+        fs::write(library.as_path(), b"").unwrap();
+        let java = fake_program("smoke-java", "[ \"$1\" = -cp ] && [ \"$3\" = -version ] || { echo 'bad arguments' >&2; exit 1; }");
+        let launcher = test_launcher(root.as_path()).jre(path::Path::new(&java)).build();
+        launcher.smoke_check("1.12.2").unwrap();
+        fs::remove_file(library.as_path()).unwrap();
+        match launcher.smoke_check("1.12.2") {
+            Result::Err(versions::Error::FileUnavailableError(path)) => assert_eq!(&*path, library.as_path()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stack_size_is_validated_and_applied() {
+        use std::fs;
+        let root = TempDir::new("stack-size");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let launcher = |size_kib: u32| {
+            test_launcher(root.as_path()).stack_size(size_kib).build()
+        };
+        let args = launcher(4096).to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&"-Xss4096k".to_owned()), "{:?}", args);
+        for &size_kib in &[64, 2 * 1024 * 1024] {
+            match launcher(size_kib).to_arguments("1.12.2") {
+                Result::Err(versions::Error::InvalidStackSize(size)) => assert_eq!(size, size_kib),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn exit_on_out_of_memory_is_opt_in() {
+        use std::fs;
+        let root = TempDir::new("exit-on-oom");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let launcher = |exit: Option<bool>| {
+            let builder = test_launcher(root.as_path())
+                ;
+            match exit {
+                Some(exit) => builder.exit_on_out_of_memory(exit).build(),
+                None => builder.build()
+            }
+        };
+        let flag = "-XX:+ExitOnOutOfMemoryError".to_owned();
+        assert!(!launcher(None).to_arguments("1.12.2").unwrap().args().contains(&flag));
+        assert!(!launcher(Some(false)).to_arguments("1.12.2").unwrap().args().contains(&flag));
+        assert!(launcher(Some(true)).to_arguments("1.12.2").unwrap().args().contains(&flag));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn game_output_is_written_to_rotating_log() {
+        use std::fs;
+        let root = TempDir::new("log-file");
+        let _ = fs::remove_dir_all(root.join("logs"));
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let java = fake_program("log-java", "for last; do :; done\n[ \"$last\" = -version ] && exit 1\necho first\necho second >&2\necho third");
+        let launcher = test_launcher(root.as_path()).jre(path::Path::new(&java)).build();
+        let mut child = launcher.to_arguments("1.12.2").unwrap().start_with_rotating_log(path::Path::new("logs/game.log"), 12, 5).unwrap();
+        assert!(child.wait().unwrap().success());
+        let read_all = || {
+            let mut lines: Vec<String> = ["logs/game.log", "logs/game.log.1", "logs/game.log.2"].iter()
+                .flat_map(|name| fs::read_to_string(root.join(name)).unwrap_or_default().lines().map(String::from).collect::<Vec<_>>()).collect();
+            lines.sort();
+            lines
+        };
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while read_all().len() < 3 && Instant::now() < deadline { thread::sleep(Duration::from_millis(10)) }
+        assert_eq!(read_all(), vec!["first", "second", "third"]);
+        assert!(fs::metadata(root.join("logs/game.log")).unwrap().len() <= 12);
+        assert!(root.join("logs/game.log.1").is_file());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn missing_game_directories_are_created() {
+        use std::fs;
+        let root = TempDir::new("create-game-dirs");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let java = fake_program("game-dirs-java", "exit 0");
+        let launcher = |game_dir: &str, create: Option<bool>| {
+            let _ = fs::remove_dir_all(root.join(game_dir));
+            let builder = test_launcher(root.as_path()).profile_game_dir(root.join(game_dir).as_path())
+                .jre(path::Path::new(&java));
+            match create {
+                Some(create) => builder.create_game_dirs(create).build(),
+                None => builder.build()
+            }
+        };
+        assert!(launcher("instance", None).to_arguments("1.12.2").unwrap().start().unwrap().wait().unwrap().success());
+        assert!(root.join("instance/saves").is_dir() && root.join("instance/resourcepacks").is_dir());
+        assert!(launcher("skipped", Some(false)).to_arguments("1.12.2").unwrap().start().is_err());
+        assert!(!root.join("skipped").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn started_process_reports_its_pid() {
+        use std::fs;
+        let root = TempDir::new("pid");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let java = fake_program("pid-java", "for last; do :; done\n[ \"$last\" = -version ] || echo $$ > pid.txt");
+        let launcher = test_launcher(root.as_path()).jre(path::Path::new(&java)).build();
+        let (mut child, pid) = launcher.to_arguments("1.12.2").unwrap().start_with_pid().unwrap();
+        assert_eq!(pid, child.id());
+        assert!(child.wait().unwrap().success());
+        assert_eq!(fs::read_to_string(root.join("pid.txt")).unwrap().trim(), pid.to_string());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn queued_launches_run_sequentially() {
+        use std::fs;
+        let root = TempDir::new("launch-queue");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let args = |name: &str, code: i32| {
+            let script = format!("for last; do :; done\n[ \"$last\" = -version ] && exit 1\n\
+                echo start {0} >> ../queue.txt\nsleep 0.2\necho end {0} >> ../queue.txt\nexit {1}", name, code);
+            let java = fake_program(&format!("queued-java-{}", name), &script);
+            test_launcher(root.as_path()).profile_game_dir(root.join(name).as_path()).jre(path::Path::new(&java))
+                .build().to_arguments("1.12.2").unwrap()
+        };
+        let queue = LaunchQueue::new().push("first", args("first", 0)).push("second", args("second", 2));
+        let results = queue.run();
+        let summary: Vec<_> = results.iter().map(|(name, result)| (name.as_str(), result.as_ref().unwrap().code())).collect();
+        assert_eq!(summary, vec![("first", Some(0)), ("second", Some(2))]);
+        assert_eq!(fs::read_to_string(root.join("queue.txt")).unwrap(), "start first\nend first\nstart second\nend second\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn supervised_game_is_restarted_until_giving_up() {
+        use std::fs;
+        use std::time::Duration;
+        let root = TempDir::new("supervised");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let java = fake_program("crashing-java", "for last; do :; done\n[ \"$last\" = -version ] && exit 1\necho run >> runs.txt\nexit 3");
+        let launcher = test_launcher(root.as_path()).jre(path::Path::new(&java)).build();
+        let args = launcher.to_arguments("1.12.2").unwrap();
+        let runs = || fs::read_to_string(root.join("runs.txt")).unwrap().lines().count();
+        let policy = RestartPolicy::new(2).backoff(Duration::from_millis(1)).min_uptime(Duration::from_secs(0));
+        let exit = args.start_supervised(&policy).unwrap();
+        assert_eq!((exit.status().code(), exit.restarts(), runs()), (Some(3), 2, 3));
+        let policy = RestartPolicy::new(5).backoff(Duration::from_millis(1)).max_immediate_crashes(2);
+        let exit = args.start_supervised(&policy).unwrap();
+        assert_eq!((exit.restarts(), runs()), (1, 5));
+        let policy = RestartPolicy::new(5).max_backoff(Duration::from_secs(30));
+        assert_eq!(policy.next_backoff(Duration::from_secs(4)), Duration::from_secs(8));
+        assert_eq!(policy.next_backoff(Duration::from_secs(20)), Duration::from_secs(30));
+        assert_eq!(policy.next_backoff(Duration::new(u64::MAX, 0)), Duration::from_secs(30));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn crash_reports_are_redirected() {
+        use std::fs;
+        let root = TempDir::new("crash-reports");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let java = fake_program("crash-java", "for last; do :; done\n[ \"$last\" = -version ] && exit 1\necho boom > crash-reports/crash-1.txt\nexit 1");
+        let collected = root.join("collected");
+        let launcher = test_launcher(root.as_path()).jre(path::Path::new(&java)).crash_reports_dir(collected.as_path()).build();
+        let args = launcher.to_arguments("1.12.2").unwrap();
+        assert!(!args.start().unwrap().wait().unwrap().success());
+        assert_eq!(args.crash_reports_dir(), collected);
+        assert_eq!(args.latest_crash_report(), Some(collected.join("crash-1.txt")));
+        assert_eq!(fs::read_to_string(collected.join("crash-1.txt")).unwrap(), "boom\n");
+    }
+
+    #[test]
+    fn launcher_brand_is_applied_consistently() {
+        use std::fs;
+        let root = TempDir::new("launcher-brand");
+        for &(id, arguments) in [("1.12.2", r#""minecraftArguments": """#), ("modern", r#""arguments": { "jvm": ["-cp", "${classpath}"] }"#)].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "",
+                "mainClass": "net.minecraft.client.main.Main", {}
+            }}"#, id, arguments)).unwrap();
+        }
+        let launcher = test_launcher(root.as_path()).launcher("Acme", "2.1").build();
+        let version = launcher.manager.version_of("modern").unwrap();
+        let map = launcher.generate_argument_map(&version);
+        assert_eq!((map["launcher_name"].as_str(), map["launcher_version"].as_str()), ("Acme", "2.1"));
+        for id in ["1.12.2", "modern"].iter() {
+            let args = launcher.to_arguments(id).unwrap().args();
+            let brands: Vec<_> = args.iter().filter(|arg| arg.starts_with("-Dminecraft.launcher.")).collect();
+            assert_eq!(brands, vec!["-Dminecraft.launcher.brand=Acme", "-Dminecraft.launcher.version=2.1"]);
+        }
+    }
+
+    #[test]
+    fn default_brand_version_is_package_version() {
+        let root = TempDir::new("brand-version");
+        let launcher = test_launcher(root.as_path()).build();
+        assert_eq!(launcher.launcher_name_version(), ("RMCLL", env!("CARGO_PKG_VERSION")));
+        assert_eq!(LAUNCHER_VERSION, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    #[cfg(target_os = "windows")]
+    fn mismatching_jvm_arch_is_rejected_on_windows() {
+        match check_jvm_pointer_width(32, "64") {
+            Result::Err(versions::Error::JvmArchMismatch(32, 64)) => (),
+            result => panic!("unexpected result: {:?}", result)
+        }
+        match check_jvm_pointer_width(64, "32") {
+            Result::Err(versions::Error::JvmArchMismatch(64, 32)) => (),
+            result => panic!("unexpected result: {:?}", result)
+        }
+        assert!(check_jvm_pointer_width(64, versions::native_arch()).is_ok() || versions::native_arch() == "32");
+    }
+
+    #[test]
+    fn missing_vc_redist_is_detected() {
+        use std::fs;
+        let system_dir = TempDir::new("system32");
+        fs::create_dir_all(system_dir.as_path()).unwrap();
+        assert_eq!(missing_vc_redist_libraries(system_dir.as_path()), vec!["vcruntime140.dll", "msvcp140.dll"]);
+        fs::write(system_dir.join("vcruntime140.dll"), b"").unwrap();
+        fs::write(system_dir.join("msvcp140.dll"), b"").unwrap();
+        assert!(missing_vc_redist_libraries(system_dir.as_path()).is_empty());
+    }
+
+    #[test]
+    fn computing_arguments_writes_nothing() {
+        use std::fs;
+        let root = TempDir::new("compute-arguments");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "libraries": [{ "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4", "natives": { "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" } }]
+        }"#).unwrap();
+        fn snapshot(dir: &path::Path, result: &mut Vec<(path::PathBuf, u64)>) {
+            for entry in fs::read_dir(dir).unwrap().flatten() {
+                let metadata = entry.metadata().unwrap();
+                if metadata.is_dir() { snapshot(entry.path().as_path(), result) }
+                result.push((entry.path(), metadata.len()));
+            }
+        }
+        let launcher = test_launcher(root.as_path()).classpath_jar(root.join("classpath.jar").as_path())
+            .map_to_resources(true).build();
+        let (mut before, mut after) = (Vec::new(), Vec::new());
+        snapshot(root.as_path(), &mut before);
+        let args = launcher.compute_arguments("1.12.2").unwrap();
+        snapshot(root.as_path(), &mut after);
+        assert_eq!(before, after);
+        assert!(args.needs_natives_extraction() && !root.join("classpath.jar").exists());
+        launcher.to_arguments("1.12.2").unwrap().write_classpath_jar().unwrap();
+        assert!(root.join("classpath.jar").is_file());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn argfile_is_written_to_configured_location() {
+        use std::fs;
+        let root = TempDir::new("argfile");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "", "javaVersion": {"majorVersion": 17},
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        fs::create_dir_all(root.join("versions/1.7.10")).unwrap();
+        fs::write(root.join("versions/1.7.10/1.7.10.json"), r#"{
+            "id": "1.7.10", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        let java = fake_program("argfile-java", "for last; do :; done\n[ \"$last\" = -version ] && exit 1\n\
+            echo \"$@\" > args.txt\ncat \"${1#@}\" > argfile.txt");
+        let launcher = |argfile: Option<&path::Path>, keep: bool| {
+            test_launcher(root.as_path()).jre(path::Path::new(&java)).argfile(argfile).keep_argfile(keep).build()
+        };
+        let args = |argfile: Option<&path::Path>, keep: bool| launcher(argfile, keep).to_arguments("1.12.2").unwrap();
+        let kept = args(Some(path::Path::new("launch/1.12.2.args")), true);
+        assert_eq!(kept.argfile(), Some(root.join("launch/1.12.2.args").as_path()));
+        assert!(kept.start_and_wait().unwrap().success());
+        assert!(root.join("launch/1.12.2.args").is_file());
+        assert!(fs::read_to_string(root.join("args.txt")).unwrap()
+                   .starts_with(&format!("@{} --username Steve", root.join("launch/1.12.2.args").display())));
+        assert!(fs::read_to_string(root.join("argfile.txt")).unwrap().ends_with("\"net.minecraft.client.main.Main\"\n"));
+        let deleted = args(None, false);
+        let argfile = deleted.argfile().unwrap().to_path_buf();
+        assert!(argfile.starts_with(root.join(".rmcll")));
+        assert!(deleted.start_and_wait().unwrap().success());
+        assert!(!argfile.exists());
+        assert!(fs::read_to_string(root.join("argfile.txt")).unwrap().contains("\"-Dminecraft.launcher.brand=RMCLL\"\n"));
+        // a plain start keeps the file until the process handle is gone
+        let mut child = deleted.start().unwrap();
+        assert!(child.wait().unwrap().success());
+        assert!(argfile.is_file());
+        drop(child);
+        assert!(!argfile.exists());
+        // java 8 cannot read the argfile, so the classpath goes through a jar instead
+        let legacy = launcher(None, false).to_arguments("1.7.10").unwrap();
+        assert!(legacy.argfile().is_none());
+        assert_eq!(legacy.classpath_jar(), Some(root.join(".rmcll/1.7.10-classpath.jar").as_path()));
+    }
+
+    #[test]
+    fn excluded_assets_are_not_downloaded() {
+        use std::fs;
+        let root = TempDir::new("excluded-assets");
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/1.12.json"), r#"{ "objects": {
+            "minecraft/sounds/ambient/cave/cave1.ogg": { "hash": "aa11", "size": 1 },
+            "minecraft/lang/en_us.lang": { "hash": "bb22", "size": 1 },
+            "minecraft/textures/gui/title.png": { "hash": "cc33", "size": 1 }
+        } }"#).unwrap();
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "", "assets": "1.12"
+        }"#).unwrap();
+        fs::create_dir_all(root.join("assets/objects/cc")).unwrap();
+        fs::write(root.join("assets/objects/cc/cc33"), b"").unwrap();
+        let launcher = test_launcher(root.as_path()).exclude_assets(&["minecraft/sounds/"]).build();
+        let downloads = launcher.asset_downloads("1.12.2").unwrap();
+        assert_eq!(downloads, vec![(format!("{}/bb/bb22", versions::RESOURCES_HOST), launcher.asset_object_path("bb22"))]);
+        let readiness = launcher.readiness("1.12.2").unwrap();
+        assert!(readiness.missing().iter().all(|(_, path_buf)| !path_buf.ends_with("aa11")));
+    }
+
+    #[test]
+    fn asset_index_override_is_used() {
+        use std::fs;
+        let root = TempDir::new("asset-index-override");
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/custom.json"), r#"{ "virtual": true, "objects": {
+            "icons/icon_16x16.png": { "hash": "bdf48ef6b5d0d23bbb02e17d04865216179f510a", "size": 3665 }
+        } }"#).unwrap();
+        fs::create_dir_all(root.join("versions/1.7.2")).unwrap();
+        fs::write(root.join("versions/1.7.2/1.7.2.json"), r#"{
+            "id": "1.7.2", "type": "release", "time": "", "releaseTime": "", "assets": "legacy",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--assetIndex ${assets_index_name} --assetsDir ${game_assets}"
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).asset_index("custom").build();
+        let args = launcher.to_arguments("1.7.2").unwrap().args();
+        let value_of = |name: &str| args[args.iter().position(|arg| arg == name).unwrap() + 1].clone();
+        assert_eq!(value_of("--assetIndex"), "custom");
+        assert_eq!(path::PathBuf::from(value_of("--assetsDir")), root.join("assets/virtual/custom"));
+        let downloads = launcher.asset_downloads("1.7.2").unwrap();
+        assert_eq!(downloads.iter().map(|(_, path_buf)| path_buf.clone()).collect::<Vec<_>>(),
+                   vec![root.join("assets/objects/bd/bdf48ef6b5d0d23bbb02e17d04865216179f510a")]);
+    }
+
+    #[test]
+    fn assets_dir_is_resolved_per_version() {
+        use std::fs;
+        let root = TempDir::new("assets-dir");
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/1.13.json"), r#"{ "objects": {} }"#).unwrap();
+        fs::write(root.join("assets/indexes/legacy.json"), r#"{ "virtual": true, "objects": {} }"#).unwrap();
+        fs::write(root.join("assets/indexes/pre-1.6.json"), r#"{ "map_to_resources": true, "objects": {} }"#).unwrap();
+        for &(id, assets, arguments) in &[("1.13", "1.13", r#""arguments": { "game": ["--assetsDir", "${assets_root}"], "jvm": [] }"#),
+                                          ("1.7.2", "legacy", r#""minecraftArguments": "--assetsDir ${game_assets}""#),
+                                          ("1.5.2", "pre-1.6", r#""minecraftArguments": "--assetsDir ${game_assets}""#)] {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "", "assets": "{}",
+                "mainClass": "net.minecraft.client.main.Main", {}
+            }}"#, id, assets, arguments)).unwrap();
+        }
+        let launcher = test_launcher(root.as_path()).build();
+        let assets_dir = |id: &str| {
+            let args = launcher.to_arguments(id).unwrap().args();
+            path::PathBuf::from(&args[args.iter().position(|arg| arg == "--assetsDir").unwrap() + 1])
+        };
+        assert_eq!(assets_dir("1.13"), root.join("assets/"));
+        assert_eq!(assets_dir("1.7.2"), root.join("assets/virtual/legacy"));
+        assert_eq!(assets_dir("1.5.2"), root.join("resources"));
+    }
+
+    #[test]
+    fn asset_layout_ignores_release_date() {
+        use std::fs;
+        let root = TempDir::new("asset-layout");
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/custom.json"), r#"{ "virtual": true, "objects": {} }"#).unwrap();
+        fs::create_dir_all(root.join("versions/retro")).unwrap();
+        fs::write(root.join("versions/retro/retro.json"), r#"{
+            "id": "retro", "type": "release", "time": "2021-01-01T00:00:00+00:00", "releaseTime": "2021-01-01T00:00:00+00:00",
+            "assets": "custom", "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--assetsDir ${game_assets}"
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let args = launcher.to_arguments("retro").unwrap().args();
+        let assets_dir = path::PathBuf::from(&args[args.iter().position(|arg| arg == "--assetsDir").unwrap() + 1]);
+        assert_eq!(assets_dir, root.join("assets/virtual/custom"));
+    }
+
+    #[test]
+    fn legacy_assets_are_mapped_to_resources() {
+        use std::fs;
+        let root = TempDir::new("map-to-resources");
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/pre-1.6.json"), r#"{ "map_to_resources": true, "objects": {
+            "sound/step/grass1.ogg": { "hash": "aa11", "size": 5 },
+            "music/calm1.ogg": { "hash": "bb22", "size": 5 }
+        } }"#).unwrap();
+        fs::create_dir_all(root.join("versions/1.5.2")).unwrap();
+        fs::write(root.join("versions/1.5.2/1.5.2.json"), r#"{
+            "id": "1.5.2", "type": "release", "time": "", "releaseTime": "", "assets": "pre-1.6",
+            "mainClass": "net.minecraft.launchwrapper.Launch", "minecraftArguments": "${auth_player_name}"
+        }"#).unwrap();
+        fs::create_dir_all(root.join("assets/objects/aa")).unwrap();
+        fs::write(root.join("assets/objects/aa/aa11"), b"grass").unwrap();
+        let launcher = |map_to_resources: bool| {
+            test_launcher(root.as_path()).map_to_resources(map_to_resources).build()
+        };
+        launcher(false).to_arguments("1.5.2").unwrap();
+        assert!(!root.join("resources").exists());
+        launcher(true).to_arguments("1.5.2").unwrap();
+        assert_eq!(fs::read(root.join("resources/sound/step/grass1.ogg")).unwrap(), b"grass");
+        assert!(!root.join("resources/music/calm1.ogg").exists());
+    }
+
+    #[test]
+    fn natives_directory_is_the_extraction_target() {
+        use std::fs;
+        let root = TempDir::new("natives-directory");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let args = launcher.to_arguments("1.12.2").unwrap();
+        assert_eq!(launcher.natives_directory("1.12.2"), args.game_native_path);
+        assert!(launcher.natives_directory("1.12.2").starts_with(root.join("versions/1.12.2")));
+    }
+
+    #[test]
+    fn session_follows_configured_format() {
+        use std::fs;
+        use uuid::Uuid;
+        let root = TempDir::new("session-format");
+        fs::create_dir_all(root.join("versions/1.5.2")).unwrap();
+        fs::write(root.join("versions/1.5.2/1.5.2.json"), r#"{
+            "id": "1.5.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.Minecraft", "minecraftArguments": "${auth_player_name} ${auth_session}"
+        }"#).unwrap();
+        let uuid = Uuid::parse_str("853c80ef-3c37-49fd-aa49-938b674adae6").unwrap();
+        let session_of = |format: Option<SessionFormat>| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            let builder = test_launcher(root.as_path()).auth(auth_info);
+            let launcher = match format {
+                Some(format) => builder.session_format(format).build(),
+                None => builder.build()
+            };
+            launcher.to_arguments("1.5.2").unwrap().args().last().cloned().unwrap()
+        };
+        assert_eq!(session_of(None), format!("token:secret:{}", uuid.simple()));
+        assert_eq!(session_of(Some(SessionFormat::AccessToken)), "secret");
+        assert_eq!(session_of(Some(SessionFormat::Offline)), "-");
+        assert_eq!(session_of(Some(SessionFormat::Template("${auth_player_name}:${auth_access_token}".to_owned()))), "Alex:secret");
+    }
+
+    #[test]
+    fn instance_config_provides_defaults() {
+        use std::fs;
+        use uuid::Uuid;
+        let root = TempDir::new("instance-config");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        fs::create_dir_all(root.join("instance/.rmcll")).unwrap();
+        fs::write(root.join("instance/.rmcll/config.toml"), r#"
+            # per-instance defaults
+            java = "/opt/jdk-17/bin/java"
+            max_memory = 3_072 # MiB
+            jvm_args = ["-XX:+DisableExplicitGC", '-Dfile.encoding=GBK']
+            width = 1280
+            height = 720
+
+            [ignored]
+            max_memory = 1024
+        "#).unwrap();
+        let game_dir = root.join("instance");
+        let config_file = game_dir.join(".rmcll/config.toml");
+        let config = read_instance_config(config_file.as_path()).unwrap();
+        assert_eq!((config.java(), config.max_memory_mib(), config.resolution()), (Some("/opt/jdk-17/bin/java"), Some(3072f32), (Some(1280), Some(720))));
+        let launcher = |max_memory_mib: Option<f32>| {
+            let profile = yggdrasil::Profile::new(Uuid::new_v4(), "Steve".to_owned(), HashMap::new());
+            let builder = builder().root_dir(root.as_path()).profile_game_dir(game_dir.as_path()).config_file(config_file.as_path()).unwrap()
+                .auth(yggdrasil::AuthInfo::new(Uuid::new_v4(), profile));
+            match max_memory_mib {
+                Some(memory_mib) => builder.max_memory(memory_mib).build(),
+                None => builder.build()
+            }
+        };
+        let configured = launcher(None);
+        assert_eq!(configured.window_resolution, (1280, 720));
+        let args = configured.to_arguments("1.12.2").unwrap();
+        assert_eq!(args.java_program_path, "/opt/jdk-17/bin/java");
+        let args = args.args();
+        for expected in &["-Xmx3072m", "-XX:+DisableExplicitGC", "-Dfile.encoding=GBK"] {
+            assert!(args.iter().any(|arg| arg == expected), "{} not in {:?}", expected, args);
+        }
+        assert!(launcher(Some(1024f32)).to_arguments("1.12.2").unwrap().args().iter().any(|arg| arg == "-Xmx1024m"));
+        // the file is only read when asked for
+        let unconfigured = test_launcher(root.as_path()).profile_game_dir(game_dir.as_path())
+            .env_lookup(|_| None).build();
+        assert_eq!(unconfigured.window_resolution, (854, 480));
+        fs::write(config_file.as_path(), "width = \"wide\"\n").unwrap();
+        assert!(builder().config_file(config_file.as_path()).is_err());
+    }
+
+    #[test]
+    fn uuid_formats_are_independent() {
+        use std::fs;
+        let root = TempDir::new("uuid-format");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--uuid ${auth_uuid} --session ${auth_session}"
+        }"#).unwrap();
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info)
+            .auth_uuid_format(UuidFormat::Hyphenated).build();
+        let args = launcher.to_arguments("1.12.2").unwrap().args();
+        let value_of = |name: &str| args[args.iter().position(|arg| arg == name).unwrap() + 1].clone();
+        assert_eq!(value_of("--uuid"), "853c80ef-3c37-49fd-aa49-938b674adae6");
+        assert_eq!(value_of("--session"), "token:secret:853c80ef3c3749fdaa49938b674adae6");
+    }
+
+    #[test]
+    fn dry_run_lists_missing_downloads() {
+        use std::fs;
+        let root = TempDir::new("install-dry-run");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "assetIndex": { "id": "1.12", "url": "https://example.com/1.12.json", "sha1": "", "size": 1, "totalSize": 2 },
+            "downloads": { "client": { "url": "https://example.com/client.jar", "sha1": "c0ffee", "size": 42 } },
+            "libraries": [
+                { "name": "com.example:present:1.0", "downloads": { "artifact": { "url": "https://example.com/present.jar", "sha1": "aa", "size": 1 } } },
+                { "name": "com.example:missing:1.0", "downloads": { "artifact": { "url": "https://example.com/missing.jar", "sha1": "bb", "size": 7 } } }
+            ]
+        }"#).unwrap();
+        fs::create_dir_all(root.join("libraries/com/example/present/1.0")).unwrap();
+        fs::write(root.join("libraries/com/example/present/1.0/present-1.0.jar"), b"").unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let summary = |plan: Vec<PlannedDownload>| -> Vec<(String, path::PathBuf, Option<u64>, Option<String>)> {
+            plan.iter().map(|d| (d.url().to_owned(), d.path().to_path_buf(), d.size(), d.sha1().map(String::from))).collect()
+        };
+        assert_eq!(summary(launcher.install_version_dry_run("1.12.2").unwrap()), vec![
+            ("https://example.com/client.jar".to_owned(), root.join("versions/1.12.2/1.12.2.jar"), Some(42), Some("c0ffee".to_owned())),
+            ("https://example.com/missing.jar".to_owned(), root.join("libraries/com/example/missing/1.0/missing-1.0.jar"), Some(7), Some("bb".to_owned())),
+            ("https://example.com/1.12.json".to_owned(), root.join("assets/indexes/1.12.json"), None, None)
+        ]);
+        let hash = "bdf48ef6b5d0d23bbb02e17d04865216179f510a";
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/1.12.json"), format!(r#"{{ "objects": {{ "icons/icon_16x16.png": {{ "hash": "{}", "size": 3665 }} }} }}"#, hash)).unwrap();
+        let plan = summary(launcher.install_version_dry_run("1.12.2").unwrap());
+        assert_eq!(plan.last().unwrap(), &(versions::AssetDownloadInfo::object_url(versions::RESOURCES_HOST, hash),
+                                           root.join("assets/objects/bd").join(hash), Some(3665), Some(hash.to_owned())));
+        assert!(!root.join("versions/1.12.2/1.12.2.jar").exists());
+    }
+
+    #[test]
+    fn install_fails_early_without_enough_disk_space() {
+        use std::fs;
+        let root = TempDir::new("disk-space");
+        // thousands of libraries of almost 2 GiB each, which no test machine has room for
+        let libraries: Vec<String> = (0..4096).map(|i| format!(r#"{{ "name": "com.example:huge{}:1.0", "downloads": {{ "artifact":
+            {{ "url": "https://example.com/huge{}.jar", "sha1": "", "size": 2000000000 }} }} }}"#, i, i)).collect();
+        for (id, libraries) in [("huge", libraries.join(",")), ("small", String::new())].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "",
+                "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "", "libraries": [{}]
+            }}"#, id, libraries)).unwrap();
+        }
+        let launcher = test_launcher(root.as_path()).build();
+        assert!(launcher.check_disk_space("small").is_ok());
+        match launcher.install_version("huge", |_| ()) {
+            Result::Err(versions::Error::InsufficientDiskSpace(required, available)) => {
+                assert_eq!(required, 4096 * 2000000000);
+                assert!(available > 0 && available < required);
+            }
+            other => panic!("unexpected result: {:?}", other)
+        }
+        assert!(!root.join("libraries").exists());
+    }
+
+    #[test]
+    fn installation_is_verified_in_parallel() {
+        use std::fs;
+        use sha1::Sha1;
+        let root = TempDir::new("parallel-verify");
+        let objects: Vec<String> = (0..16).map(|i| format!("object {}", i)).collect();
+        let index: Vec<String> = objects.iter().enumerate().map(|(i, content)| {
+            format!(r#""file{}": {{ "hash": "{}", "size": 1 }}"#, i, Sha1::from(content.as_bytes()).digest())
+        }).collect();
+        fs::create_dir_all(root.join("assets/indexes")).unwrap();
+        fs::write(root.join("assets/indexes/1.12.json"), format!(r#"{{ "objects": {{ {} }} }}"#, index.join(", "))).unwrap();
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "", "assets": "1.12"
+        }"#).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let mut expected = Vec::new();
+        for (i, content) in objects.iter().enumerate() {
+            let path_buf = launcher.asset_object_path(&Sha1::from(content.as_bytes()).digest().to_string());
+            fs::create_dir_all(path_buf.parent().unwrap()).unwrap();
+            // corrupt every fifth object and leave out the last one
+            match i {
+                15 => expected.push(path_buf),
+                _ if i % 5 == 0 => {
+                    fs::write(path_buf.as_path(), b"corrupted").unwrap();
+                    expected.push(path_buf);
+                }
+                _ => fs::write(path_buf.as_path(), content.as_bytes()).unwrap()
+            }
+        }
+        expected.sort();
+        for threads in [1, 3, 8].iter() {
+            assert_eq!(launcher.verify_installation("1.12.2", *threads).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn client_id_is_stable_across_launches() {
+        use std::fs;
+        let root = TempDir::new("client-id");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--clientId ${clientid}"
+        }"#).unwrap();
+        fs::create_dir_all(root.join("versions/1.6.4")).unwrap();
+        fs::write(root.join("versions/1.6.4/1.6.4.json"), r#"{
+            "id": "1.6.4", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        let launcher = |client_id: Option<&str>| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            let mut builder = test_launcher(root.as_path()).auth(auth_info);
+            if let Some(client_id) = client_id { builder = builder.client_id(client_id) }
+            builder.build()
+        };
+        let client_id_of = |client_id: Option<&str>| {
+            let args = launcher(client_id).to_arguments("1.12.2").unwrap().args();
+            args[args.iter().position(|arg| arg == "--clientId").unwrap() + 1].clone()
+        };
+        // neither building a launcher nor launching a version without ${clientid} creates the id
+        launcher(None).to_arguments("1.6.4").unwrap();
+        assert!(!root.join(".rmcll").exists());
+        let first = client_id_of(None);
+        assert!(!first.is_empty());
+        assert_eq!(client_id_of(None), first);
+        assert_eq!(client_id_of(Some("custom")), "custom");
+        assert_eq!(fs::read_to_string(root.join(".rmcll/client_id")).unwrap(), first);
+    }
+
+    #[test]
+    fn custom_hasher_is_used_for_every_file() {
+        use std::fs;
+        struct RecordingHasher(Arc<Mutex<Vec<path::PathBuf>>>);
+        impl versions::FileHasher for RecordingHasher {
+            fn sha1(&self, path: &path::Path) -> Result<String, versions::Error> {
+                self.0.lock().unwrap().push(path.to_path_buf());
+                Result::Ok("c0ffee".to_owned())
+            }
+        }
+        let root = TempDir::new("custom-hasher");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "downloads": { "client": { "url": "https://example.com/client.jar", "sha1": "c0ffee", "size": 1 } },
+            "libraries": [{ "name": "com.example:lib:1.0", "downloads": { "artifact": { "url": "https://example.com/lib.jar", "sha1": "bad", "size": 1 } } }]
+        }"#).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.jar"), b"client").unwrap();
+        let hashed = Arc::new(Mutex::new(Vec::new()));
+        let launcher = test_launcher(root.as_path())
+            .hasher(RecordingHasher(hashed.clone())).build();
+        let library = root.join("libraries/com/example/lib/1.0/lib-1.0.jar");
+        assert_eq!(launcher.verify_installation("1.12.2", 2).unwrap(), vec![library.clone()]);
+        let mut hashed = hashed.lock().unwrap().clone();
+        hashed.sort();
+        assert_eq!(hashed, vec![library, root.join("versions/1.12.2/1.12.2.jar")]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn serialized_launch_plan_is_executable() {
+        use std::fs;
+        let root = TempDir::new("launch-plan");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main",
+            "minecraftArguments": "--username ${auth_player_name} --accessToken ${auth_access_token}"
+        }"#).unwrap();
+        let java = fake_program("plan-java", "for last; do :; done\n[ \"$last\" = -version ] && exit 1\necho \"$@\" > args.txt");
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let jar_path = root.join("classpath.jar");
+        let launcher = test_launcher(root.as_path()).profile_game_dir(root.join("instance").as_path()).jre(path::Path::new(&java))
+            .classpath_jar(jar_path.as_path()).auth(auth_info).build();
+        let plan = launcher.launch_plan("1.12.2").unwrap();
+        assert!(!jar_path.exists() && !root.join("instance").exists());
+        let version = launcher.manager.version_of("1.12.2").unwrap();
+        let entries: Vec<String> = launcher.classpath_jar_entries(&version).unwrap().iter().map(|entry| entry.to_str().unwrap().to_owned()).collect();
+        assert_eq!(plan.classpath(), &entries);
+        assert_eq!(plan.game_args()[..4], ["--username", "Alex", "--accessToken", "${auth_access_token}"]);
+        assert_eq!(plan.environment().get("PATH"), env::var("PATH").ok().as_ref());
+        let serialized = serde_json::to_string(&plan).unwrap();
+        assert!(!serialized.contains("secret"));
+        let reloaded: LaunchPlan = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reloaded, plan);
+        assert!(reloaded.execute(|| "fresh".to_owned()).unwrap().wait().unwrap().success());
+        assert!(jar_path.is_file());
+        let args = fs::read_to_string(root.join("instance/args.txt")).unwrap();
+        assert!(args.contains(&format!("-cp {} net.minecraft.client.main.Main --username Alex --accessToken fresh", jar_path.display())));
+    }
+
+    #[test]
+    fn authlib_injector_agent_is_emitted_for_auth_server() {
+        use std::fs;
+        let root = TempDir::new("auth-server");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let args_of = |agent_jar: Option<&path::Path>| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            let mut builder = test_launcher(root.as_path()).auth(auth_info)
+                .auth_server("https://example.com/api/yggdrasil");
+            if let Some(agent_jar) = agent_jar { builder = builder.authlib_injector(agent_jar) }
+            builder.build().to_arguments("1.12.2").unwrap().args()
+        };
+        let agent_jar = root.join("authlib-injector.jar");
+        let agent = format!("-javaagent:{}=https://example.com/api/yggdrasil", agent_jar.display());
+        assert!(args_of(Some(agent_jar.as_path())).contains(&agent));
+        assert!(args_of(None).contains(&"-Dauthlibinjector.yggdrasil=https://example.com/api/yggdrasil".to_owned()));
+    }
+
+    #[test]
+    fn prefetched_auth_server_metadata_is_emitted() {
+        use std::fs;
+        let root = TempDir::new("auth-server-metadata");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let metadata = json!({ "signaturePublickey": "key" });
+        let agent_jar = root.join("authlib-injector.jar");
+        let args = test_launcher(root.as_path()).auth(auth_info).auth_server("https://example.com/api/yggdrasil")
+            .authlib_injector(agent_jar.as_path()).auth_server_metadata(&metadata).build().to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&format!("-javaagent:{}=https://example.com/api/yggdrasil", agent_jar.display())));
+        assert!(args.contains(&"-Dauthlibinjector.yggdrasil.prefetched=eyJzaWduYXR1cmVQdWJsaWNrZXkiOiJrZXkifQ==".to_owned()));
+    }
+
+    #[test]
+    fn inherited_versions_are_read_once_per_launch() {
+        use std::fs;
+        let root = TempDir::new("cached-versions");
+        for (id, extra) in [("1.12.2", r#""minecraftArguments": "--username ${auth_player_name}""#),
+                            ("1.12.2-forge", r#""inheritsFrom": "1.12.2", "mainClass": "net.minecraft.launchwrapper.Launch""#),
+                            ("modpack", r#""inheritsFrom": "1.12.2-forge""#)].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "", {}
+            }}"#, id, extra)).unwrap();
+        }
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info).build();
+        let args = launcher.to_arguments("modpack").unwrap();
+        assert_eq!(launcher.manager.json_reads(), 3);
+        assert_eq!(args.java_main_class, "net.minecraft.launchwrapper.Launch");
+        launcher.to_arguments("modpack").unwrap();
+        assert_eq!(launcher.manager.json_reads(), 6);
+        let launcher = &launcher;
+        ::std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|_| scope.spawn(move || launcher.to_arguments("modpack").map(|args| args.java_main_class))).collect();
+            for handle in handles { assert_eq!(handle.join().unwrap().unwrap(), "net.minecraft.launchwrapper.Launch") }
+        });
+    }
+
+    #[test]
+    fn max_memory_percentage_is_resolved_from_total_memory() {
+        use std::fs;
+        let root = TempDir::new("memory-percent");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": ""
+        }"#).unwrap();
+        let launcher = |percent: f32| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            test_launcher(root.as_path()).auth(auth_info)
+                .max_memory_percent(percent).total_memory(16001f32).build()
+        };
+        assert!(launcher(50f32).to_arguments("1.12.2").unwrap().args().contains(&"-Xmx8000m".to_owned()));
+        for &percent in &[0f32, 150f32, f32::NAN] {
+            match launcher(percent).to_arguments("1.12.2") {
+                Result::Err(versions::Error::InvalidMemoryPercentage(_)) => (),
+                other => panic!("unexpected result for {}: {:?}", percent, other),
+            }
+        }
+        fs::create_dir_all(root.join("instance/.rmcll")).unwrap();
+        fs::write(root.join("instance/.rmcll/config.toml"), "max_memory = \"25%\"\n").unwrap();
+        assert_eq!(read_instance_config(root.join("instance/.rmcll/config.toml").as_path()).unwrap().max_memory_percent(), Some(25f32));
+    }
+
+    #[test]
+    fn main_class_of_child_overrides_parent() {
+        use std::fs;
+        let root = TempDir::new("main-class-override");
+        for (id, extra) in [("1.12.2", r#""mainClass": "net.minecraft.client.main.Main", "minecraftArguments": """#),
+                            ("1.12.2-forge", r#""inheritsFrom": "1.12.2", "mainClass": "net.minecraft.launchwrapper.Launch""#),
+                            ("modpack", r#""inheritsFrom": "1.12.2-forge""#)].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "", {}
+            }}"#, id, extra)).unwrap();
+        }
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info).build();
+        assert_eq!(launcher.to_arguments("modpack").unwrap().java_main_class, "net.minecraft.launchwrapper.Launch");
+        assert_eq!(launcher.main_class_layers("modpack").unwrap(), vec![
+            ("1.12.2-forge".to_owned(), "net.minecraft.launchwrapper.Launch".to_owned()),
+            ("1.12.2".to_owned(), "net.minecraft.client.main.Main".to_owned()),
+        ]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn writes_to_read_only_libraries_go_to_overlay() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+        let root = TempDir::new("libraries-overlay");
+        if root.join("shared").exists() { fs::set_permissions(root.join("shared"), fs::Permissions::from_mode(0o755)).unwrap() }
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "libraries": [
+                { "name": "com.example:patched:1.0", "downloads": { "artifact": { "url": "https://example.com/patched.jar", "sha1": "aa", "size": 1 } } },
+                { "name": "com.example:missing:1.0", "downloads": { "artifact": { "url": "https://example.com/missing.jar", "sha1": "bb", "size": 1 } } }
+            ]
+        }"#).unwrap();
+        let shared_jar = root.join("shared/com/example/patched/1.0/patched-1.0.jar");
+        fs::create_dir_all(shared_jar.parent().unwrap()).unwrap();
+        fs::write(shared_jar.as_path(), b"original").unwrap();
+        fs::set_permissions(root.join("shared"), fs::Permissions::from_mode(0o555)).unwrap();
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info)
+            .libraries_dir(root.join("shared").as_path()).libraries_overlay_dir(root.join("overlay").as_path()).build();
+        let overlay_jar = launcher.writable_library(shared_jar.as_path()).unwrap();
+        assert_eq!(overlay_jar, root.join("overlay/com/example/patched/1.0/patched-1.0.jar"));
+        assert_eq!(fs::read(overlay_jar.as_path()).unwrap(), b"original");
+        let version = launcher.manager.version_of("1.12.2").unwrap();
+        fs::create_dir_all(root.join("overlay/com/example/missing/1.0")).unwrap();
+        fs::write(root.join("overlay/com/example/missing/1.0/missing-1.0.jar"), b"").unwrap();
+        assert_eq!(launcher.classpath_entries(&version).unwrap()[0], fs::canonicalize(overlay_jar).unwrap());
+        fs::remove_file(root.join("overlay/com/example/missing/1.0/missing-1.0.jar")).unwrap();
+        let planned: Vec<_> = launcher.install_version_dry_run("1.12.2").unwrap().into_iter().map(|d| d.path().to_path_buf()).collect();
+        assert!(planned.contains(&root.join("overlay/com/example/missing/1.0/missing-1.0.jar")), "{:?}", planned);
+        fs::set_permissions(root.join("shared"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn long_command_line_gets_relative_classpath() {
+        use std::fs;
+        let root = TempDir::new("shortened-classpath");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "libraries": [{ "name": "com.example:shared:1.0" }]
+        }"#).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.jar"), b"").unwrap();
+        let shared = TempDir::new("shortened-classpath-libraries");
+        fs::create_dir_all(shared.join("com/example/shared/1.0")).unwrap();
+        fs::write(shared.join("com/example/shared/1.0/shared-1.0.jar"), b"").unwrap();
+        fs::create_dir_all(root.join("versions/1.17")).unwrap();
+        fs::write(root.join("versions/1.17/1.17.json"), r#"{
+            "id": "1.17", "inheritsFrom": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "javaVersion": { "component": "java-runtime-alpha", "majorVersion": 16 }
+        }"#).unwrap();
+        let arguments_of = |id: &str, limit: usize| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            test_launcher(root.as_path()).libraries_dir(shared.as_path()).auth(auth_info)
+                .command_line_limit(limit).build().compute_arguments(id).unwrap()
+        };
+        let args_of = |limit: usize| arguments_of("1.17", limit);
+        let classpath_of = |arguments: &LaunchArguments| {
+            let args = arguments.args();
+            args[args.iter().position(|arg| arg == "-cp").unwrap() + 1].clone()
+        };
+        let library = fs::canonicalize(shared.join("com/example/shared/1.0/shared-1.0.jar")).unwrap();
+        let relative_jar = path::Path::new("versions/1.12.2/1.12.2.jar");
+        let unlimited = args_of(usize::MAX);
+        assert!(classpath_of(&unlimited).ends_with(fs::canonicalize(root.join(relative_jar)).unwrap().to_str().unwrap()));
+        assert!(unlimited.argfile().is_none());
+        let shortened = args_of(1);
+        let expected = [library.to_str().unwrap(), relative_jar.to_str().unwrap()].join(versions::CLASSPATH_SEPARATOR);
+        assert_eq!(classpath_of(&shortened), expected);
+        assert!(shortened.argfile().is_some() && shortened.classpath_jar().is_none());
+        // java 8 cannot read the argfile, so the classpath goes through a jar
+        let shortened = arguments_of("1.12.2", 1);
+        let jar_path = root.join(".rmcll/1.12.2-classpath.jar");
+        assert!(shortened.argfile().is_none());
+        assert_eq!(shortened.classpath_jar(), Some(jar_path.as_path()));
+        assert_eq!(classpath_of(&shortened), jar_path.to_str().unwrap());
+        assert!(!jar_path.exists());
+        shortened.write_classpath_jar().unwrap();
+        assert!(jar_path.is_file());
+    }
+
+    #[test]
+    fn user_type_follows_account_type() {
+        use std::fs;
+        let root = TempDir::new("user-type");
+        for (id, template) in [("1.12.2", "--username ${auth_player_name} --userType ${user_type}"), ("1.6.4", "--username ${auth_player_name}")].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "",
+                "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "{}"
+            }}"#, id, template)).unwrap();
+        }
+        let args_of = |id: &str, account_type: yggdrasil::AccountType| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "token", account_type).unwrap();
+            test_launcher(root.as_path()).auth(auth_info).build().to_arguments(id).unwrap().args()
+        };
+        let user_type_of = |args: Vec<String>| args.iter().position(|arg| arg == "--userType").map(|i| args[i + 1].clone());
+        assert_eq!(user_type_of(args_of("1.12.2", yggdrasil::AccountType::Msa)), Some("msa".to_owned()));
+        assert_eq!(user_type_of(args_of("1.12.2", yggdrasil::AccountType::Legacy)), Some("legacy".to_owned()));
+        assert_eq!(user_type_of(args_of("1.12.2", yggdrasil::AccountType::Mojang)), Some("mojang".to_owned()));
+        // versions without the option in their template never receive it
+        assert_eq!(user_type_of(args_of("1.6.4", yggdrasil::AccountType::Msa)), None);
+    }
+
+    #[test]
+    fn positional_versions_receive_no_modern_options() {
+        use std::fs;
+        let root = TempDir::new("positional-options");
+        fs::create_dir_all(root.join("versions/1.5.2")).unwrap();
+        // a hand-written profile keeps the options of a newer template along with literal values
+        fs::write(root.join("versions/1.5.2/1.5.2.json"), r#"{
+            "id": "1.5.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.Minecraft",
+            "minecraftArguments": "${auth_player_name} ${auth_session} --userType legacy --versionType release"
+        }"#).unwrap();
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "token", yggdrasil::AccountType::Legacy).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info)
+            .resolution(1280, 720).disable_chat(true).proxy("127.0.0.1", 1080).build();
+        let args = launcher.to_arguments("1.5.2").unwrap().args();
+        let main_class = args.iter().position(|arg| arg == "net.minecraft.client.Minecraft").unwrap();
+        assert_eq!(&args[main_class + 1..], &["Alex".to_owned(), "token:token:853c80ef3c3749fdaa49938b674adae6".to_owned()]);
+    }
+
+    #[test]
+    fn telemetry_and_chat_flags_are_emitted() {
+        use std::fs;
+        let root = TempDir::new("telemetry-flags");
+        fs::create_dir_all(root.join("versions/1.19.2")).unwrap();
+        fs::write(root.join("versions/1.19.2/1.19.2.json"), r#"{
+            "id": "1.19.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "game": ["--username", "${auth_player_name}"], "jvm": ["-cp", "${classpath}"] }
+        }"#).unwrap();
+        let args = test_launcher(root.as_path()).telemetry_host("").disable_chat(true).build().to_arguments("1.19.2").unwrap().args();
+        let main_class = args.iter().position(|arg| arg == "net.minecraft.client.main.Main").unwrap();
+        assert!(args[..main_class].contains(&"-Dminecraft.api.telemetry.host=".to_owned()));
+        assert!(args[main_class + 1..].contains(&"--disableChat".to_owned()));
+        let args = test_launcher(root.as_path()).build().to_arguments("1.19.2").unwrap().args();
+        assert!(!args.iter().any(|arg| arg.starts_with("-Dminecraft.api.telemetry.host=") || arg == "--disableChat"));
+    }
+
+    #[test]
+    fn classpath_separator_can_be_forced() {
+        use std::fs;
+        let root = TempDir::new("classpath-separator");
+        fs::create_dir_all(root.join("versions/1.19.2")).unwrap();
+        fs::write(root.join("versions/1.19.2/1.19.2.json"), r#"{
+            "id": "1.19.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "game": [], "jvm": ["-Dseparator=${classpath_separator}", "-cp", "${classpath}"] },
+            "libraries": [{ "name": "com.example:library:1.0" }]
+        }"#).unwrap();
+        fs::create_dir_all(root.join("libraries/com/example/library/1.0")).unwrap();
+        fs::write(root.join("libraries/com/example/library/1.0/library-1.0.jar"), b"").unwrap();
+        let args = test_launcher(root.as_path()).classpath_separator(";").build().to_arguments("1.19.2").unwrap().args();
+        let classpath = format!("{};{}", root.join("libraries/com/example/library/1.0/library-1.0.jar").display(),
+                                root.join("versions/1.19.2/1.19.2.jar").display());
+        let cp = args.iter().position(|arg| arg == "-cp").unwrap();
+        assert_eq!(args[cp + 1], classpath);
+        assert!(args.contains(&"-Dseparator=;".to_owned()));
+    }
+
+    #[test]
+    fn primary_jar_override_is_launched() {
+        use std::fs;
+        let root = TempDir::new("primary-jar-override");
+        fs::create_dir_all(root.join("versions/1.19.2")).unwrap();
+        fs::write(root.join("versions/1.19.2/1.19.2.json"), r#"{
+            "id": "1.19.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": { "game": [], "jvm": ["-Dprimary=${primary_jar}", "-cp", "${classpath}"] }
+        }"#).unwrap();
+        let patched = root.join("patched/client.jar");
+        let args = test_launcher(root.as_path()).primary_jar(patched.as_path()).build().to_arguments("1.19.2").unwrap().args();
+        assert!(args.contains(&format!("-Dprimary={}", patched.display())));
+        let cp = args.iter().position(|arg| arg == "-cp").unwrap();
+        assert_eq!(args[cp + 1], patched.to_str().unwrap());
+        assert!(!args.iter().any(|arg| arg.contains("1.19.2.jar")));
+    }
+
+    #[test]
+    fn options_are_tagged_by_their_source() {
+        use std::fs;
+        let root = TempDir::new("option-sources");
+        for (id, body) in [("1.19.2", r#""arguments": { "game": ["--username", "${auth_player_name}"], "jvm": ["-cp", "${classpath}"] }"#),
+                           ("fabric", r#""inheritsFrom": "1.19.2", "arguments": { "game": ["--loaderFlag"], "jvm": [] }"#),
+                           ("1.12.2", r#""minecraftArguments": "--username ${auth_player_name}""#),
+                           ("1.12.2-forge", r#""inheritsFrom": "1.12.2", "minecraftArguments": "--username ${auth_player_name} --tweakClass Tweaker""#)].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main", {}
+            }}"#, id, body)).unwrap();
+        }
+        let launcher = test_launcher(root.as_path()).jvm_args(&["-Dexample=true"]).disable_chat(true).build();
+        for id in ["fabric", "1.12.2-forge"].iter() {
+            let by_source = launcher.to_arguments(id).unwrap().args_by_source();
+            let user = &by_source[&OptionSource::User];
+            assert!(user.contains(&"-Dexample=true".to_owned()) && user.contains(&"--disableChat".to_owned()), "{}", id);
+            assert!(by_source[&OptionSource::Version].windows(2).any(|pair| pair == ["--username", "Steve"]), "{}", id);
+            assert!(!by_source[&OptionSource::Loader].contains(&"--username".to_owned()), "{}", id);
+        }
+        let loader_args = |id: &str| launcher.to_arguments(id).unwrap().args_by_source()[&OptionSource::Loader].clone();
+        assert_eq!(loader_args("fabric"), ["--loaderFlag"]);
+        assert_eq!(loader_args("1.12.2-forge"), ["--tweakClass", "Tweaker"]);
+    }
+
+    #[test]
+    fn snapshots_get_preview_features() {
+        use std::fs;
+        let root = TempDir::new("snapshot-flags");
+        for &(id, version_type, java) in [("23w31a", "snapshot", 17), ("1.20.1", "release", 17), ("13w16a", "snapshot", 8)].iter() {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "{}", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+                "javaVersion": {{ "component": "java-runtime", "majorVersion": {} }},
+                "arguments": {{ "game": [], "jvm": ["-cp", "${{classpath}}"] }}
+            }}"#, id, version_type, java)).unwrap();
+        }
+        let launcher = test_launcher(root.as_path()).build();
+        let enables_preview = |id: &str| launcher.to_arguments(id).unwrap().args().iter().filter(|arg| *arg == "--enable-preview").count();
+        assert_eq!(enables_preview("23w31a"), 1);
+        assert_eq!(enables_preview("1.20.1"), 0);
+        // java 8 refuses to start with the flag
+        assert_eq!(enables_preview("13w16a"), 0);
+        let launcher = test_launcher(root.as_path()).version_kind_jvm_options(versions::VersionKind::Snapshot, &["--enable-preview"]).build();
+        assert_eq!(launcher.to_arguments("23w31a").unwrap().args().iter().filter(|arg| *arg == "--enable-preview").count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn environment_is_captured_without_the_token() {
+        use std::fs;
+        let java = fake_program("java-snapshot", "echo 'openjdk version \"17.0.2\"' >&2\necho 'OpenJDK 64-Bit Server VM' >&2");
+        let root = TempDir::new("capture-environment");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--accessToken ${auth_access_token}"
+        }"#).unwrap();
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let launcher = test_launcher(root.as_path()).jre(path::Path::new(&java)).auth(auth_info).build();
+        let snapshot = launcher.to_arguments("1.12.2").unwrap().capture_environment();
+        assert_eq!(snapshot.java_program_path(), java);
+        assert_eq!(snapshot.java_version(), Some("17.0.2"));
+        assert_eq!(snapshot.arch(), env::consts::ARCH);
+        assert!(snapshot.args().windows(2).any(|pair| pair == ["--accessToken", "<redacted>"]));
+        let json = snapshot.to_json();
+        assert!(json.contains(&java) && json.contains(env::consts::ARCH));
+        assert!(!json.contains("secret"));
+    }
+
+    #[test]
+    fn profile_game_dir_keeps_the_shared_root() {
+        use std::fs;
+        let root = TempDir::new("shared-root");
+        let profile = TempDir::new("profile-dir");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "minecraftArguments": "--gameDir ${game_directory} --assetsDir ${assets_root}",
+            "libraries": [{ "name": "com.example:library:1.0" }]
+        }"#).unwrap();
+        fs::create_dir_all(root.join("libraries/com/example/library/1.0")).unwrap();
+        fs::write(root.join("libraries/com/example/library/1.0/library-1.0.jar"), b"").unwrap();
+        let launcher = test_launcher(root.as_path()).profile_game_dir(profile.as_path()).build();
+        let args = launcher.to_arguments("1.12.2").unwrap().args();
+        assert!(args.windows(2).any(|pair| pair[0] == "--gameDir" && pair[1] == profile.to_str().unwrap()), "{:?}", args);
+        assert!(args.windows(2).any(|pair| pair[0] == "--assetsDir" && path::Path::new(&pair[1]).starts_with(root.as_path())));
+        let classpath = &args[args.iter().position(|arg| arg == "-cp").unwrap() + 1];
+        assert_eq!(classpath, &format!("{}{}{}", root.join("libraries/com/example/library/1.0/library-1.0.jar").display(),
+                                       versions::CLASSPATH_SEPARATOR, root.join("versions/1.12.2/1.12.2.jar").display()));
+    }
+
+    #[test]
+    fn consumed_placeholders_are_recorded() {
+        use std::fs;
+        let root = TempDir::new("consumed-placeholders");
+        fs::create_dir_all(root.join("versions/modern")).unwrap();
+        fs::write(root.join("versions/modern/modern.json"), r#"{
+            "id": "modern", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "arguments": {
+                "game": ["--username", "${auth_player_name}", "--version", "${version_name}",
+                         { "rules": [{ "action": "allow", "features": { "is_demo_user": true } }], "value": ["--demo", "${demo_key}"] }],
+                "jvm": ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}"]
+            }
+        }"#).unwrap();
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "token", yggdrasil::AccountType::Msa).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info).build();
+        let arguments = launcher.compute_arguments("modern").unwrap();
+        assert_eq!(arguments.consumed_placeholders(), ["auth_player_name", "classpath", "natives_directory",
+                                                       "resolution_height", "resolution_width", "version_name"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn java_version_file_selects_matching_jre() {
+        use std::fs;
+        let root = TempDir::new("java-version-file");
+        let probes = root.join("probes.log");
+        let java_script = |version: &str| format!(concat!(
+            "echo {} >> '{}'\n",
+            "echo 'openjdk version \"{}\"' >&2\n",
+            "echo 'OpenJDK 64-Bit Server VM' >&2"), version, probes.display(), version);
+        let java_17 = fake_program("java-17-toolchain", &java_script("17.0.2"));
+        let java_21 = fake_program("java-21-toolchain", &java_script("21.0.1"));
+        fs::create_dir_all(root.join("versions/simple")).unwrap();
+        fs::write(root.join("versions/simple/simple.json"), r#"{
+            "id": "simple", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        let launcher = || {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            let candidates = [path::Path::new(&java_17), path::Path::new(&java_21)];
+            builder().root_dir(root.as_path()).jre_candidates(&candidates).auth(auth_info).build()
+        };
+        let java_program_path = || launcher().to_arguments("simple").unwrap().java_program_path;
+        assert_eq!(java_program_path(), java_17);
+        fs::write(root.join(".sdkmanrc"), "# sdk env\njava=21.0.1-tem\n").unwrap();
+        // the candidates are only asked for their version once a launch is computed
+        let _ = fs::remove_file(probes.as_path());
+        let built = launcher();
+        assert!(!probes.exists());
+        assert_eq!(built.to_arguments("simple").unwrap().java_program_path, java_21);
+        assert!(probes.is_file());
+        assert_eq!(java_program_path(), java_21);
+        fs::write(root.join(".java-version"), "temurin-17\n").unwrap();
+        assert_eq!(java_program_path(), java_17);
+        assert_eq!(read_java_version_file(root.as_path()), Some("temurin-17".to_owned()));
+    }
+
+    #[test]
+    #[cfg(feature = "progress-stream")]
+    fn install_progress_is_streamed() {
+        use std::fs;
+        use futures::{Future, Stream};
+        let root = TempDir::new("install-stream");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "downloads": { "client": { "url": "https://example.com/client.jar", "sha1": "", "size": 6 } },
+            "libraries": [
+                { "name": "com.example:library:1.0", "downloads": { "artifact": { "url": "https://example.com/library.jar", "sha1": "", "size": 4 } } }
+            ]
+        }"#).unwrap();
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info).build();
+        let stream = launcher.into_install_stream_with("1.12.2", |download, on_bytes| {
+            let content: &[u8] = if download.url() == "https://example.com/client.jar" { b"client" } else { b"lib!" };
+            on_bytes(content.len() as u64);
+            fs::write(download.path(), content).map_err(versions::Error::from)
+        });
+        let progress = |files_done, bytes_done| ProgressEvent::Progress(InstallProgress { files_done, files_total: 2, bytes_done, bytes_total: 10 });
+        assert_eq!(stream.collect().wait().unwrap(), vec![
+            progress(0, 0),
+            progress(0, 6),
+            ProgressEvent::Downloaded(root.join("versions/1.12.2/1.12.2.jar")),
+            progress(1, 6),
+            progress(1, 10),
+            ProgressEvent::Downloaded(root.join("libraries/com/example/library/1.0/library-1.0.jar")),
+            progress(2, 10)
+        ]);
+        assert_eq!(fs::read(root.join("versions/1.12.2/1.12.2.jar")).unwrap(), b"client");
+    }
+
+    #[test]
+    fn cancelled_install_removes_the_fetched_files() {
+        use std::fs;
+        use std::thread;
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let _ = stream.read(&mut [0u8; 4096]).unwrap();
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\njar!");
+        });
+        let root = TempDir::new("install-cancel");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), format!(r#"{{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "downloads": {{ "client": {{ "url": "{0}/client.jar", "size": 4 }} }},
+            "libraries": [
+                {{ "name": "com.example:library:1.0", "downloads": {{ "artifact": {{ "url": "{0}/library.jar", "size": 4 }} }} }}
+            ]
+        }}"#, host)).unwrap();
+        let launcher = test_launcher(root.as_path()).build();
+        let cancelled = AtomicBool::new(false);
+        let result = launcher.install_version_cancellable("1.12.2", |progress| {
+            if progress.files_done == 1 { cancelled.store(true, Ordering::SeqCst); }
+        }, &cancelled);
+        match result {
+            Result::Err(versions::Error::Cancelled) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ()).map_err(|e| e.user_message())),
+        }
+        assert!(!root.join("versions/1.12.2/1.12.2.jar").exists());
+        assert!(!root.join("libraries/com/example/library/1.0/library-1.0.jar").exists());
+        assert!(launcher.install_version("1.12.2", |_| ()).is_ok());
+        assert_eq!(fs::read(root.join("versions/1.12.2/1.12.2.jar")).unwrap(), b"jar!");
+        match launcher.verify_installation_cancellable("1.12.2", 2, Arc::new(AtomicBool::new(true))) {
+            Result::Err(versions::Error::Cancelled) => (),
+            other => panic!("unexpected result: {:?}", other.map_err(|e| e.user_message())),
+        }
+    }
+
+    #[test]
+    fn install_progress_counts_files_and_bytes() {
+        use std::fs;
+        let root = TempDir::new("install-progress");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "",
+            "assetIndex": { "id": "1.12", "url": "https://example.com/1.12.json", "sha1": "", "size": 1, "totalSize": 2 },
+            "downloads": { "client": { "url": "https://example.com/client.jar", "sha1": "", "size": 6 } },
+            "libraries": [
+                { "name": "com.example:library:1.0", "downloads": { "artifact": { "url": "https://example.com/library.jar", "sha1": "", "size": 4 } } }
+            ]
+        }"#).unwrap();
+        let hash = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        let index = format!(r#"{{ "objects": {{ "icons/icon.png": {{ "hash": "{}", "size": 3 }} }} }}"#, hash);
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+        let launcher = test_launcher(root.as_path()).auth(auth_info).build();
+        let mut events = Vec::new();
+        let result = launcher.install_version_with("1.12.2", |download, on_bytes| {
+            let content: &[u8] = match download.url() {
+                "https://example.com/client.jar" => b"client",
+                "https://example.com/library.jar" => b"lib!",
+                "https://example.com/1.12.json" => index.as_bytes(),
+                _ => b"abc"
+            };
+            // every file arrives in two chunks
+            let (first, second) = content.split_at(content.len() / 2);
+            on_bytes(first.len() as u64);
+            on_bytes(second.len() as u64);
+            fs::write(download.path(), content).map_err(versions::Error::from)
+        }, |progress| events.push((progress.files_done(), progress.files_total(), progress.bytes_done(), progress.bytes_total())));
+        let total = 6 + 4 + index.len() as u64 + 3;
+        assert_eq!(result.unwrap(), InstallProgress { files_done: 4, files_total: 4, bytes_done: total, bytes_total: total });
+        assert_eq!(events.first(), Some(&(0, 3, 0, 10)));
+        assert!(events.contains(&(1, 3, 6, 10)));
+        assert!(events.contains(&(3, 4, total - 3, total)));
+        assert_eq!(events.last(), Some(&(4, 4, total, total)));
+        assert!(events.windows(2).all(|pair| pair[0].0 <= pair[1].0 && pair[0].2 <= pair[1].2));
+        assert!(root.join("assets/objects/a9").join(hash).is_file());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn java_failures_are_explained() {
+        use std::fs;
+        let java_8 = fake_program("java-8-explained", concat!(
+            "echo 'openjdk version \"1.8.0_312\"' >&2\n",
+            "echo 'OpenJDK 64-Bit Server VM' >&2"));
+        let root = TempDir::new("java-explained");
+        fs::create_dir_all(root.join("versions/1.18")).unwrap();
+        fs::write(root.join("versions/1.18/1.18.json"), r#"{
+            "id": "1.18", "type": "release", "time": "", "releaseTime": "", "mainClass": "net.minecraft.client.main.Main",
+            "javaVersion": { "component": "java-runtime-gamma", "majorVersion": 17 }, "arguments": { "game": [], "jvm": [] }
+        }"#).unwrap();
+        let message = |program: &str| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            let launcher = test_launcher(root.as_path()).jre(path::Path::new(program)).auth(auth_info).build();
+            launcher.check_java("1.18").unwrap_err().user_message()
+        };
+        assert_eq!(message(&java_8), format!("Java 8 at {} is too old for this version; install Java 17 and select it.", java_8));
+        assert_eq!(message("/opt/missing/java"), "No compatible Java found; install Java 17 or point the launcher to it.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn user_gc_flags_are_probed_once_on_request() {
+        use std::fs;
+        let root = TempDir::new("gc-probe");
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--username ${auth_player_name}"
+        }"#).unwrap();
+        let probes = root.join("probes.log");
+        let java_8 = fake_program("java-8-gc-probe", &format!(concat!(
+            "case \"$1\" in -XX:+UseZGC) echo \"$1\" >> '{}'; echo 'Unrecognized VM option UseZGC' >&2; exit 1;; esac\n",
+            "echo 'openjdk version \"1.8.0_301\"' >&2"), probes.display()));
+        let launcher = |java: &str, jvm_args: &[&str], probe: bool| {
+            let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "secret", yggdrasil::AccountType::Mojang).unwrap();
+            test_launcher(root.as_path()).jre(path::Path::new(java)).auth(auth_info).jvm_args(jvm_args).probe_gc_flags(probe).build()
+        };
+        // the defaults do not depend on the runtime being runnable
+        let args = launcher(root.join("missing-java").to_str().unwrap(), &[], true).to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&"-XX:+UseG1GC".to_owned()), "{:?}", args);
+        let args = launcher(&java_8, &["-XX:+UseZGC"], false).to_arguments("1.12.2").unwrap().args();
+        assert!(args.contains(&"-XX:+UseZGC".to_owned()) && !args.contains(&"-XX:+UseG1GC".to_owned()), "{:?}", args);
+        assert!(!probes.exists());
+        let probing = launcher(&java_8, &["-XX:+UseZGC"], true);
+        for _ in 0..2 {
+            let args = probing.to_arguments("1.12.2").unwrap().args();
+            assert!(!args.contains(&"-XX:+UseZGC".to_owned()) && args.contains(&"-XX:+UseG1GC".to_owned()), "{:?}", args);
+        }
+        assert_eq!(fs::read_to_string(probes).unwrap(), "-XX:+UseZGC\n");
     }
 }
@@ -1,23 +1,66 @@
+extern crate base64;
 extern crate futures;
 extern crate hyper;
 extern crate hyper_tls;
+#[cfg(unix)]
+extern crate libc;
+#[macro_use]
+extern crate log;
+extern crate md5;
 extern crate serde;
 #[macro_use]
 extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
+extern crate sha1;
 extern crate tokio_core;
+extern crate toml;
 extern crate uuid;
 extern crate zip;
 
 pub mod launcher;
 pub mod parsing;
+pub mod profiles;
 pub mod requests;
 pub mod versions;
 pub mod yggdrasil;
 
 #[cfg(test)]
 mod tests {
+    use std::env;
+    use std::fs;
+    use std::ops;
+    use std::path::PathBuf;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub fn new(name: &str) -> TempDir {
+            // each test gets its own directory, so parallel and repeated runs never share state
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let unique = format!("rmcll-test-{}-{}-{}", name, process::id(), COUNTER.fetch_add(1, Ordering::SeqCst));
+            let path_buf = env::temp_dir().join(unique);
+            fs::create_dir_all(path_buf.as_path()).unwrap();
+            TempDir(path_buf)
+        }
+    }
+
+    impl ops::Deref for TempDir {
+        type Target = PathBuf;
+
+        fn deref(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.0.as_path());
+        }
+    }
+
     #[test]
     fn start_minecraft() {
         use std::env;
@@ -28,6 +28,7 @@ impl<'a> Iterator for ArgumentIterator<'a> {
     type Item = String;
 
     fn next(&mut self) -> Option<String> {
+        if self.chars[self.index..].iter().all(|c| c.is_whitespace()) { return None; }
         let (index, result) = parse_whole_string(&self.chars, self.index, &self.strategy);
         self.index = index;
         result
@@ -38,6 +39,43 @@ pub fn parse<'a>(string: &str, strategy: &'a ParameterStrategy) -> ArgumentItera
     ArgumentIterator { strategy, chars: string.chars().collect(), index: 0 }
 }
 
+pub fn substitute(string: &str, strategy: &ParameterStrategy) -> String {
+    match *strategy {
+        ParameterStrategy::Ignore => string.to_owned(),
+        ParameterStrategy::Map(ref b) => {
+            let mut result = String::new();
+            let mut rest = string;
+            while let Some(start) = rest.find("${") {
+                match rest[start..].find('}') {
+                    Some(end) => {
+                        result.push_str(&rest[..start]);
+                        result.push_str(&b.as_ref()(rest[start + 2..start + end].to_owned()));
+                        rest = &rest[start + end + 1..];
+                    }
+                    None => break
+                }
+            }
+            result.push_str(rest);
+            result
+        }
+    }
+}
+
+pub fn placeholders(string: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut rest = string;
+    while let Some(start) = rest.find("${") {
+        match rest[start..].find('}') {
+            Some(end) => {
+                result.push(rest[start + 2..start + end].to_owned());
+                rest = &rest[start + end + 1..];
+            }
+            None => break
+        }
+    }
+    result
+}
+
 fn parse_whole_string(chars: &Vec<char>, original_pos: usize, strategy: &ParameterStrategy) -> (usize, Option<String>) {
     let mut index = original_pos;
     let mut result: String = String::new();
@@ -192,3 +230,22 @@ fn parse_dollar_parameters(chars: &Vec<char>, pos: usize, strategy: &ParameterSt
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iteration_stops_at_trailing_whitespace() {
+        let strategy = ParameterStrategy::map(|s| if s == "empty" { String::new() } else { s.to_uppercase() });
+        let args: Vec<String> = parse("--username ${name} --assetIndex ${empty} --demo  ", &strategy).collect();
+        assert_eq!(args, vec!["--username", "NAME", "--assetIndex", "", "--demo"]);
+    }
+
+    #[test]
+    fn substitution_keeps_whitespace_in_place() {
+        let strategy = ParameterStrategy::map(|s| s.to_uppercase());
+        assert_eq!(substitute("-Dos.name=Windows 10 ${name}/${dir", &strategy), "-Dos.name=Windows 10 NAME/${dir");
+        assert_eq!(placeholders("${library_directory}/${version_name}.jar"), vec!["library_directory", "version_name"]);
+    }
+}
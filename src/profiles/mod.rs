@@ -0,0 +1,338 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::result::Result;
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json;
+
+use launcher;
+use parsing;
+use versions;
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct LauncherProfile {
+    #[serde(skip)]
+    key: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(rename = "lastVersionId")]
+    last_version_id: Option<String>,
+    #[serde(rename = "javaArgs")]
+    java_args: Option<String>,
+    #[serde(rename = "javaDir")]
+    java_dir: Option<String>,
+    #[serde(rename = "gameDir")]
+    game_dir: Option<String>,
+    resolution: Option<ProfileResolution>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct ProfileResolution {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct LauncherProfiles {
+    #[serde(default)]
+    profiles: BTreeMap<String, LauncherProfile>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InstanceProfile {
+    name: String,
+    version_id: String,
+    minecraft_version: String,
+    loaders: Vec<(String, String)>,
+    java_path: Option<String>,
+    min_memory_mib: Option<f32>,
+    max_memory_mib: Option<f32>,
+    jvm_args: Option<String>,
+    game_dir: PathBuf,
+}
+
+#[derive(Deserialize, Debug)]
+struct InstancePack {
+    #[serde(default)]
+    components: Vec<InstanceComponent>,
+}
+
+#[derive(Deserialize, Debug)]
+struct InstanceComponent {
+    uid: String,
+    version: Option<String>,
+    #[serde(rename = "cachedVersion")]
+    cached_version: Option<String>,
+}
+
+pub fn read_launcher_profiles(path: &Path) -> Result<Vec<LauncherProfile>, versions::Error> {
+    let profiles: LauncherProfiles = serde_json::from_reader(fs::File::open(path)?)?;
+    Result::Ok(profiles.profiles.into_iter().map(|(key, mut profile)| {
+        profile.key = key;
+        profile
+    }).collect())
+}
+
+pub fn read_instance(instance_dir: &Path) -> Result<InstanceProfile, versions::Error> {
+    let pack_path = instance_dir.join("mmc-pack.json");
+    if !pack_path.is_file() {
+        return Result::Err(versions::Error::FileUnavailableError(pack_path.into_boxed_path()));
+    }
+    let pack: InstancePack = serde_json::from_reader(fs::File::open(pack_path)?)?;
+    let config = match fs::read_to_string(instance_dir.join("instance.cfg")) {
+        Result::Ok(content) => parse_instance_config(&content),
+        Result::Err(_) => HashMap::new()
+    };
+    let mut minecraft_version = String::new();
+    let mut loaders = Vec::new();
+    for component in pack.components {
+        let version = match component.version.or(component.cached_version) {
+            Some(version) => version,
+            None => continue
+        };
+        match component.uid.as_str() {
+            "net.minecraft" => minecraft_version = version,
+            "net.minecraftforge" => loaders.push(("forge".to_owned(), version)),
+            "net.neoforged" => loaders.push(("neoforge".to_owned(), version)),
+            "net.fabricmc.fabric-loader" => loaders.push(("fabric".to_owned(), version)),
+            "org.quiltmc.quilt-loader" => loaders.push(("quilt".to_owned(), version)),
+            _ => ()
+        }
+    }
+    if minecraft_version.is_empty() {
+        return Result::Err(versions::Error::InvalidVersionId(String::new()));
+    }
+    // the same ids as those generated by the official installers of each loader
+    let version_id = match loaders.first().map(|(loader, version)| (loader.as_str(), version)) {
+        Some(("forge", version)) => format!("{}-forge-{}", minecraft_version, version),
+        Some(("neoforge", version)) => format!("neoforge-{}", version),
+        Some(("fabric", version)) => format!("fabric-loader-{}-{}", version, minecraft_version),
+        Some((_, version)) => format!("quilt-loader-{}-{}", version, minecraft_version),
+        None => minecraft_version.clone()
+    };
+    let overridden = |key: &str| config.get(key).map(String::as_str) != Some("false");
+    let setting = |key: &str, override_key: &str| config.get(key).filter(|v| !v.is_empty() && overridden(override_key)).cloned();
+    let memory = |key: &str| setting(key, "OverrideMemory").and_then(|v| v.parse::<f32>().ok());
+    let game_dir = [".minecraft", "minecraft"].iter().map(|name| instance_dir.join(name))
+        .find(|dir| dir.is_dir()).unwrap_or_else(|| instance_dir.join(".minecraft"));
+    Result::Ok(InstanceProfile {
+        name: config.get("name").cloned().unwrap_or_else(|| version_id.clone()),
+        java_path: setting("JavaPath", "OverrideJavaLocation"),
+        min_memory_mib: memory("MinMemAlloc"),
+        max_memory_mib: memory("MaxMemAlloc"),
+        jvm_args: setting("JvmArgs", "OverrideJavaArgs"),
+        version_id,
+        minecraft_version,
+        loaders,
+        game_dir,
+    })
+}
+
+fn parse_instance_config(content: &str) -> HashMap<String, String> {
+    content.lines().map(str::trim).filter(|line| !line.starts_with('[') && !line.starts_with('#')).filter_map(|line| {
+        let mut parts = line.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => Some((key.trim().to_owned(), value.trim().to_owned())),
+            _ => None
+        }
+    }).collect()
+}
+
+impl InstanceProfile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version_id(&self) -> &str {
+        &self.version_id
+    }
+
+    pub fn minecraft_version(&self) -> &str {
+        &self.minecraft_version
+    }
+
+    pub fn loaders(&self) -> &[(String, String)] {
+        &self.loaders
+    }
+
+    pub fn java_path(&self) -> Option<PathBuf> {
+        self.java_path.as_ref().map(PathBuf::from)
+    }
+
+    pub fn memory_mib(&self) -> (Option<f32>, Option<f32>) {
+        (self.min_memory_mib, self.max_memory_mib)
+    }
+
+    pub fn jvm_args(&self) -> Vec<String> {
+        let strategy = parsing::ParameterStrategy::map(|s| format!("${{{}}}", s));
+        match self.jvm_args {
+            Some(ref args) => parsing::parse(args, &strategy).collect(),
+            None => Vec::new()
+        }
+    }
+
+    pub fn game_dir(&self) -> &Path {
+        &self.game_dir
+    }
+
+    pub fn apply_to(&self, builder: launcher::MinecraftLauncherBuilder) -> launcher::MinecraftLauncherBuilder {
+        let mut builder = builder.profile_game_dir(self.game_dir.as_path());
+        if let Some(java_path) = self.java_path() {
+            builder = builder.jre(java_path.as_path());
+        }
+        if let Some(min_memory_mib) = self.min_memory_mib {
+            builder = builder.min_memory(min_memory_mib);
+        }
+        if let Some(max_memory_mib) = self.max_memory_mib {
+            builder = builder.max_memory(max_memory_mib);
+        }
+        let jvm_args = self.jvm_args();
+        builder.jvm_args(&jvm_args.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+}
+
+impl LauncherProfile {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_ref().unwrap_or(&self.key)
+    }
+
+    pub fn last_version_id(&self) -> Option<&str> {
+        self.last_version_id.as_deref()
+    }
+
+    pub fn java_args(&self) -> Vec<String> {
+        let strategy = parsing::ParameterStrategy::map(|s| format!("${{{}}}", s));
+        match self.java_args {
+            Some(ref args) => parsing::parse(args, &strategy).collect(),
+            None => Vec::new()
+        }
+    }
+
+    pub fn java_dir(&self) -> Option<PathBuf> {
+        self.java_dir.as_ref().map(PathBuf::from)
+    }
+
+    pub fn game_dir(&self) -> Option<PathBuf> {
+        self.game_dir.as_ref().map(PathBuf::from)
+    }
+
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        self.resolution.map(|r| (r.width, r.height))
+    }
+
+    pub fn apply_to(&self, builder: launcher::MinecraftLauncherBuilder) -> launcher::MinecraftLauncherBuilder {
+        let mut builder = builder;
+        if let Some(java_dir) = self.java_dir() {
+            builder = builder.jre(java_dir.as_path());
+        }
+        if let Some(game_dir) = self.game_dir() {
+            builder = builder.profile_game_dir(game_dir.as_path());
+        }
+        if let Some((width, height)) = self.resolution() {
+            builder = builder.resolution(width, height);
+        }
+        let java_args = self.java_args();
+        builder.jvm_args(&java_args.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use tests::TempDir;
+    use yggdrasil;
+
+    fn launch_args(builder: launcher::MinecraftLauncherBuilder) -> (String, Vec<String>) {
+        let profile = yggdrasil::Profile::new(Uuid::new_v4(), "Steve".to_owned(), HashMap::new());
+        let arguments = builder.auth(yggdrasil::AuthInfo::new(Uuid::new_v4(), profile)).build().to_arguments("1.12.2").unwrap();
+        (arguments.program(), arguments.args())
+    }
+
+    fn prepare_root(root: &Path) {
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "minecraftArguments": "--gameDir ${game_directory}"
+        }"#).unwrap();
+    }
+
+    #[test]
+    fn legacy_launcher_profiles_are_imported() {
+        let root = TempDir::new("launcher-profiles");
+        prepare_root(root.as_path());
+        let game_dir = root.join("forge");
+        fs::write(root.join("launcher_profiles.json"), json!({
+            "profiles": {
+                "forge": {
+                    "name": "Forge", "lastVersionId": "1.12.2-forge", "javaDir": "/usr/bin/java", "gameDir": game_dir,
+                    "javaArgs": "-Xmx4G -Dfoo=\"bar baz\"", "resolution": { "width": 1280, "height": 720 }
+                },
+                "vanilla": {}
+            },
+            "selectedProfile": "forge"
+        }).to_string()).unwrap();
+        let profiles = read_launcher_profiles(root.join("launcher_profiles.json").as_path()).unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name(), "Forge");
+        assert_eq!(profiles[0].last_version_id(), Some("1.12.2-forge"));
+        assert_eq!(profiles[0].java_args(), vec!["-Xmx4G", "-Dfoo=bar baz"]);
+        assert_eq!(profiles[0].resolution(), Some((1280, 720)));
+        assert_eq!(profiles[1].name(), "vanilla");
+        assert_eq!(profiles[1].last_version_id(), None);
+        assert!(profiles[1].java_args().is_empty());
+        let (program, args) = launch_args(profiles[0].apply_to(launcher::builder().root_dir(root.as_path())));
+        assert_eq!(program, "/usr/bin/java");
+        let main_class = args.iter().position(|arg| arg == "net.minecraft.client.main.Main").unwrap();
+        assert!(args[..main_class].contains(&"-Xmx4G".to_owned()) && args[..main_class].contains(&"-Dfoo=bar baz".to_owned()));
+        assert_eq!(&args[main_class + 1..], &["--gameDir".to_owned(), game_dir.to_str().unwrap().to_owned(),
+                                               "--width".to_owned(), "1280".to_owned(), "--height".to_owned(), "720".to_owned()]);
+        let (_, args) = launch_args(profiles[1].apply_to(launcher::builder().root_dir(root.as_path()).jre(Path::new("java"))));
+        assert!(args.windows(2).any(|pair| pair[0] == "--gameDir" && pair[1] == root.to_str().unwrap()));
+    }
+
+    #[test]
+    fn instances_are_imported() {
+        let root = TempDir::new("instance-root");
+        prepare_root(root.as_path());
+        let instance_dir = root.join("instance");
+        fs::create_dir_all(instance_dir.join(".minecraft")).unwrap();
+        fs::write(instance_dir.join("mmc-pack.json"), r#"{
+            "components": [
+                { "uid": "org.lwjgl3", "version": "3.3.1" },
+                { "uid": "net.minecraft", "version": "1.20.1" },
+                { "uid": "net.fabricmc.intermediary", "version": "1.20.1" },
+                { "uid": "net.fabricmc.fabric-loader", "cachedVersion": "0.14.22" }
+            ],
+            "formatVersion": 1
+        }"#).unwrap();
+        fs::write(instance_dir.join("instance.cfg"), "[General]\r\nname=Fabric\r\nOverrideMemory=true\r\n\
+            MinMemAlloc=512\r\nMaxMemAlloc=4096\r\nOverrideJavaLocation=false\r\nJavaPath=/usr/bin/java\r\n\
+            JvmArgs=-Dfoo=\"bar baz\"\r\n").unwrap();
+        let instance = read_instance(instance_dir.as_path()).unwrap();
+        assert_eq!(instance.name(), "Fabric");
+        assert_eq!(instance.version_id(), "fabric-loader-0.14.22-1.20.1");
+        assert_eq!(instance.minecraft_version(), "1.20.1");
+        assert_eq!(instance.loaders(), &[("fabric".to_owned(), "0.14.22".to_owned())]);
+        assert_eq!(instance.memory_mib(), (Some(512f32), Some(4096f32)));
+        assert_eq!(instance.java_path(), None);
+        assert_eq!(instance.jvm_args(), vec!["-Dfoo=bar baz"]);
+        assert_eq!(instance.game_dir(), instance_dir.join(".minecraft").as_path());
+        let (program, args) = launch_args(instance.apply_to(launcher::builder().root_dir(root.as_path()).jre(Path::new("java"))));
+        assert_eq!(program, "java");
+        let main_class = args.iter().position(|arg| arg == "net.minecraft.client.main.Main").unwrap();
+        for expected in ["-Xmn512m", "-Xmx4096m", "-Dfoo=bar baz"].iter() {
+            assert!(args[..main_class].contains(&expected.to_string()), "{}", expected);
+        }
+        assert_eq!(&args[main_class + 1..main_class + 3], &["--gameDir".to_owned(), instance_dir.join(".minecraft").to_str().unwrap().to_owned()]);
+    }
+}
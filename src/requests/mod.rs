@@ -1,19 +1,24 @@
 #![allow(dead_code)]
 
+use std::io::{self, Write};
+use std::fs;
 use std::fmt;
 use std::error;
+use std::path::Path;
 use std::result::Result;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use uuid::Uuid;
 use serde_json;
 use hyper::error::UriError;
 use hyper::client::FutureResponse;
-use hyper::header::{ContentType, ContentLength};
+use hyper::header::{Authorization, Bearer, ContentType, ContentLength};
 use hyper::{Client, Method, Request, Error as HyperError};
 use hyper_tls::HttpsConnector;
 use tokio_core::reactor::{Core, Handle};
 use futures::{Poll, Future, Stream, IntoFuture};
+use futures::future::Either;
 
 use versions;
 use yggdrasil;
@@ -21,17 +26,40 @@ use yggdrasil;
 #[derive(Debug)]
 pub enum Error {
     UnrecognizedJson(String),
+    AccountNotFound(String),
+    InvalidCredentials(String),
+    ProfileMismatch(String),
+    Unsupported(String),
+    Cancelled,
     NetworkIOError(Box<error::Error + Send + Sync>),
 }
 
+pub const AUTH_SERVER_HOST: &str = "https://authserver.mojang.com";
+
+pub const SERVICES_HOST: &str = "https://api.minecraftservices.com";
+
+pub const AUTHLIB_INJECTOR_LATEST: &str = "https://authlib-injector.yushi.moe/artifact/latest.json";
+
 pub struct RequestFuture<T>(Box<Future<Item=T, Error=Error>>);
 
+pub struct LoaderMeta {
+    fabric_host: String,
+    forge_host: String,
+    cache: HashMap<String, serde_json::Value>,
+}
+
 impl From<serde_json::Error> for Error {
     fn from(e: serde_json::Error) -> Self {
         Error::NetworkIOError(Box::new(e))
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::NetworkIOError(Box::new(e))
+    }
+}
+
 impl From<UriError> for Error {
     fn from(e: UriError) -> Self {
         Error::NetworkIOError(Box::new(e))
@@ -48,11 +76,65 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::UnrecognizedJson(ref s) => fmt::Display::fmt(s, f),
+            Error::AccountNotFound(ref s) => write!(f, "account not found: {}", s),
+            Error::InvalidCredentials(ref s) => write!(f, "invalid credentials: {}", s),
+            Error::ProfileMismatch(ref s) => write!(f, "profile mismatch: {}", s),
+            Error::Unsupported(ref s) => write!(f, "unsupported: {}", s),
+            Error::Cancelled => f.write_str("the download was cancelled"),
             Error::NetworkIOError(ref e) => fmt::Display::fmt(e, f),
         }
     }
 }
 
+impl Default for LoaderMeta {
+    fn default() -> Self {
+        LoaderMeta::new()
+    }
+}
+
+impl LoaderMeta {
+    pub fn new() -> LoaderMeta {
+        LoaderMeta::with_hosts("https://meta.fabricmc.net", "https://files.minecraftforge.net")
+    }
+
+    pub fn with_hosts(fabric_host: &str, forge_host: &str) -> LoaderMeta {
+        LoaderMeta { fabric_host: fabric_host.to_owned(), forge_host: forge_host.to_owned(), cache: HashMap::new() }
+    }
+
+    pub fn fabric_loader_versions(&mut self, minecraft_version: &str) -> Result<Vec<String>, Error> {
+        let url = format!("{}/v2/versions/loader/{}", self.fabric_host, minecraft_version);
+        let json = self.fetch(&url)?;
+        let error = || Error::UnrecognizedJson(json.to_string());
+        json.as_array().ok_or_else(error)?.iter().map(|entry| {
+            entry["loader"]["version"].as_str().map(String::from).ok_or_else(error)
+        }).collect()
+    }
+
+    pub fn forge_versions(&mut self, minecraft_version: &str) -> Result<Vec<String>, Error> {
+        let url = format!("{}/net/minecraftforge/forge/maven-metadata.json", self.forge_host);
+        let json = self.fetch(&url)?;
+        let error = || Error::UnrecognizedJson(json.to_string());
+        let prefix = format!("{}-", minecraft_version);
+        match json.as_object().ok_or_else(error)?.get(minecraft_version) {
+            // entries look like <minecraft version>-<forge version>
+            Some(versions) => versions.as_array().ok_or_else(error)?.iter().map(|version| {
+                version.as_str().map(|version| version.strip_prefix(prefix.as_str()).unwrap_or(version).to_owned()).ok_or_else(error)
+            }).collect(),
+            None => Result::Ok(Vec::new())
+        }
+    }
+
+    fn fetch(&mut self, url: &str) -> Result<serde_json::Value, Error> {
+        if let Some(json) = self.cache.get(url) {
+            return Result::Ok(json.clone());
+        }
+        let mut core = Core::new().unwrap();
+        let json = core.run(make_json_request(core.handle(), url, serde_json::Value::Null))?;
+        self.cache.insert(url.to_owned(), json.clone());
+        Result::Ok(json)
+    }
+}
+
 impl<T> RequestFuture<T> {
     fn new<F: Future<Item=T, Error=Error> + 'static>(future: F) -> RequestFuture<T> {
         RequestFuture(Box::new(future))
@@ -71,10 +153,17 @@ impl<T> Future for RequestFuture<T> {
 fn make_json_https_request(handle: Handle,
                            url: &str,
                            json_value: serde_json::Value) -> Result<FutureResponse, Error> {
+    make_authorized_json_https_request(handle, url, json_value, None)
+}
+
+fn make_authorized_json_https_request(handle: Handle,
+                                      url: &str,
+                                      json_value: serde_json::Value,
+                                      bearer_token: Option<&str>) -> Result<FutureResponse, Error> {
     let connector = HttpsConnector::new(4, &handle).unwrap();
     let client = Client::configure().connector(connector).keep_alive(true).build(&handle);
 
-    let request = match json_value {
+    let mut request = match json_value {
         serde_json::Value::Null => Request::new(Method::Get, url.parse()?),
         _ => {
             let json = json_value.to_string();
@@ -85,6 +174,9 @@ fn make_json_https_request(handle: Handle,
             req
         }
     };
+    if let Some(token) = bearer_token {
+        request.headers_mut().set(Authorization(Bearer { token: token.to_owned() }));
+    }
 
     Result::Ok(client.request(request))
 }
@@ -101,12 +193,36 @@ fn make_json_request(handle: Handle,
     }))
 }
 
+fn make_status_request(handle: Handle,
+                       url: &str,
+                       json_value: serde_json::Value) -> RequestFuture<bool> {
+    RequestFuture::new(make_json_https_request(handle, url, json_value).into_future().and_then(|req| {
+        req.map_err(Error::from).map(|res| res.status().is_success())
+    }))
+}
+
+fn make_bytes_request(handle: Handle, url: &str) -> RequestFuture<Vec<u8>> {
+    RequestFuture::new(make_json_https_request(handle, url, serde_json::Value::Null).into_future().and_then(|req| {
+        req.map_err(Error::from).and_then(|res| {
+            res.body().concat2().map_err(Error::from).map(|body| body.to_vec())
+        })
+    }))
+}
+
 pub fn req_authenticate(username: &str,
                         password: &str,
                         client_token: &Uuid) -> Result<(Uuid, yggdrasil::Profile), Error> {
+    req_authenticate_with(AUTH_SERVER_HOST, username, password, client_token)
+}
+
+pub fn req_authenticate_with(auth_server: &str,
+                             username: &str,
+                             password: &str,
+                             client_token: &Uuid) -> Result<(Uuid, yggdrasil::Profile), Error> {
     let mut core = Core::new().unwrap();
+    let url = format!("{}/authenticate", auth_server.trim_end_matches('/'));
 
-    let req = make_json_request(core.handle(), "https://authserver.mojang.com/authenticate", json!({
+    let req = make_json_request(core.handle(), &url, json!({
         "username": username,
         "password": password,
         "clientToken": client_token.simple().to_string(),
@@ -126,10 +242,17 @@ pub fn req_authenticate(username: &str,
 
 pub fn req_refresh(access_token: &Uuid,
                    client_token: &Uuid) -> Result<(Uuid, yggdrasil::Profile), Error> {
+    req_refresh_with(AUTH_SERVER_HOST, &access_token.simple().to_string(), client_token)
+}
+
+pub fn req_refresh_with(auth_server: &str,
+                        access_token: &str,
+                        client_token: &Uuid) -> Result<(Uuid, yggdrasil::Profile), Error> {
     let mut core = Core::new().unwrap();
+    let url = format!("{}/refresh", auth_server.trim_end_matches('/'));
 
-    let req = make_json_request(core.handle(), "https://authserver.mojang.com/refresh", json!({
-        "accessToken": access_token.simple().to_string(),
+    let req = make_json_request(core.handle(), &url, json!({
+        "accessToken": access_token,
         "clientToken": client_token.simple().to_string()
     }));
 
@@ -144,6 +267,105 @@ pub fn req_refresh(access_token: &Uuid,
     }))?
 }
 
+pub fn req_validate_with(auth_server: &str,
+                         access_token: &str,
+                         client_token: &Uuid) -> Result<bool, Error> {
+    let mut core = Core::new().unwrap();
+    let url = format!("{}/validate", auth_server.trim_end_matches('/'));
+
+    // a valid token is answered with an empty 204, an invalid one with 403
+    let req = make_status_request(core.handle(), &url, json!({
+        "accessToken": access_token,
+        "clientToken": client_token.simple().to_string()
+    }));
+
+    core.run(req)
+}
+
+pub fn req_token_profile(services_host: &str, access_token: &str) -> Result<(Uuid, String), Error> {
+    let mut core = Core::new().unwrap();
+    let url = format!("{}/minecraft/profile", services_host.trim_end_matches('/'));
+
+    let res = make_authorized_json_https_request(core.handle(), &url, serde_json::Value::Null, Some(access_token))?;
+    let req = res.map_err(Error::from).and_then(|res| {
+        res.body().concat2().map_err(Error::from).and_then(|body| {
+            serde_json::from_slice(&body).map_err(Error::from).into_future()
+        })
+    });
+
+    core.run(req.map(|json: serde_json::Value| {
+        let error = || Error::UnrecognizedJson(json.to_string());
+        let uuid = Uuid::parse_str(json["id"].as_str().ok_or_else(error)?).map_err(|_| error())?;
+        let name = json["name"].as_str().ok_or_else(error)?.to_owned();
+        Result::Ok((uuid, name))
+    }))?
+}
+
+pub fn req_auth_server_metadata(api_root: &str) -> Result<serde_json::Value, Error> {
+    let mut core = Core::new().unwrap();
+
+    let req = make_json_request(core.handle(), api_root, serde_json::Value::Null);
+
+    core.run(req.map(|json| {
+        // the metadata of an authlib-injector server always contains its signature public key
+        if json["signaturePublickey"].is_string() { Result::Ok(json) } else { Result::Err(Error::UnrecognizedJson(json.to_string())) }
+    }))?
+}
+
+pub fn req_download_authlib_injector(target: &Path) -> Result<(), Error> {
+    let mut core = Core::new().unwrap();
+
+    let latest = core.run(make_json_request(core.handle(), AUTHLIB_INJECTOR_LATEST, serde_json::Value::Null))?;
+    let url = latest["download_url"].as_str().ok_or_else(|| Error::UnrecognizedJson(latest.to_string()))?;
+    let bytes = core.run(make_bytes_request(core.handle(), url))?;
+
+    if let Some(parent) = target.parent() { fs::create_dir_all(parent)?; }
+    fs::write(target, bytes)?;
+    Result::Ok(())
+}
+
+pub fn req_download<F, V>(url: &str, target: &Path, on_bytes: F, verify: V) -> Result<u64, Error>
+    where F: FnMut(u64), V: FnOnce(&Path) -> Result<(), Error> {
+    req_download_cancellable(url, target, on_bytes, verify, &AtomicBool::new(false))
+}
+
+pub fn req_download_cancellable<F, V>(url: &str, target: &Path, on_bytes: F, verify: V, cancelled: &AtomicBool) -> Result<u64, Error>
+    where F: FnMut(u64), V: FnOnce(&Path) -> Result<(), Error> {
+    // the target only appears once it is complete and verified, so a failed download is never taken as installed
+    let partial = target.with_file_name(format!("{}.part", target.file_name().and_then(|name| name.to_str()).unwrap_or("download")));
+    let result = download_to(url, partial.as_path(), on_bytes, cancelled).and_then(|written| {
+        verify(partial.as_path())?;
+        fs::rename(partial.as_path(), target)?;
+        Result::Ok(written)
+    });
+    if result.is_err() { let _ = fs::remove_file(partial.as_path()); }
+    result
+}
+
+fn download_to<F: FnMut(u64)>(url: &str, partial: &Path, mut on_bytes: F, cancelled: &AtomicBool) -> Result<u64, Error> {
+    let mut core = Core::new().unwrap();
+
+    let mut file = fs::File::create(partial)?;
+    let mut written = 0u64;
+
+    let req = make_json_https_request(core.handle(), url, serde_json::Value::Null);
+
+    core.run(req.into_future().and_then(|req| req.map_err(Error::from)).and_then(|res| if res.status().is_success() {
+        Either::A(res.body().map_err(Error::from).for_each(|chunk| {
+            if cancelled.load(Ordering::SeqCst) { return Result::Err(Error::Cancelled); }
+            file.write_all(&chunk)?;
+            written += chunk.len() as u64;
+            on_bytes(chunk.len() as u64);
+            Result::Ok::<(), Error>(())
+        }))
+    } else {
+        let message = format!("{} responded with {}", url, res.status());
+        Either::B(Result::Err(Error::from(io::Error::other(message))).into_future())
+    }))?;
+
+    Result::Ok(written)
+}
+
 pub fn req_versions() -> Result<serde_json::Value, Error> {
     let mut core = Core::new().unwrap();
     let url = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
@@ -162,3 +384,159 @@ pub fn req_deserialize_version(url: &str) -> Result<versions::MinecraftVersion,
         Result::Ok(serde_json::from_value(json.clone()).unwrap())
     }))?
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::thread;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    fn serve(routes: Vec<(&'static str, &'static str)>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        thread::spawn(move || for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let request = read_request(&mut stream);
+            let path = request.split_whitespace().nth(1).unwrap_or("").to_owned();
+            counter.fetch_add(1, Ordering::SeqCst);
+            let body = routes.iter().find(|route| route.0 == path).map(|route| route.1).unwrap_or("null");
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+        });
+        (host, requests)
+    }
+
+    // the whole request is read before answering, since closing with unread bytes resets the connection
+    fn read_request<R: Read>(stream: &mut R) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let size = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..size]);
+            let text = String::from_utf8_lossy(&request).into_owned();
+            let head_end = match text.find("\r\n\r\n") {
+                Some(index) => index + 4,
+                None if size > 0 => continue,
+                None => return text
+            };
+            let content_length = text[..head_end].lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|header| header.0.eq_ignore_ascii_case("content-length"))
+                .and_then(|header| header.1.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            if size == 0 || request.len() >= head_end + content_length { return text; }
+        }
+    }
+
+    #[test]
+    fn loader_versions_are_queried_and_cached() {
+        let (host, requests) = serve(vec![
+            ("/v2/versions/loader/1.20.1", r#"[{ "loader": { "version": "0.14.22", "stable": true } },
+                                               { "loader": { "version": "0.14.21", "stable": true } }]"#),
+            ("/net/minecraftforge/forge/maven-metadata.json", r#"{ "1.12.2": ["1.12.2-14.23.5.2859", "1.12.2-14.23.5.2860"],
+                                                                   "1.20.1": ["1.20.1-47.1.0"] }"#),
+        ]);
+        let mut meta = LoaderMeta::with_hosts(&host, &host);
+        assert_eq!(meta.fabric_loader_versions("1.20.1").unwrap(), vec!["0.14.22", "0.14.21"]);
+        assert_eq!(meta.forge_versions("1.12.2").unwrap(), vec!["14.23.5.2859", "14.23.5.2860"]);
+        assert_eq!(meta.forge_versions("1.20.1").unwrap(), vec!["47.1.0"]);
+        assert!(meta.forge_versions("1.7.10").unwrap().is_empty());
+        assert_eq!(meta.fabric_loader_versions("1.20.1").unwrap().len(), 2);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+    #[test]
+    fn auth_requests_go_to_configured_server() {
+        let (host, requests) = serve(vec![
+            ("/api/yggdrasil/authserver/authenticate", r#"{ "accessToken": "0123456789abcdef0123456789abcdef",
+                "selectedProfile": { "id": "853c80ef3c3749fdaa49938b674adae6", "name": "Alex" } }"#),
+        ]);
+        let auth_server = yggdrasil::authlib_injector_auth_server(&format!("{}/api/yggdrasil/", host));
+        let client_token = Uuid::new_v4();
+        let (access_token, profile) = req_authenticate_with(&auth_server, "alex@example.com", "secret", &client_token).unwrap();
+        assert_eq!(access_token.simple().to_string(), "0123456789abcdef0123456789abcdef");
+        assert_eq!(profile.name(), "Alex");
+        assert!(req_validate_with(&auth_server, "0123456789abcdef0123456789abcdef", &client_token).unwrap());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+    #[test]
+    fn auth_server_metadata_is_fetched_from_api_root() {
+        let (host, _) = serve(vec![
+            ("/api/yggdrasil", r#"{ "meta": { "serverName": "Example" }, "skinDomains": [], "signaturePublickey": "key" }"#),
+            ("/not-yggdrasil", r#"{ "meta": {} }"#),
+        ]);
+        let metadata = req_auth_server_metadata(&format!("{}/api/yggdrasil", host)).unwrap();
+        assert_eq!(metadata["meta"]["serverName"], "Example");
+        match req_auth_server_metadata(&format!("{}/not-yggdrasil", host)) {
+            Result::Err(Error::UnrecognizedJson(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+    #[test]
+    fn stale_profile_is_reported() {
+        let (host, requests) = serve(vec![
+            ("/minecraft/profile", r#"{ "id": "36532b5ec4423dbba24cc7e55d0f979a", "name": "Alex" }"#),
+        ]);
+        let auth_info = yggdrasil::AuthInfo::from_parts("Alex", "36532b5ec4423dbba24cc7e55d0f979a", "token", yggdrasil::AccountType::Msa).unwrap();
+        auth_info.verify_profile_with(&host).unwrap();
+        let stale = yggdrasil::AuthInfo::from_parts("Alex", "853c80ef3c3749fdaa49938b674adae6", "token", yggdrasil::AccountType::Msa).unwrap();
+        match stale.verify_profile_with(&host) {
+            Result::Err(Error::ProfileMismatch(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mojang = yggdrasil::AuthInfo::from_parts("Alex", "36532b5ec4423dbba24cc7e55d0f979a", "token", yggdrasil::AccountType::Mojang).unwrap();
+        match mojang.verify_profile_with(&host) {
+            Result::Err(Error::Unsupported(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+    #[test]
+    fn rejected_download_leaves_nothing_behind() {
+        let (host, _) = serve(vec![("/client.jar", "corrupt")]);
+        let target = env::temp_dir().join("rmcll-test-download/client.jar");
+        let _ = fs::remove_dir_all(target.parent().unwrap());
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        let url = format!("{}/client.jar", host);
+        let rejected = req_download(&url, target.as_path(), |_| (), |partial| {
+            assert_eq!(fs::read_to_string(partial).unwrap(), "corrupt");
+            Result::Err(Error::from(io::Error::other("sha1 mismatch")))
+        });
+        assert!(rejected.is_err());
+        assert!(!target.exists() && !target.with_file_name("client.jar.part").exists());
+        assert_eq!(req_download(&url, target.as_path(), |_| (), |_| Result::Ok(())).unwrap(), 7);
+        assert_eq!(fs::read_to_string(target.as_path()).unwrap(), "corrupt");
+    }
+
+    #[test]
+    fn cancelled_download_leaves_nothing_behind() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/client.jar", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let mut stream = listener.incoming().next().unwrap().unwrap();
+            let _ = stream.read(&mut [0u8; 4096]).unwrap();
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\nfirst");
+            let _ = stream.flush();
+            thread::sleep(Duration::from_millis(200));
+            let _ = write!(stream, "later");
+        });
+        let target = env::temp_dir().join("rmcll-test-cancelled-download/client.jar");
+        let _ = fs::remove_dir_all(target.parent().unwrap());
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        let cancelled = AtomicBool::new(false);
+        let result = req_download_cancellable(&url, target.as_path(), |_| cancelled.store(true, Ordering::SeqCst), |_| {
+            panic!("a cancelled download must not be verified")
+        }, &cancelled);
+        match result {
+            Result::Err(Error::Cancelled) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!target.exists() && !target.with_file_name("client.jar.part").exists());
+        let _ = fs::remove_dir_all(target.parent().unwrap());
+    }
+}
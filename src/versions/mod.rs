@@ -5,12 +5,18 @@ use std::io;
 use std::fs;
 use std::fmt;
 use std::error;
-use std::rc::Rc;
 use std::ffi::OsString;
-use std::path::{Path, PathBuf};
+use std::thread::{self, ThreadId};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::path::{Component, Path, PathBuf};
 use std::result::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use sha1::Sha1;
 use zip::read::ZipArchive;
+use zip::write::{FileOptions, ZipWriter};
 use zip::result::ZipError;
 use serde_json::{Value, self};
 use serde::de::{Deserialize, Deserializer, Visitor, MapAccess, self};
@@ -20,18 +26,27 @@ use parsing;
 
 #[cfg(target_pointer_width = "32")]
 const OS_ARCH: &str = "32";
-#[cfg(target_pointer_width = "64")]
+#[cfg(all(target_pointer_width = "64", not(all(target_os = "macos", target_arch = "aarch64"))))]
 const OS_ARCH: &str = "64";
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+const OS_ARCH: &str = "arm64";
 #[cfg(target_os = "windows")]
 const OS_PLATFORM: &str = "windows";
 #[cfg(target_os = "macos")]
-const OS_PLATFORM: &str = "macos";
+const OS_PLATFORM: &str = "osx";
 #[cfg(target_os = "linux")]
 const OS_PLATFORM: &str = "linux";
 
-const CLASSPATH_SEPARATOR: &str = ":";
+#[cfg(target_os = "windows")]
+pub const CLASSPATH_SEPARATOR: &str = ";";
+#[cfg(not(target_os = "windows"))]
+pub const CLASSPATH_SEPARATOR: &str = ":";
+
+pub const RESOURCES_HOST: &str = "https://resources.download.minecraft.net";
+
+pub const LIBRARIES_HOST: &str = "https://libraries.minecraft.net";
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct MinecraftVersion {
     id: String,
     #[serde(rename = "type")]
@@ -40,11 +55,8 @@ pub struct MinecraftVersion {
     publish_time: String,
     #[serde(rename = "releaseTime")]
     release_time: String,
-    // TODO: 1.13+ arguments
-    /*
     #[serde(default)]
-    arguments: HashMap<String, String>,
-    */
+    arguments: Option<VersionArguments>,
     #[serde(rename = "minecraftArguments")]
     minecraft_arguments: Option<String>,
     #[serde(rename = "mainClass", default)]
@@ -63,6 +75,62 @@ pub struct MinecraftVersion {
     downloads: HashMap<String, DownloadInfo>,
     #[serde(rename = "inheritsFrom")]
     inherits_from: Option<String>,
+    #[serde(rename = "javaVersion")]
+    java_version: Option<JavaVersionRequirement>,
+    #[serde(rename = "complianceLevel")]
+    compliance_level: Option<u32>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct VersionArguments {
+    #[serde(default)]
+    game: Vec<Value>,
+    #[serde(default)]
+    jvm: Vec<Value>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct JavaVersionRequirement {
+    #[serde(default)]
+    component: String,
+    #[serde(rename = "majorVersion")]
+    major_version: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VersionKind {
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetLayout {
+    Objects,
+    Virtual,
+    Resources,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionIcon {
+    File(PathBuf),
+    Builtin(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    path: String,
+    message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionDiff {
+    added_libraries: Vec<String>,
+    removed_libraries: Vec<String>,
+    changed_libraries: Vec<(String, String)>,
+    asset_index: Option<(String, String)>,
 }
 
 #[derive(Debug)]
@@ -76,13 +144,14 @@ pub struct DownloadStrategy {
 pub struct Library {
     name: String,
     is_native: bool,
-    downloads: Rc<DownloadStrategy>,
-    extract_ignored: Rc<Vec<String>>,
+    downloads: Arc<DownloadStrategy>,
+    extract_ignored: Arc<Vec<String>>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct NativeCollection {
-    libraries: Vec<(PathBuf, Rc<Vec<String>>)>
+    libraries: Vec<(PathBuf, Arc<Vec<String>>)>,
+    strip_versions: bool,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -106,15 +175,496 @@ pub struct AssetDownloadInfo {
     size_and_hash_known: bool,
 }
 
-pub struct VersionManager(Box<Path>);
+// versions are only cached while a scope is alive on the reading thread, and the number of json files read is counted
+pub struct VersionManager(Box<Path>, Mutex<HashMap<ThreadId, VersionCache>>, AtomicUsize);
+
+// the depth of nested scopes on a thread, and the versions read inside them
+type VersionCache = (usize, HashMap<String, MinecraftVersion>);
+
+pub struct VersionCacheScope<'a>(&'a VersionManager, ThreadId);
 
 #[derive(Debug)]
 pub enum Error {
     FileUnavailableError(Box<Path>),
     UnrecognizedPathString(OsString),
+    ConflictingNatives(Vec<String>),
+    InvalidVersionId(String),
+    UnknownClassifier(String),
+    InvalidCoordinate(String),
+    AssetIndexCorrupt(Box<Path>),
+    MissingPlaceholders(Vec<String>),
+    NoMainClass(String),
+    UnsafeArchiveEntry(String),
+    MissingVcRedist(Vec<String>),
+    InvalidStackSize(u32),
+    InvalidNatives(Vec<PathBuf>),
+    JvmArchMismatch(u32, u32),
+    InvalidMemoryPercentage(f32),
+    NoJavaFound(u32),
+    IncompatibleJava(String, u32, u32),
+    InsufficientDiskSpace(u64, u64),
+    Cancelled,
     IOError(Box<error::Error + Send + Sync>),
 }
 
+pub fn native_arch() -> &'static str {
+    OS_ARCH
+}
+
+pub fn normalize_version_id(id: &str) -> Result<String, Error> {
+    let normalized = id.trim();
+    let is_invalid_char = |c: char| c.is_control() || "/\\:*?\"<>|".contains(c);
+    if normalized.is_empty() || normalized == "." || normalized == ".." || normalized.contains(is_invalid_char) {
+        Result::Err(Error::InvalidVersionId(id.to_owned()))
+    } else {
+        Result::Ok(normalized.to_owned())
+    }
+}
+
+fn without_superseded_natives(libs: Vec<Library>) -> Vec<Library> {
+    // only the newest of several versions of a native library is kept, for both the classpath and extraction
+    let native_key = |lib: &Library| {
+        let parts: Vec<_> = lib.name.split(':').collect();
+        let version: Vec<u64> = parts.get(2).unwrap_or(&"").split(|c: char| !c.is_ascii_digit()).filter_map(|n| n.parse().ok()).collect();
+        let mut artifact = parts.clone();
+        if artifact.len() > 2 { artifact.remove(2); }
+        (artifact.join(":"), version)
+    };
+    let mut newest: HashMap<String, (Vec<u64>, usize)> = HashMap::new();
+    for (index, lib) in libs.iter().enumerate().filter(|&(_, lib)| lib.is_native()) {
+        let (artifact, version) = native_key(lib);
+        match newest.get(&artifact) {
+            Some((newest_version, _)) if *newest_version > version => (),
+            _ => { newest.insert(artifact, (version, index)); }
+        }
+    }
+    let kept: HashSet<usize> = newest.values().map(|&(_, index)| index).collect();
+    libs.into_iter().enumerate().filter(|&(index, ref lib)| !lib.is_native() || kept.contains(&index)).map(|(_, lib)| lib).collect()
+}
+
+pub fn file_sha1(path: &Path) -> Result<String, Error> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha1::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => return Result::Ok(hasher.digest().to_string()),
+            n => hasher.update(&buffer[..n])
+        }
+    }
+}
+
+pub trait FileHasher: Send + Sync {
+    fn sha1(&self, path: &Path) -> Result<String, Error>;
+}
+
+pub struct BuiltinHasher;
+
+impl FileHasher for BuiltinHasher {
+    fn sha1(&self, path: &Path) -> Result<String, Error> {
+        file_sha1(path)
+    }
+}
+
+fn read_json_bytes(path: &Path) -> Result<Vec<u8>, Error> {
+    // crlf line endings are plain whitespace to serde_json, only a leading utf-8 bom needs stripping
+    let mut bytes = fs::read(path)?;
+    if bytes.starts_with(b"\xef\xbb\xbf") { bytes.drain(..3); }
+    Result::Ok(bytes)
+}
+
+pub fn jar_main_class(path: &Path) -> Result<Option<String>, Error> {
+    let mut zip = ZipArchive::new(fs::File::open(path)?)?;
+    let mut manifest = String::new();
+    match zip.by_name("META-INF/MANIFEST.MF") {
+        Result::Ok(mut file) => { file.read_to_string(&mut manifest)?; }
+        Result::Err(ZipError::FileNotFound) => return Result::Ok(None),
+        Result::Err(e) => return Result::Err(Error::from(e))
+    }
+    // long manifest values continue on the following lines which start with a single space
+    let manifest = manifest.replace("\r\n", "\n").replace("\n ", "");
+    Result::Ok(manifest.lines().filter_map(|line| line.strip_prefix("Main-Class:")).map(|value| value.trim().to_owned()).next())
+}
+
+pub fn canonical_native_name(file_name: &str) -> String {
+    // e.g. liblwjgl-3.3.1.so to liblwjgl.so, which is the name lwjgl looks for
+    let extension = [".so", ".dll", ".dylib", ".jnilib"].iter().find(|extension| file_name.ends_with(*extension));
+    let base = &file_name[..file_name.len() - extension.map_or(0, |extension| extension.len())];
+    let is_version = |version: &str| version.starts_with(|c: char| c.is_ascii_digit()) && version.chars().all(|c| c.is_ascii_digit() || c == '.');
+    match (extension, base.rfind('-')) {
+        (Some(extension), Some(index)) if !base[index..].contains('/') && is_version(&base[index + 1..]) => {
+            format!("{}{}", &base[..index], extension)
+        }
+        _ => file_name.to_owned()
+    }
+}
+
+fn native_platform_of(name: &str) -> Option<&'static str> {
+    match name {
+        "windows" | "win32" | "win64" | "win" => Some("windows"),
+        "macos" | "osx" | "darwin" | "mac" => Some("osx"),
+        "linux" => Some("linux"),
+        _ => None
+    }
+}
+
+fn native_arch_of(name: &str) -> Option<&'static str> {
+    match name {
+        "x86" | "i386" | "i686" | "32" => Some("32"),
+        "x64" | "x86-64" | "x86_64" | "amd64" | "64" => Some("64"),
+        "arm64" | "aarch64" => Some("arm64"),
+        _ => None
+    }
+}
+
+pub fn platform_native_entry(file_name: &str, platform: &str, arch: &str) -> Option<String> {
+    // e.g. linux/x64/liblwjgl.so or linux-x86-64/libjnidispatch.so in jars bundling natives of several platforms
+    let (first, rest) = match file_name.split_once('/') {
+        Some(parts) => parts,
+        None => return Some(file_name.to_owned())
+    };
+    let (entry_platform, mut entry_arch) = match native_platform_of(first) {
+        Some(entry_platform) => (entry_platform, None),
+        None => match first.split_once('-') {
+            Some((head, tail)) => match (native_platform_of(head), native_arch_of(tail)) {
+                (Some(entry_platform), Some(entry_arch)) => (entry_platform, Some(entry_arch)),
+                _ => return Some(file_name.to_owned())
+            },
+            None => return Some(file_name.to_owned())
+        }
+    };
+    let mut rest = rest;
+    if entry_arch.is_none() {
+        if let Some((second, remaining)) = rest.split_once('/') {
+            if let Some(second_arch) = native_arch_of(second) {
+                entry_arch = Some(second_arch);
+                rest = remaining;
+            }
+        }
+    }
+    if entry_platform != platform || entry_arch.is_some_and(|entry_arch| entry_arch != arch) { return None; }
+    Some(rest.to_owned())
+}
+
+pub fn native_signature_matches(path: &Path, platform: &str) -> Result<bool, Error> {
+    let mut magic = [0u8; 4];
+    let mut file = fs::File::open(path)?;
+    let mut read = 0;
+    while read < magic.len() {
+        match file.read(&mut magic[read..])? {
+            0 => return Result::Ok(false),
+            n => read += n
+        }
+    }
+    Result::Ok(match platform {
+        "linux" => magic == *b"\x7fELF",
+        "windows" => magic.starts_with(b"MZ"),
+        // thin binaries of either byte order, or universal binaries
+        "osx" => [[0xfe, 0xed, 0xfa, 0xce], [0xfe, 0xed, 0xfa, 0xcf], [0xce, 0xfa, 0xed, 0xfe], [0xcf, 0xfa, 0xed, 0xfe], [0xca, 0xfe, 0xba, 0xbe]].contains(&magic),
+        _ => true
+    })
+}
+
+pub fn invalid_natives_of(dir: &Path, platform: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut result = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir.as_path())? {
+            let path_buf = entry?.path();
+            let is_native = [".so", ".dll", ".dylib", ".jnilib"].iter().any(|extension| path_buf.to_string_lossy().ends_with(extension));
+            if path_buf.is_dir() {
+                dirs.push(path_buf);
+            } else if is_native && !native_signature_matches(path_buf.as_path(), platform)? {
+                result.push(path_buf);
+            }
+        }
+    }
+    result.sort();
+    Result::Ok(result)
+}
+
+pub fn invalid_natives(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    invalid_natives_of(dir, OS_PLATFORM)
+}
+
+pub fn jar_embedded_version(path: &Path) -> Result<Option<String>, Error> {
+    let mut zip = ZipArchive::new(fs::File::open(path)?)?;
+    let version: Value = match zip.by_name("version.json") {
+        Result::Ok(file) => serde_json::from_reader(file)?,
+        Result::Err(ZipError::FileNotFound) => return Result::Ok(None),
+        Result::Err(e) => return Result::Err(Error::from(e))
+    };
+    Result::Ok(version.get("id").and_then(Value::as_str).map(String::from))
+}
+
+pub fn is_case_insensitive(dir: &Path) -> Result<bool, Error> {
+    let probe_name = format!(".rmcll-case-probe-{}", process::id());
+    let probe = dir.join(probe_name.as_str());
+    fs::write(probe.as_path(), b"")?;
+    let result = dir.join(probe_name.to_uppercase()).exists();
+    fs::remove_file(probe)?;
+    Result::Ok(result)
+}
+
+fn is_safe_archive_entry(file_name: &str) -> bool {
+    let file_name = file_name.replace("\\", "/");
+    Path::new(file_name.as_str()).components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+pub fn extract_bundle(bundle: &Path, target_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut result = Vec::new();
+    let mut zip = ZipArchive::new(fs::File::open(bundle)?)?;
+    for i in 0..zip.len() {
+        let mut source = zip.by_index(i)?;
+        let file_name = source.name().replace("\\", "/");
+        if !is_safe_archive_entry(file_name.as_str()) { return Result::Err(Error::UnsafeArchiveEntry(file_name)); }
+        let target_path = target_dir.join(file_name.as_str());
+        if file_name.ends_with('/') {
+            fs::create_dir_all(target_path.as_path())?;
+            continue;
+        }
+        if let Some(parent) = target_path.parent() { fs::create_dir_all(parent)? }
+        io::copy(&mut source, &mut fs::File::create(target_path.as_path())?)?;
+        result.push(target_path);
+    }
+    Result::Ok(result)
+}
+
+pub fn write_classpath_jar(jar_path: &Path, entries: &[PathBuf]) -> Result<(), Error> {
+    let urls: Vec<String> = entries.iter().map(|path_buf| file_url(path_buf.as_path())).collect();
+    let mut manifest = String::from("Manifest-Version: 1.0\r\n");
+    // manifest lines are limited to 72 bytes, longer ones continue after a leading space
+    let class_path = format!("Class-Path: {}", urls.join(" "));
+    let (first, mut rest) = class_path.split_at(class_path.len().min(72));
+    manifest.push_str(first);
+    while !rest.is_empty() {
+        let (line, remaining) = rest.split_at(rest.len().min(71));
+        manifest.push_str("\r\n ");
+        manifest.push_str(line);
+        rest = remaining;
+    }
+    manifest.push_str("\r\n\r\n");
+    if let Some(parent) = jar_path.parent() { fs::create_dir_all(parent)? }
+    let mut zip = ZipWriter::new(fs::File::create(jar_path)?);
+    zip.start_file("META-INF/MANIFEST.MF", FileOptions::default())?;
+    io::Write::write_all(&mut zip, manifest.as_bytes())?;
+    zip.finish()?;
+    Result::Ok(())
+}
+
+fn file_url(path: &Path) -> String {
+    let mut result = String::from("file:");
+    let string = path.to_string_lossy().replace("\\", "/");
+    if !string.starts_with('/') { result.push('/') }
+    for byte in string.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' | b':' => result.push(byte as char),
+            _ => result.push_str(&format!("%{:02X}", byte))
+        }
+    }
+    if path.is_dir() && !result.ends_with('/') { result.push('/') }
+    result
+}
+
+pub fn verify_sha1_parallel(files: Vec<(PathBuf, String)>, threads: usize) -> Vec<PathBuf> {
+    verify_sha1_parallel_with(files, threads, Arc::new(BuiltinHasher))
+}
+
+pub fn verify_sha1_parallel_with(files: Vec<(PathBuf, String)>, threads: usize, hasher: Arc<dyn FileHasher>) -> Vec<PathBuf> {
+    // nothing else holds the flag, so the verification always runs to the end
+    verify_sha1_parallel_cancellable(files, threads, hasher, Arc::new(AtomicBool::new(false))).unwrap_or_default()
+}
+
+pub fn verify_sha1_parallel_cancellable(files: Vec<(PathBuf, String)>,
+                                        threads: usize,
+                                        hasher: Arc<dyn FileHasher>,
+                                        cancelled: Arc<AtomicBool>) -> Result<Vec<PathBuf>, Error> {
+    let queue = Arc::new(Mutex::new(files.into_iter()));
+    let workers: Vec<_> = (0..threads.max(1)).map(|_| {
+        let queue = queue.clone();
+        let hasher = hasher.clone();
+        let cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let mut mismatches = Vec::new();
+            loop {
+                if cancelled.load(Ordering::SeqCst) { return mismatches; }
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((path_buf, expected)) => match hasher.sha1(path_buf.as_path()) {
+                        Result::Ok(ref sha1) if sha1.eq_ignore_ascii_case(&expected) => (),
+                        _ => mismatches.push(path_buf) // unreadable files count as mismatches as well
+                    },
+                    None => return mismatches
+                }
+            }
+        })
+    }).collect();
+    let mut result: Vec<_> = workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect();
+    if cancelled.load(Ordering::SeqCst) { return Result::Err(Error::Cancelled); }
+    result.sort();
+    Result::Ok(result)
+}
+
+pub fn join_classpath(entries: Vec<PathBuf>, classpath_separator: &str) -> Result<String, Error> {
+    let mut result = Vec::new();
+    for path_buf in entries {
+        result.push(path_buf.into_os_string().into_string()?);
+    }
+    Result::Ok(result.join(classpath_separator))
+}
+
+pub fn relative_path(path: &Path, base_dir: &Path) -> PathBuf {
+    let path_buf = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let base_dir = fs::canonicalize(base_dir).unwrap_or_else(|_| base_dir.to_path_buf());
+    match path_buf.strip_prefix(base_dir.as_path()) {
+        Result::Ok(relative) => relative.to_path_buf(),
+        Result::Err(_) => path_buf.clone(),
+    }
+}
+
+pub fn validate_version_json(json: &Value) -> Vec<ValidationIssue> {
+    let mut result = Vec::new();
+    {
+        let mut issue = |path: String, message: &str| result.push(ValidationIssue { path, message: message.to_owned() });
+        for key in ["id", "type", "time", "releaseTime", "mainClass"].iter() {
+            match json.get(*key) {
+                Some(Value::String(_)) => (),
+                Some(_) => issue(format!("/{}", key), "should be a string"),
+                None => issue(format!("/{}", key), "is missing"),
+            }
+        }
+        if json.get("minecraftArguments").is_none() && json.get("arguments").is_none() {
+            issue("/arguments".to_owned(), "neither arguments nor minecraftArguments is present");
+        }
+        for kind in ["game", "jvm"].iter() {
+            let list = match json.get("arguments").and_then(|arguments| arguments.get(*kind)) {
+                Some(Value::Array(list)) => list,
+                Some(_) => {
+                    issue(format!("/arguments/{}", kind), "should be an array");
+                    continue;
+                }
+                None => continue
+            };
+            for (i, argument) in list.iter().enumerate() {
+                let is_valid = match *argument {
+                    Value::String(_) => true,
+                    Value::Object(ref object) => object.get("rules").into_iter().all(Value::is_array) && match object.get("value") {
+                        Some(Value::String(_)) => true,
+                        Some(Value::Array(values)) => values.iter().all(Value::is_string),
+                        _ => false
+                    },
+                    _ => false
+                };
+                if !is_valid { issue(format!("/arguments/{}/{}", kind, i), "should be a string or an object with rules and value") }
+            }
+        }
+        match json.get("libraries") {
+            Some(Value::Array(libraries)) => for (i, library) in libraries.iter().enumerate() {
+                match library.get("name").and_then(Value::as_str) {
+                    Some(name) if maven_suffix(name).is_some() => (),
+                    Some(_) => issue(format!("/libraries/{}/name", i), "is not a valid maven coordinate"),
+                    None => issue(format!("/libraries/{}/name", i), "is missing"),
+                }
+                if library.get("rules").into_iter().any(|rules| !rules.is_array()) {
+                    issue(format!("/libraries/{}/rules", i), "should be an array");
+                }
+            },
+            Some(_) => issue("/libraries".to_owned(), "should be an array"),
+            None => ()
+        }
+    }
+    result
+}
+
+pub fn modpack_dependencies(manifest: &Value) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    // curseforge manifests: { "minecraft": { "version": "1.20.1", "modLoaders": [{ "id": "forge-47.2.0" }] } }
+    if let Some(minecraft) = manifest.get("minecraft") {
+        if let Some(version) = minecraft.get("version").and_then(Value::as_str) {
+            result.push(("minecraft".to_owned(), version.to_owned()));
+        }
+        for loader in minecraft.get("modLoaders").and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]) {
+            let mut split = loader.get("id").and_then(Value::as_str).unwrap_or("").splitn(2, '-');
+            if let (Some(name), Some(version)) = (split.next(), split.next()) {
+                result.push((name.to_owned(), version.to_owned()));
+            }
+        }
+    }
+    // modrinth indexes: { "dependencies": { "minecraft": "1.20.1", "fabric-loader": "0.14.22" } }
+    if let Some(dependencies) = manifest.get("dependencies").and_then(Value::as_object) {
+        for (key, version) in dependencies.iter() {
+            let name = key.trim_end_matches("-loader");
+            if let Some(version) = version.as_str() { result.push((name.to_owned(), version.to_owned())) }
+        }
+    }
+    result
+}
+
+pub fn maven_path(coordinate: &str) -> Result<PathBuf, Error> {
+    maven_suffix(coordinate).map(PathBuf::from).ok_or_else(|| Error::InvalidCoordinate(coordinate.to_owned()))
+}
+
+pub fn library_url(coordinate: &str, repository: Option<&str>) -> Result<String, Error> {
+    // legacy entries without a downloads block live in the maven layout of the given repository
+    let suffix = maven_suffix(coordinate).ok_or_else(|| Error::InvalidCoordinate(coordinate.to_owned()))?;
+    Result::Ok(format!("{}/{}", repository.unwrap_or(LIBRARIES_HOST).trim_end_matches('/'), suffix))
+}
+
+fn maven_suffix(coordinate: &str) -> Option<String> {
+    let mut split = coordinate.splitn(2, '@');
+    let (coordinate, extension) = (split.next().unwrap_or(""), split.next().unwrap_or("jar"));
+    let parts: Vec<_> = coordinate.split(':').collect();
+    if parts.len() < 3 || parts.len() > 4 || parts.iter().any(|part| part.is_empty()) || extension.is_empty() {
+        return None;
+    }
+    let dir = format!("{}/{}/{}", parts[0].replace(".", "/"), parts[1], parts[2]);
+    match parts.get(3) {
+        Some(classifier) => Some(format!("{}/{}-{}-{}.{}", dir, parts[1], parts[2], classifier, extension)),
+        None => Some(format!("{}/{}-{}.{}", dir, parts[1], parts[2], extension))
+    }
+}
+
+pub fn absolute_path(path: &Path, base_dir: &Path) -> PathBuf {
+    let path_buf = base_dir.join(path);
+    fs::canonicalize(path_buf.as_path()).unwrap_or(path_buf)
+}
+
+fn is_argument_allowed(rules: &Value) -> bool {
+    let mut allowed = false;
+    for rule in rules.as_array().map(Vec::as_slice).unwrap_or(&[]) {
+        // features such as is_demo_user or has_custom_resolution are never enabled
+        let os_name = rule.get("os").and_then(|os| os.get("name")).and_then(Value::as_str);
+        let os_arch = rule.get("os").and_then(|os| os.get("arch")).and_then(Value::as_str);
+        let matched = rule.get("features").is_none()
+            && os_name.unwrap_or(OS_PLATFORM) == OS_PLATFORM
+            && (os_arch != Some("x86") || OS_ARCH == "32");
+        if matched {
+            match rule.get("action").and_then(Value::as_str) {
+                Some("allow") => allowed = true,
+                Some("disallow") => allowed = false,
+                _ => () // just ignore it
+            }
+        }
+    }
+    allowed
+}
+
+fn allowed_argument_values(list: &[Value]) -> Vec<String> {
+    let mut result = Vec::new();
+    for item in list {
+        match item {
+            Value::String(arg) => result.push(arg.clone()),
+            Value::Object(map) if map.get("rules").map(is_argument_allowed).unwrap_or(true) => match map.get("value") {
+                Some(Value::String(arg)) => result.push(arg.clone()),
+                Some(Value::Array(args)) => result.extend(args.iter().filter_map(Value::as_str).map(String::from)),
+                _ => () // just ignore it
+            }
+            _ => () // just ignore it
+        }
+    }
+    result
+}
+
 impl From<serde_json::Error> for Error {
     fn from(e: serde_json::Error) -> Self {
         Error::IOError(Box::new(e))
@@ -139,23 +689,170 @@ impl From<ZipError> for Error {
     }
 }
 
+impl Error {
+    pub fn user_message(&self) -> String {
+        match *self {
+            Error::FileUnavailableError(ref path) =>
+                format!("{} is missing; reinstall the version or download the file again.", path.display()),
+            Error::UnrecognizedPathString(ref path) =>
+                format!("The path {:?} cannot be handled; move the game directory to a path without unusual characters.", path),
+            Error::ConflictingNatives(ref names) =>
+                format!("Several native libraries provide {}; remove the duplicated libraries from the version json.", names.join(", ")),
+            Error::InvalidVersionId(ref id) =>
+                format!("{:?} is not a valid version name; rename the version without characters such as / or :.", id),
+            Error::UnknownClassifier(ref classifier) =>
+                format!("No native library is available for {}; choose another classifier or install the matching natives.", classifier),
+            Error::InvalidCoordinate(ref coordinate) =>
+                format!("The library {:?} is not a valid maven coordinate; fix it in the version json.", coordinate),
+            Error::AssetIndexCorrupt(ref path) =>
+                format!("The asset index {} is corrupt; delete it so that it is downloaded again.", path.display()),
+            Error::MissingPlaceholders(ref keys) =>
+                format!("The version requires {} which the launcher cannot provide; update the launcher or the version json.", keys.join(", ")),
+            Error::NoMainClass(ref id) =>
+                format!("The version {} has no main class; reinstall it or fix its version json.", id),
+            Error::UnsafeArchiveEntry(ref name) =>
+                format!("The archive entry {} points outside of the target directory; the archive may be malicious, so it is not extracted.", name),
+            Error::MissingVcRedist(ref dlls) =>
+                format!("{} cannot be found; install the Microsoft Visual C++ Redistributable from https://aka.ms/vs/17/release/vc_redist.x64.exe.", dlls.join(", ")),
+            Error::InvalidStackSize(size) =>
+                format!("A thread stack size of {} KiB is not usable; choose a size between 160 KiB and 1 GiB.", size),
+            Error::InvalidNatives(ref paths) =>
+                format!("The native libraries {} are broken; delete them so that they are downloaded again.",
+                        paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")),
+            Error::JvmArchMismatch(jvm_bits, native_bits) =>
+                format!("The Java in use is {}-bit but the natives are {}-bit; install a {}-bit Java.", jvm_bits, native_bits, native_bits),
+            Error::InvalidMemoryPercentage(percent) =>
+                format!("{}% is not a valid share of memory; choose a percentage above 0 and at most 100.", percent),
+            Error::NoJavaFound(required) =>
+                format!("No compatible Java found; install Java {} or point the launcher to it.", required),
+            Error::IncompatibleJava(ref program, found, required) =>
+                format!("Java {} at {} is too old for this version; install Java {} and select it.", found, program, required),
+            Error::InsufficientDiskSpace(required, available) =>
+                format!("The installation needs {} MiB but only {} MiB are free; free up disk space or install to another drive.",
+                        required / (1024 * 1024), available / (1024 * 1024)),
+            Error::Cancelled => "The operation was cancelled.".to_owned(),
+            Error::IOError(ref e) => format!("The launch failed: {}.", e),
+        }
+    }
+}
+
 impl NativeCollection {
     fn is_file_included(&self, extract_ignored: &Vec<String>, file_name: &str) -> bool {
         extract_ignored.iter().find(|rule| file_name.starts_with(rule.as_str())).is_none()
     }
 
+    pub fn conflicting_entries(&self) -> Result<Vec<String>, Error> {
+        let mut result = Vec::new();
+        let mut sources: HashMap<String, &PathBuf> = HashMap::new();
+        for (path_buf, extract_ignored) in self.libraries.iter() {
+            let zip_file = fs::File::open(path_buf)?;
+            let mut zip = ZipArchive::new(zip_file)?;
+            for i in 0..zip.len() {
+                let file_name = zip.by_index(i)?.name().to_owned();
+                if file_name.ends_with('/') || !self.is_file_included(extract_ignored, file_name.as_str()) {
+                    continue;
+                }
+                let file_name = match platform_native_entry(&file_name, OS_PLATFORM, OS_ARCH) {
+                    Some(name) => name,
+                    None => continue
+                };
+                // manifests and signatures are present in nearly every jar and never loaded as natives
+                if file_name.starts_with("META-INF/") { continue; }
+                if let Some(source) = sources.insert(file_name.clone(), path_buf) {
+                    if source != path_buf && !result.contains(&file_name) { result.push(file_name) }
+                }
+            }
+        }
+        Result::Ok(result)
+    }
+
+    pub fn new(libraries: Vec<(PathBuf, Vec<String>)>) -> NativeCollection {
+        let libraries = libraries.into_iter().map(|(path_buf, extract_ignored)| (path_buf, Arc::new(extract_ignored))).collect();
+        NativeCollection { libraries, strip_versions: false }
+    }
+
+    pub fn libraries(&self) -> Vec<(PathBuf, Vec<String>)> {
+        self.libraries.iter().map(|(path_buf, extract_ignored)| (path_buf.clone(), extract_ignored.as_ref().clone())).collect()
+    }
+
+    pub fn strips_versions(&self) -> bool {
+        self.strip_versions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.libraries.is_empty()
+    }
+
+    pub fn strip_versions(mut self, strip_versions: bool) -> Self {
+        self.strip_versions = strip_versions;
+        self
+    }
+
+    pub fn fingerprint(&self) -> Result<Vec<(String, Vec<String>)>, Error> {
+        let mut result = Vec::new();
+        for (path_buf, extract_ignored) in self.libraries.iter() {
+            result.push((file_sha1(path_buf)?, extract_ignored.as_ref().clone()));
+        }
+        result.sort();
+        Result::Ok(result)
+    }
+
+    pub fn paths(&self) -> Vec<&Path> {
+        self.libraries.iter().map(|library| library.0.as_path()).collect()
+    }
+
+    pub fn check_conflicts(&self) -> Result<(), Error> {
+        let conflicts = self.conflicting_entries()?;
+        if conflicts.is_empty() { Result::Ok(()) } else { Result::Err(Error::ConflictingNatives(conflicts)) }
+    }
+
     pub fn extract_to(&self, target_dir_path: &Path) -> Result<Vec<String>, Error> {
+        self.extract_to_cancellable(target_dir_path, &AtomicBool::new(false))
+    }
+
+    pub fn extract_to_cancellable(&self,
+                                  target_dir_path: &Path,
+                                  cancelled: &AtomicBool) -> Result<Vec<String>, Error> {
+        if !target_dir_path.is_dir() { fs::create_dir_all(target_dir_path)? }
+        let case_insensitive = is_case_insensitive(target_dir_path)?;
+        self.extract_with(target_dir_path, cancelled, case_insensitive)
+    }
+
+    fn extract_with(&self,
+                    target_dir_path: &Path,
+                    cancelled: &AtomicBool,
+                    case_insensitive: bool) -> Result<Vec<String>, Error> {
         let mut result = Vec::new();
+        let mut extracted_names = HashSet::new();
         let target_path_buf = target_dir_path.to_path_buf();
-        if !target_dir_path.is_dir() { fs::create_dir_all(target_dir_path)? }
         for &(ref path_buf, ref extract_ignored) in self.libraries.iter() {
             let zip_file = fs::File::open(path_buf)?;
             let mut zip = ZipArchive::new(zip_file)?;
             for i in 0..zip.len() {
+                if cancelled.load(Ordering::SeqCst) {
+                    for file_name in result.iter() {
+                        let _ = fs::remove_file(target_path_buf.join(file_name));
+                    }
+                    return Result::Err(Error::Cancelled);
+                }
                 let mut source = zip.by_index(i)?;
                 let file_name = source.name().to_owned();
                 if self.is_file_included(&extract_ignored, file_name.as_str()) {
+                    // natives of other platforms bundled in the same jar are skipped
+                    let file_name = match platform_native_entry(&file_name, OS_PLATFORM, OS_ARCH) {
+                        Some(ref name) if name.is_empty() || name.ends_with('/') => continue,
+                        Some(name) => name,
+                        None => continue
+                    };
+                    let file_name = if self.strip_versions { canonical_native_name(&file_name) } else { file_name };
+                    if !is_safe_archive_entry(file_name.as_str()) { return Result::Err(Error::UnsafeArchiveEntry(file_name)); }
+                    // on case-insensitive filesystems the first entry wins instead of being overwritten silently
+                    if case_insensitive && !extracted_names.insert(file_name.to_lowercase()) {
+                        warn!("Skipping native {} which collides with an extracted one by case", file_name);
+                        continue;
+                    }
                     let target_path = target_path_buf.join(file_name.as_str());
+                    if let Some(parent) = target_path.parent() { fs::create_dir_all(parent)?; }
                     let mut target = fs::File::create(target_path)?;
                     io::copy(&mut source, &mut target)?;
                     result.push(file_name);
@@ -168,7 +865,18 @@ impl NativeCollection {
 
 impl VersionManager {
     pub fn new(path: &Path) -> VersionManager {
-        VersionManager(Box::from(path))
+        VersionManager(Box::from(path), Mutex::new(HashMap::new()), AtomicUsize::new(0))
+    }
+
+    pub fn cache_scope(&self) -> VersionCacheScope<'_> {
+        let thread_id = thread::current().id();
+        self.1.lock().unwrap().entry(thread_id).or_insert_with(|| (0, HashMap::new())).0 += 1;
+        VersionCacheScope(self, thread_id)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn json_reads(&self) -> usize {
+        self.2.load(Ordering::SeqCst)
     }
 
     pub fn get_version_path(&self) -> PathBuf {
@@ -182,24 +890,258 @@ impl VersionManager {
         path_buf
     }
 
+    pub fn purge_orphan_natives(&self) -> Result<Vec<PathBuf>, Error> {
+        let installed = self.version_ids()?;
+        let mut result = Vec::new();
+        for entry in fs::read_dir(self.0.as_ref())? {
+            let version_dir = entry?.path();
+            if !version_dir.is_dir() { continue; }
+            for entry in fs::read_dir(version_dir.as_path())? {
+                let path_buf = entry?.path();
+                let name = path_buf.file_name().and_then(|name| name.to_str()).unwrap_or("").to_owned();
+                let owner = match name.find("-natives-") {
+                    Some(index) if path_buf.is_dir() => &name[..index],
+                    _ => continue
+                };
+                if !installed.iter().any(|id| id == owner) || version_dir != self.0.join(owner) {
+                    fs::remove_dir_all(path_buf.as_path())?;
+                    result.push(path_buf);
+                }
+            }
+        }
+        result.sort();
+        Result::Ok(result)
+    }
+
+    pub fn repair_layout(&self) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+        let mut result = Vec::new();
+        let mut version_dirs = Vec::new();
+        for entry in fs::read_dir(self.0.as_ref())? {
+            let path_buf = entry?.path();
+            if path_buf.is_dir() { version_dirs.push(path_buf) }
+        }
+        version_dirs.sort();
+        for version_dir in version_dirs {
+            let dir_name = version_dir.file_name().and_then(|name| name.to_str()).unwrap_or("").to_owned();
+            if version_dir.join(format!("{}.json", dir_name)).is_file() { continue; }
+            let mut candidates = Vec::new();
+            for entry in fs::read_dir(version_dir.as_path())? {
+                let path_buf = entry?.path();
+                if path_buf.extension().and_then(|ext| ext.to_str()) != Some("json") { continue; }
+                let json: Value = match fs::read(path_buf.as_path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()) {
+                    Some(json) => json,
+                    None => continue
+                };
+                if let Some(id) = json.get("id").and_then(Value::as_str).and_then(|id| normalize_version_id(id).ok()) {
+                    candidates.push((path_buf, id));
+                }
+            }
+            // only a single version json can be relocated without guessing
+            let (json_path, id) = match candidates.pop() {
+                Some(candidate) if candidates.is_empty() => candidate,
+                _ => continue
+            };
+            let target_dir = self.0.join(id.as_str());
+            if target_dir != version_dir {
+                if target_dir.exists() { continue; }
+                fs::rename(version_dir.as_path(), target_dir.as_path())?;
+                result.push((version_dir.clone(), target_dir.clone()));
+            }
+            let json_path = target_dir.join(json_path.file_name().unwrap_or_default());
+            let jar_path = json_path.with_extension("jar");
+            let target_json = target_dir.join(format!("{}.json", id));
+            let target_jar = target_dir.join(format!("{}.jar", id));
+            if json_path != target_json {
+                fs::rename(json_path.as_path(), target_json.as_path())?;
+                result.push((json_path, target_json));
+            }
+            if jar_path.is_file() && !target_jar.exists() {
+                fs::rename(jar_path.as_path(), target_jar.as_path())?;
+                result.push((jar_path, target_jar));
+            }
+        }
+        Result::Ok(result)
+    }
+
+    pub fn icon_of(&self, id: &str) -> Result<VersionIcon, Error> {
+        let version = self.version_of(id)?;
+        let icon_path = self.0.join(version.id()).join("icon.png");
+        if icon_path.is_file() { return Result::Ok(VersionIcon::File(icon_path)); }
+        // the same blocks as those picked by the official launcher for its profiles
+        Result::Ok(VersionIcon::Builtin(match version.version_kind() {
+            _ if version.inherits_from.is_some() => "furnace",
+            VersionKind::Release => "grass",
+            VersionKind::Snapshot => "dirt",
+            VersionKind::OldBeta => "stone",
+            VersionKind::OldAlpha => "cobblestone",
+            VersionKind::Unknown => "furnace"
+        }))
+    }
+
+    pub fn version_ids(&self) -> Result<Vec<String>, Error> {
+        let mut result = Vec::new();
+        for entry in fs::read_dir(self.0.as_ref())? {
+            let path_buf = entry?.path();
+            if let Some(id) = path_buf.file_name().and_then(|name| name.to_str()) {
+                if path_buf.join(format!("{}.json", id)).is_file() { result.push(id.to_owned()) }
+            }
+        }
+        result.sort();
+        Result::Ok(result)
+    }
+
+    pub fn missing_modpack_dependencies(&self, manifest: &Value) -> Result<Vec<(String, String)>, Error> {
+        let mut installed = Vec::new();
+        for id in self.version_ids()? {
+            if let Result::Ok(version) = self.version_of(&id) {
+                let loader = version.mod_loader(self).map(|(name, version)| (name.to_owned(), version));
+                installed.push((version.base_version_id(self), loader));
+            }
+        }
+        let dependencies = modpack_dependencies(manifest);
+        let minecraft = dependencies.iter().find(|dependency| dependency.0 == "minecraft").map(|dependency| dependency.1.clone());
+        Result::Ok(dependencies.into_iter().filter(|dependency| !installed.iter().any(|(base, loader)| {
+            match dependency.0.as_str() {
+                "minecraft" => base == &dependency.1,
+                _ => Some(base) == minecraft.as_ref() && loader.as_ref() == Some(dependency)
+            }
+        })).collect())
+    }
+
+    pub fn validate(&self, id: &str) -> Result<Vec<ValidationIssue>, Error> {
+        Result::Ok(validate_version_json(&self.resolved_raw_version_of(id)?))
+    }
+
+    pub fn can_share_natives(&self, first_id: &str, second_id: &str, library_path: &Path) -> Result<bool, Error> {
+        let first = self.version_of(first_id)?.to_native_collection(self, library_path)?;
+        let second = self.version_of(second_id)?.to_native_collection(self, library_path)?;
+        Result::Ok(first.fingerprint()? == second.fingerprint()?)
+    }
+
     pub fn extract_natives(&self, id: &str, library_path: &Path) -> Result<Vec<String>, Error> {
         let info = self.version_of(id)?;
         let path_buf = self.get_natives_path(id);
         info.to_native_collection(self, library_path)?.extract_to(path_buf.as_path())
     }
 
+    pub fn raw_version_of(&self, id: &str) -> Result<Value, Error> {
+        let id = normalize_version_id(id)?;
+        let path_buf_json = self.0.join(id.as_str()).join(format!("{}.json", id));
+        if path_buf_json.exists() {
+            self.2.fetch_add(1, Ordering::SeqCst);
+            Result::Ok(serde_json::from_slice(&read_json_bytes(path_buf_json.as_path())?)?)
+        } else {
+            Result::Err(Error::FileUnavailableError(path_buf_json.into_boxed_path()))
+        }
+    }
+
+    pub fn diff(&self, old_id: &str, new_id: &str) -> Result<VersionDiff, Error> {
+        let libraries_of = |id: &str| -> Result<BTreeMap<String, String>, Error> {
+            // libraries are matched by group, artifact and classifier, regardless of their versions
+            Result::Ok(self.version_of(id)?.libraries(self)?.iter().map(|lib| {
+                let parts: Vec<_> = lib.name().split(':').collect();
+                let mut key = parts.iter().take(2).cloned().collect::<Vec<_>>();
+                key.extend(parts.iter().skip(3));
+                (key.join(":"), lib.name().to_owned())
+            }).collect())
+        };
+        let asset_index_of = |id: &str| -> Result<String, Error> {
+            Result::Ok(self.version_of(id)?.asset_index(self).map(|i| i.id().to_owned()).unwrap_or_default())
+        };
+        let (old_libraries, new_libraries) = (libraries_of(old_id)?, libraries_of(new_id)?);
+        let mut diff = VersionDiff::default();
+        for (key, old_name) in old_libraries.iter() {
+            match new_libraries.get(key) {
+                Some(new_name) if new_name != old_name => diff.changed_libraries.push((old_name.clone(), new_name.clone())),
+                Some(_) => (),
+                None => diff.removed_libraries.push(old_name.clone())
+            }
+        }
+        diff.added_libraries = new_libraries.iter().filter(|&(key, _)| !old_libraries.contains_key(key)).map(|(_, name)| name.clone()).collect();
+        let (old_asset_index, new_asset_index) = (asset_index_of(old_id)?, asset_index_of(new_id)?);
+        if old_asset_index != new_asset_index { diff.asset_index = Some((old_asset_index, new_asset_index)) }
+        Result::Ok(diff)
+    }
+
+    pub fn download_cache_key(&self, id: &str) -> Result<String, Error> {
+        let resolved = self.resolved_raw_version_of(id)?;
+        let mut hasher = Sha1::new();
+        // objects in serde_json are sorted by keys, so the serialized form is stable
+        for key in &["assetIndex", "assets", "downloads", "libraries"] {
+            hasher.update(key.as_bytes());
+            hasher.update(serde_json::to_string(resolved.get(*key).unwrap_or(&Value::Null))?.as_bytes());
+        }
+        Result::Ok(hasher.digest().to_string())
+    }
+
+    pub fn resolved_raw_version_of(&self, id: &str) -> Result<Value, Error> {
+        let mut child = self.raw_version_of(id)?;
+        let inherits_from = child.get("inheritsFrom").and_then(Value::as_str).map(String::from);
+        match (inherits_from, child.as_object_mut()) {
+            (Some(ref inherits_from), Some(child_map)) => {
+                let mut parent = self.resolved_raw_version_of(inherits_from)?;
+                child_map.remove("inheritsFrom");
+                if let Some(parent_map) = parent.as_object_mut() {
+                    for (key, value) in child_map.iter() {
+                        let merged = match (key.as_str(), parent_map.remove(key), value) {
+                            ("libraries", Some(Value::Array(mut libs)), Value::Array(child_libs)) => {
+                                libs.extend(child_libs.iter().cloned());
+                                Value::Array(libs)
+                            }
+                            ("arguments", Some(Value::Object(mut args)), Value::Object(child_args)) => {
+                                for (kind, child_list) in child_args.iter() {
+                                    let list = match (args.remove(kind), child_list) {
+                                        (Some(Value::Array(mut list)), Value::Array(child_list)) => {
+                                            list.extend(child_list.iter().cloned());
+                                            Value::Array(list)
+                                        }
+                                        _ => child_list.clone()
+                                    };
+                                    args.insert(kind.clone(), list);
+                                }
+                                Value::Object(args)
+                            }
+                            _ => value.clone()
+                        };
+                        parent_map.insert(key.clone(), merged);
+                    }
+                }
+                Result::Ok(parent)
+            }
+            _ => Result::Ok(child)
+        }
+    }
+
     pub fn version_of(&self, id: &str) -> Result<MinecraftVersion, Error> {
-        let path_buf = self.0.join(id);
+        let id = normalize_version_id(id)?;
+        let thread_id = thread::current().id();
+        if let Some(version) = self.1.lock().unwrap().get(&thread_id).and_then(|cache| cache.1.get(&id)) {
+            return Result::Ok(version.clone());
+        }
+        let path_buf = self.0.join(id.as_str());
         if !path_buf.is_dir() { fs::create_dir_all(path_buf.as_path())? }
         let path_buf_json = path_buf.join(format!("{}.json", id));
         if path_buf_json.exists() {
-            Result::Ok(serde_json::from_reader(fs::File::open(path_buf_json)?)?)
+            self.2.fetch_add(1, Ordering::SeqCst);
+            let version: MinecraftVersion = serde_json::from_slice(&read_json_bytes(path_buf_json.as_path())?)?;
+            normalize_version_id(version.id())?;
+            if let Some(cache) = self.1.lock().unwrap().get_mut(&thread_id) { cache.1.insert(id, version.clone()); }
+            Result::Ok(version)
         } else {
             Result::Err(Error::FileUnavailableError(path_buf_json.into_boxed_path()))
         }
     }
 }
 
+impl<'a> Drop for VersionCacheScope<'a> {
+    fn drop(&mut self) {
+        // nested scopes leave the cache to the outermost one of the same thread
+        let mut caches = (self.0).1.lock().unwrap();
+        let is_outermost = caches.get_mut(&self.1).map(|cache| { cache.0 -= 1; cache.0 == 0 }).unwrap_or(false);
+        if is_outermost { caches.remove(&self.1); }
+    }
+}
+
 impl MinecraftVersion {
     pub fn id(&self) -> &str {
         &self.id
@@ -209,6 +1151,16 @@ impl MinecraftVersion {
         &self.version_type
     }
 
+    pub fn version_kind(&self) -> VersionKind {
+        match self.version_type.as_str() {
+            "release" => VersionKind::Release,
+            "snapshot" => VersionKind::Snapshot,
+            "old_beta" => VersionKind::OldBeta,
+            "old_alpha" => VersionKind::OldAlpha,
+            _ => VersionKind::Unknown
+        }
+    }
+
     pub fn publish_time(&self) -> &str {
         &self.publish_time
     }
@@ -228,9 +1180,102 @@ impl MinecraftVersion {
     }
 
     pub fn main_class(&self, manager: &VersionManager) -> Option<String> {
-        self.main_class.clone().or_else(|| {
+        // the nearest layer which declares one wins, whatever its parents declare
+        self.main_class_layers(manager).into_iter().next().map(|(_, main_class)| main_class)
+    }
+
+    pub fn main_class_layers(&self, manager: &VersionManager) -> Vec<(String, String)> {
+        let mut result: Vec<_> = self.main_class.iter().map(|main_class| (self.id.clone(), main_class.clone())).collect();
+        if let Some(ref inherits_from) = self.inherits_from {
+            if let Result::Ok(parent) = manager.version_of(inherits_from) { result.extend(parent.main_class_layers(manager)) }
+        }
+        result
+    }
+
+    pub fn to_resolved_json(&self, manager: &VersionManager) -> Result<String, Error> {
+        Result::Ok(serde_json::to_string_pretty(&manager.resolved_raw_version_of(&self.id)?)?)
+    }
+
+    pub fn java_major_version(&self, manager: &VersionManager) -> Option<u32> {
+        self.java_version.as_ref().map(|v| v.major_version).or_else(|| {
             if let Some(ref inherits_from) = self.inherits_from {
-                manager.version_of(&inherits_from).ok().and_then(|v| v.main_class(manager))
+                manager.version_of(inherits_from).ok().and_then(|v| v.java_major_version(manager))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn display_name(&self, manager: &VersionManager) -> String {
+        if self.inherits_from.is_none() { return self.id.clone(); }
+        let base_id = self.base_version_id(manager);
+        let loader = self.mod_loader(manager);
+        let is_descriptive = |parts: &[&str]| parts.iter().all(|part| self.id.contains(part));
+        match loader {
+            Some((name, ref version)) if is_descriptive(&[base_id.as_str(), name, version.as_str()]) => self.id.clone(),
+            Some((name, version)) => format!("{}-{}-{}", base_id, name, version),
+            None if is_descriptive(&[base_id.as_str()]) => self.id.clone(),
+            None => format!("{} ({})", self.id, base_id)
+        }
+    }
+
+    pub fn mod_loader(&self, manager: &VersionManager) -> Option<(&'static str, String)> {
+        self.inherits_from.as_ref()?;
+        let base_id = self.base_version_id(manager);
+        self.libraries(manager).unwrap_or_default().iter().filter_map(|lib| {
+            let parts: Vec<_> = lib.name().splitn(3, ':').collect();
+            let loader_name = match (parts.first(), parts.get(1)) {
+                (Some(&"net.minecraftforge"), Some(&"forge")) => "forge",
+                (Some(&"net.neoforged"), Some(&"neoforge")) => "neoforge",
+                (Some(&"net.fabricmc"), Some(&"fabric-loader")) => "fabric",
+                (Some(&"org.quiltmc"), Some(&"quilt-loader")) => "quilt",
+                _ => return None
+            };
+            let version = parts.get(2).unwrap_or(&"");
+            let version = version.strip_prefix(base_id.as_str()).map(|v| v.trim_start_matches('-')).unwrap_or(version);
+            Some((loader_name, version.to_owned()))
+        }).next()
+    }
+
+    pub fn requires_64bit(&self, manager: &VersionManager) -> bool {
+        // natives shipped without a 32-bit classifier cannot be loaded by a 32-bit jvm
+        self.libraries(manager).unwrap_or_default().iter().filter(|lib| lib.is_native() && lib.downloads.is_allowed()).any(|lib| {
+            !lib.has_natives_of("32", OS_PLATFORM) && (lib.has_natives_of("64", OS_PLATFORM) || lib.has_natives_of("arm64", OS_PLATFORM))
+        })
+    }
+
+    pub fn classify_libraries(&self, manager: &VersionManager) -> Result<(Vec<Library>, Vec<Library>), Error> {
+        Result::Ok(self.libraries(manager)?.into_iter().partition(|lib| !lib.is_native()))
+    }
+
+    pub fn mcp_version(&self, manager: &VersionManager) -> Option<String> {
+        let base_id = self.base_version_id(manager);
+        self.libraries(manager).unwrap_or_default().iter().filter_map(|lib| {
+            let parts: Vec<_> = lib.name().split(':').collect();
+            match (parts.first(), parts.get(1), parts.get(2)) {
+                (Some(&"de.oceanlabs.mcp"), Some(&"mcp_config"), Some(version)) |
+                (Some(&"net.minecraft"), Some(&"client"), Some(version)) => {
+                    version.strip_prefix(base_id.as_str()).map(|v| v.trim_start_matches('-').to_owned())
+                }
+                _ => None
+            }
+        }).find(|version| !version.is_empty())
+    }
+
+    pub fn base_version_id(&self, manager: &VersionManager) -> String {
+        match self.inherits_from {
+            Some(ref inherits_from) => match manager.version_of(inherits_from) {
+                Result::Ok(parent) => parent.base_version_id(manager),
+                Result::Err(_) => inherits_from.clone()
+            },
+            None => self.id.clone()
+        }
+    }
+
+    pub fn compliance_level(&self, manager: &VersionManager) -> Option<u32> {
+        self.compliance_level.or_else(|| {
+            if let Some(ref inherits_from) = self.inherits_from {
+                manager.version_of(inherits_from).ok().and_then(|v| v.compliance_level(manager))
             } else {
                 None
             }
@@ -247,6 +1292,16 @@ impl MinecraftVersion {
         }
     }
 
+    pub fn client_download(&self, manager: &VersionManager) -> Option<DownloadInfo> {
+        self.downloads.get("client").cloned().or_else(|| {
+            if let Some(ref inherits_from) = self.inherits_from {
+                manager.version_of(inherits_from).ok().and_then(|v| v.client_download(manager))
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn version_jar_path(&self, manager: &VersionManager) -> Result<PathBuf, Error> {
         match self.version_jar {
             Some(ref jar) => {
@@ -262,98 +1317,303 @@ impl MinecraftVersion {
         }
     }
 
+    pub fn accepts_named_options(&self, manager: &VersionManager) -> Result<bool, Error> {
+        match self.minecraft_arguments {
+            Some(ref args) => Result::Ok(MinecraftVersion::is_named_options_template(args)),
+            None => if let Some(ref inherits_from) = self.inherits_from {
+                manager.version_of(inherits_from)?.accepts_named_options(manager)
+            } else {
+                Result::Ok(true)
+            }
+        }
+    }
+
     pub fn collect_game_arguments(&self,
                                   manager: &VersionManager,
                                   parameters: &mut Vec<launcher::GameOption>,
                                   s: &parsing::ParameterStrategy) -> Result<(), Error> {
-        let mut option_name = None;
+        let source = launcher::OptionSource::Version;
         match self.minecraft_arguments {
             Some(ref args) => {
-                for arg in parsing::parse(&args, s) {
-                    if arg.is_empty() { return Result::Ok(()); }
-                    match option_name {
-                        None => if arg.starts_with("-") {
-                            option_name = Some(arg);
-                        } else {
-                            (*parameters).push(launcher::GameOption::new_single(arg));
-                        }
-                        Some(name) => if arg.starts_with("-") {
-                            (*parameters).push(launcher::GameOption::new_single(name));
-                            option_name = Some(arg);
-                        } else {
-                            (*parameters).push(launcher::GameOption::new_pair(name, arg));
-                            option_name = None;
-                        }
-                    }
+                let mut options = Vec::new();
+                MinecraftVersion::push_game_options(parsing::parse(&args, s), &mut options, source);
+                if let Some(ref inherits_from) = self.inherits_from {
+                    // the template of a loader replaces the inherited one, so only the options it adds are its own
+                    let mut inherited = Vec::new();
+                    manager.version_of(inherits_from)?.collect_game_arguments(manager, &mut inherited, s)?;
+                    let inherited_names: HashSet<_> = inherited.iter().map(|option| option.name().to_owned()).collect();
+                    options = options.into_iter().map(|option| if inherited_names.contains(option.name()) {
+                        option
+                    } else {
+                        option.with_source(launcher::OptionSource::Loader)
+                    }).collect();
                 }
-                if let Some(name) = option_name {
-                    (*parameters).push(launcher::GameOption::new_single(name));
+                parameters.extend(options);
+                if MinecraftVersion::is_named_options_template(args) {
+                    parameters.push(launcher::GameOption::new_pair("--width".to_owned(), self.parse_token("${resolution_width}", s), source));
+                    parameters.push(launcher::GameOption::new_pair("--height".to_owned(), self.parse_token("${resolution_height}", s), source));
                 }
-                parameters.push(launcher::GameOption::new_pair("--width".to_owned(), self.parse_token("${resolution_width}", s)));
-                parameters.push(launcher::GameOption::new_pair("--height".to_owned(), self.parse_token("${resolution_height}", s)));
             }
-            None => if let Some(ref inherits_from) = self.inherits_from {
-                let version = manager.version_of(&inherits_from)?;
-                return version.collect_game_arguments(manager, parameters, s);
+            None => match self.inherits_from {
+                Some(ref inherits_from) if self.arguments.is_none() => {
+                    let version = manager.version_of(&inherits_from)?;
+                    return version.collect_game_arguments(manager, parameters, s);
+                }
+                _ => {
+                    let layers = self.modern_argument_layers(manager, |a| &a.game)?;
+                    if layers.iter().any(|layer| !layer.1.is_empty()) {
+                        for (layer_source, args) in layers {
+                            let args = args.iter().map(|arg| parsing::substitute(arg, s));
+                            MinecraftVersion::push_game_options(args, parameters, layer_source);
+                        }
+                        parameters.push(launcher::GameOption::new_pair("--width".to_owned(), self.parse_token("${resolution_width}", s), source));
+                        parameters.push(launcher::GameOption::new_pair("--height".to_owned(), self.parse_token("${resolution_height}", s), source));
+                    }
+                }
             }
         }
         Result::Ok(())
     }
 
-    pub fn collect_jvm_arguments(&self,
-                                 _: &VersionManager,
-                                 parameters: &mut Vec<launcher::JvmOption>,
-                                 s: &parsing::ParameterStrategy) -> Result<(), Error> {
-        if OS_PLATFORM == "windows" { parameters.push(launcher::JvmOption::new("-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump".to_owned())); }
-        parameters.push(launcher::JvmOption::new(self.parse_token("-Djava.library.path=${natives_directory}", s)));
-        parameters.push(launcher::JvmOption::new(self.parse_token("-Dminecraft.launcher.brand=${launcher_name}", s)));
-        parameters.push(launcher::JvmOption::new(self.parse_token("-Dminecraft.launcher.version=${launcher_version}", s)));
-        parameters.push(launcher::JvmOption::new(self.parse_token("-Dminecraft.client.jar=${primary_jar}", s)));
-        parameters.push(launcher::JvmOption::new("-cp".to_owned()));
-        parameters.push(launcher::JvmOption::new(self.parse_token("${classpath}", s)));
-        Result::Ok(())
+    pub fn collect_jvm_arguments(&self,
+                                 manager: &VersionManager,
+                                 parameters: &mut Vec<launcher::JvmOption>,
+                                 s: &parsing::ParameterStrategy) -> Result<(), Error> {
+        let source = launcher::OptionSource::Version;
+        for template in self.jvm_argument_templates(manager)? {
+            parameters.push(launcher::JvmOption::new(parsing::substitute(&template, s), source));
+        }
+        Result::Ok(())
+    }
+
+    pub fn jvm_argument_templates(&self, manager: &VersionManager) -> Result<Vec<String>, Error> {
+        let mut result = self.modern_arguments(manager, |a| &a.jvm)?;
+        if result.is_empty() {
+            if OS_PLATFORM == "windows" { result.push("-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump".to_owned()); }
+            result.push("-Djava.library.path=${natives_directory}".to_owned());
+            result.push("-Dminecraft.launcher.brand=${launcher_name}".to_owned());
+            result.push("-Dminecraft.launcher.version=${launcher_version}".to_owned());
+            result.push("-Dminecraft.client.jar=${primary_jar}".to_owned());
+            result.push("-cp".to_owned());
+            result.push("${classpath}".to_owned());
+        }
+        Result::Ok(result)
+    }
+
+    pub fn game_argument_templates(&self, manager: &VersionManager) -> Result<Vec<String>, Error> {
+        let mut result = match self.minecraft_arguments {
+            Some(ref args) => parsing::parse(args, &parsing::ParameterStrategy::ignore()).collect(),
+            None => match self.inherits_from {
+                Some(ref inherits_from) if self.arguments.is_none() => {
+                    return manager.version_of(inherits_from)?.game_argument_templates(manager);
+                }
+                _ => self.modern_arguments(manager, |a| &a.game)?
+            }
+        };
+        if !result.is_empty() && self.accepts_named_options(manager)? {
+            result.extend(["--width", "${resolution_width}", "--height", "${resolution_height}"].iter().map(|s| s.to_string()));
+        }
+        Result::Ok(result)
+    }
+
+    pub fn required_placeholders(&self, manager: &VersionManager) -> Result<HashSet<String>, Error> {
+        let mut templates = self.jvm_argument_templates(manager)?;
+        templates.extend(self.game_argument_templates(manager)?);
+        Result::Ok(templates.iter().flat_map(|template| parsing::placeholders(template)).collect())
+    }
+
+    pub fn classpath(&self,
+                     library_path: &Path,
+                     manager: &VersionManager) -> Result<String, Error> {
+        self.classpath_with_separator(library_path, CLASSPATH_SEPARATOR, manager)
+    }
+
+    pub fn classpath_with_separator(&self,
+                                    library_path: &Path,
+                                    classpath_separator: &str,
+                                    manager: &VersionManager) -> Result<String, Error> {
+        join_classpath(self.classpath_entries(library_path, manager)?, classpath_separator)
+    }
+
+    pub fn write_classpath_file(&self,
+                                path: &Path,
+                                manager: &VersionManager,
+                                library_path: &Path) -> Result<(), Error> {
+        let mut content = join_classpath(self.classpath_entries(library_path, manager)?, "\n")?;
+        content.push('\n');
+        Result::Ok(fs::write(path, content)?)
+    }
+
+    pub fn classpath_relative_to(&self,
+                                 library_path: &Path,
+                                 base_dir: &Path,
+                                 classpath_separator: &str,
+                                 manager: &VersionManager) -> Result<String, Error> {
+        let entries = self.classpath_entries(library_path, manager)?;
+        let relative_entries = entries.iter().map(|path_buf| relative_path(path_buf, base_dir)).collect();
+        join_classpath(relative_entries, classpath_separator)
+    }
+
+    pub fn classpath_entries(&self,
+                             library_path: &Path,
+                             manager: &VersionManager) -> Result<Vec<PathBuf>, Error> {
+        let mut result = self.library_classpath_entries(library_path, manager)?;
+        result.push(self.version_jar_path(manager)?);
+        Result::Ok(result)
+    }
+
+    pub fn library_classpath_entries(&self,
+                                     library_path: &Path,
+                                     manager: &VersionManager) -> Result<Vec<PathBuf>, Error> {
+        self.library_classpath_entries_with_classifiers(library_path, &HashMap::new(), manager)
+    }
+
+    pub fn library_classpath_entries_with_classifiers(&self,
+                                                      library_path: &Path,
+                                                      classifiers: &HashMap<String, String>,
+                                                      manager: &VersionManager) -> Result<Vec<PathBuf>, Error> {
+        let mut result = Vec::new();
+        for path_buf in self.library_paths_with_classifiers(library_path, classifiers, manager)? {
+            result.push(fs::canonicalize(path_buf.as_path())?);
+        }
+        Result::Ok(result)
+    }
+
+    pub fn library_paths(&self,
+                         library_path: &Path,
+                         manager: &VersionManager) -> Result<Vec<PathBuf>, Error> {
+        self.library_paths_with_classifiers(library_path, &HashMap::new(), manager)
+    }
+
+    pub fn library_paths_with_classifiers(&self,
+                                          library_path: &Path,
+                                          classifiers: &HashMap<String, String>,
+                                          manager: &VersionManager) -> Result<Vec<PathBuf>, Error> {
+        let libs = without_superseded_natives(self.libraries(manager)?);
+        Result::Ok(libs.iter().filter_map(|lib| {
+            // libraries such as lwjgl 3.1 to 3.2 ship both a classpath artifact and natives to extract
+            if lib.is_native() { lib.artifact_classpath(library_path) } else { lib.classpath_with_overrides(library_path, classifiers) }
+        }).collect())
     }
 
-    pub fn classpath(&self,
-                     library_path: &Path,
-                     manager: &VersionManager) -> Result<String, Error> {
-        self.classpath_with_separator(library_path, CLASSPATH_SEPARATOR, manager)
+    pub fn needs_natives_extraction(&self, manager: &VersionManager) -> Result<bool, Error> {
+        // lwjgl 3.3+ (1.19+) ships natives as plain classpath entries and unpacks them itself
+        Result::Ok(self.libraries(manager)?.iter().any(Library::is_native))
     }
 
-    pub fn classpath_with_separator(&self,
-                                    library_path: &Path,
-                                    classpath_separator: &str,
-                                    manager: &VersionManager) -> Result<String, Error> {
-        let libs = self.libraries(manager)?;
-        let mut result = String::new();
-        for lib in libs.iter() {
-            if !lib.is_native() {
-                if let Some(path_buf) = lib.classpath_default(library_path) {
-                    let path = fs::canonicalize(path_buf.as_path())?.into_os_string();
-                    result.push_str(&path.into_string()?);
-                    result.push_str(classpath_separator);
+    pub fn to_native_collection_with_classifier(&self,
+                                                manager: &VersionManager,
+                                                library_path: &Path,
+                                                classifier: &str) -> Result<NativeCollection, Error> {
+        let mut collection = NativeCollection::default();
+        let mut classifier_found = false;
+        for lib in without_superseded_natives(self.libraries(manager)?).iter() {
+            if lib.is_native() {
+                let forced_path = lib.classpath_with_classifier(library_path, classifier);
+                classifier_found = classifier_found || forced_path.is_some();
+                if let Some(path_buf) = forced_path.or_else(|| lib.classpath_default(library_path)) {
+                    collection.libraries.push((path_buf, lib.extract_ignored.clone()))
                 }
             }
         }
-        let primary_jar_path = self.version_jar_path(manager)?.into_os_string();
-        result.push_str(primary_jar_path.into_string()?.as_str());
-        Result::Ok(result)
+        if classifier_found {
+            Result::Ok(collection)
+        } else {
+            Result::Err(Error::UnknownClassifier(classifier.to_owned()))
+        }
     }
 
     pub fn to_native_collection(&self,
                                 manager: &VersionManager,
                                 library_path: &Path) -> Result<NativeCollection, Error> {
-        let mut collection = NativeCollection { libraries: Vec::new() };
-        for lib in self.libraries(manager)?.iter() {
+        self.to_native_collection_with_classifiers(manager, library_path, &HashMap::new())
+    }
+
+    pub fn to_native_collection_with_classifiers(&self,
+                                                 manager: &VersionManager,
+                                                 library_path: &Path,
+                                                 classifiers: &HashMap<String, String>) -> Result<NativeCollection, Error> {
+        let mut collection = NativeCollection::default();
+        let mut fallbacks = Vec::new();
+        for lib in without_superseded_natives(self.libraries(manager)?).iter() {
             if lib.is_native() {
-                if let Some(path_buf) = lib.classpath_default(library_path) {
+                if let Some(path_buf) = lib.classpath_with_overrides(library_path, classifiers) {
                     collection.libraries.push((path_buf, lib.extract_ignored.clone()))
                 }
+                if lib.falls_back_to_x86_64(OS_ARCH, OS_PLATFORM) { fallbacks.push(lib.name().to_owned()) }
             }
         }
+        if !fallbacks.is_empty() {
+            warn!("No arm64 natives available for {}, falling back to x86_64 ones which require rosetta", fallbacks.join(", "));
+        }
         Result::Ok(collection)
     }
 
+    fn modern_arguments(&self,
+                        manager: &VersionManager,
+                        select: fn(&VersionArguments) -> &Vec<Value>) -> Result<Vec<String>, Error> {
+        Result::Ok(self.modern_argument_layers(manager, select)?.into_iter().flat_map(|layer| layer.1).collect())
+    }
+
+    // the arguments of each version in the chain, which are from a loader once the version inherits another one
+    fn modern_argument_layers(&self,
+                              manager: &VersionManager,
+                              select: fn(&VersionArguments) -> &Vec<Value>) -> Result<Vec<(launcher::OptionSource, Vec<String>)>, Error> {
+        let mut result = match self.inherits_from {
+            Some(ref inherits_from) => manager.version_of(inherits_from)?.modern_argument_layers(manager, select)?,
+            None => Vec::new()
+        };
+        if let Some(ref arguments) = self.arguments {
+            let source = if self.inherits_from.is_some() { launcher::OptionSource::Loader } else { launcher::OptionSource::Version };
+            result.push((source, allowed_argument_values(select(arguments))));
+        }
+        Result::Ok(result)
+    }
+
+    pub fn known_features(&self, manager: &VersionManager) -> HashSet<String> {
+        let mut result = match self.inherits_from {
+            Some(ref inherits_from) => manager.version_of(inherits_from).map(|v| v.known_features(manager)).unwrap_or_default(),
+            None => HashSet::new()
+        };
+        let arguments = self.arguments.iter().flat_map(|arguments| arguments.game.iter().chain(arguments.jvm.iter()));
+        for rule in arguments.filter_map(|item| item.get("rules")).filter_map(Value::as_array).flatten() {
+            if let Some(features) = rule.get("features").and_then(Value::as_object) {
+                result.extend(features.keys().cloned());
+            }
+        }
+        result
+    }
+
+    fn push_game_options<I: Iterator<Item = String>>(args: I,
+                                                     parameters: &mut Vec<launcher::GameOption>,
+                                                     source: launcher::OptionSource) {
+        let mut option_name = None;
+        for arg in args {
+            match option_name {
+                None => if arg.starts_with("-") {
+                    option_name = Some(arg);
+                } else {
+                    parameters.push(launcher::GameOption::new_single(arg, source));
+                }
+                Some(name) => if arg.starts_with("-") {
+                    parameters.push(launcher::GameOption::new_single(name, source));
+                    option_name = Some(arg);
+                } else {
+                    parameters.push(launcher::GameOption::new_pair(name, arg, source));
+                    option_name = None;
+                }
+            }
+        }
+        if let Some(name) = option_name {
+            parameters.push(launcher::GameOption::new_single(name, source));
+        }
+    }
+
+    fn is_named_options_template(args: &str) -> bool {
+        // versions before 1.6 take positional arguments and reject unknown options
+        args.trim_start().starts_with("--")
+    }
+
     fn parse_token(&self, token: &str, s: &parsing::ParameterStrategy) -> String {
         match parsing::parse(token, s).next() {
             Some(parsed_token) => parsed_token,
@@ -377,6 +1637,110 @@ impl AssetDownloadInfo {
     pub fn id(&self) -> &str {
         &self.asset_index_id
     }
+
+    pub fn index_path(&self, assets_dir: &Path) -> PathBuf {
+        assets_dir.join("indexes").join(format!("{}.json", self.asset_index_id))
+    }
+
+    pub fn virtual_path(&self, assets_dir: &Path) -> PathBuf {
+        assets_dir.join("virtual").join(self.asset_index_id.as_str())
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.asset_index_id == "legacy" || self.asset_index_id == "pre-1.6"
+    }
+
+    pub fn legacy_object_path(&self, assets_dir: &Path, name: &str) -> PathBuf {
+        self.virtual_path(assets_dir).join(name)
+    }
+
+    pub fn object_url(host: &str, hash: &str) -> String {
+        format!("{}/{}/{}", host.trim_end_matches('/'), hash.get(0..2).unwrap_or(""), hash)
+    }
+
+    pub fn object_hashes(&self, assets_dir: &Path) -> Result<Vec<String>, Error> {
+        Result::Ok(self.objects(assets_dir)?.into_iter().map(|(_, hash)| hash).collect())
+    }
+
+    pub fn objects(&self, assets_dir: &Path) -> Result<Vec<(String, String)>, Error> {
+        let index: Value = serde_json::from_reader(fs::File::open(self.index_path(assets_dir))?)?;
+        let objects = index.get("objects").and_then(Value::as_object);
+        Result::Ok(objects.iter().flat_map(|objects| objects.iter()).filter_map(|(name, object)| {
+            object.get("hash").and_then(Value::as_str).map(|hash| (name.clone(), hash.to_owned()))
+        }).collect())
+    }
+
+    pub fn object_sizes(&self, assets_dir: &Path) -> Result<HashMap<String, u64>, Error> {
+        let index: Value = serde_json::from_reader(fs::File::open(self.index_path(assets_dir))?)?;
+        let objects = index.get("objects").and_then(Value::as_object);
+        Result::Ok(objects.iter().flat_map(|objects| objects.values()).filter_map(|object| {
+            match (object.get("hash").and_then(Value::as_str), object.get("size").and_then(Value::as_u64)) {
+                (Some(hash), Some(size)) => Some((hash.to_owned(), size)),
+                _ => None
+            }
+        }).collect())
+    }
+
+    pub fn maps_to_resources(&self, assets_dir: &Path) -> Result<bool, Error> {
+        Result::Ok(self.layout(assets_dir)? == AssetLayout::Resources)
+    }
+
+    pub fn layout(&self, assets_dir: &Path) -> Result<AssetLayout, Error> {
+        // the flags of the index itself are more reliable than guessing from the release date
+        let index: Value = serde_json::from_reader(fs::File::open(self.index_path(assets_dir))?)?;
+        let flag = |key: &str| index.get(key).and_then(Value::as_bool).unwrap_or(false);
+        Result::Ok(if flag("map_to_resources") {
+            AssetLayout::Resources
+        } else if flag("virtual") {
+            AssetLayout::Virtual
+        } else {
+            AssetLayout::Objects
+        })
+    }
+
+    pub fn object_path(objects_dir: &Path, hash: &str) -> PathBuf {
+        objects_dir.join(hash.get(0..2).unwrap_or("")).join(hash)
+    }
+
+    pub fn verify_index(&self, assets_dir: &Path) -> Result<(), Error> {
+        let path_buf = self.index_path(assets_dir);
+        let size = fs::metadata(path_buf.as_path())?.len();
+        let size_matches = match self.size {
+            Some(expected) => expected as u64 == size,
+            None => true
+        };
+        let sha1_matches = match self.sha1 {
+            Some(ref expected) => expected.eq_ignore_ascii_case(&file_sha1(path_buf.as_path())?),
+            None => true
+        };
+        if size_matches && sha1_matches {
+            Result::Ok(())
+        } else {
+            Result::Err(Error::AssetIndexCorrupt(path_buf.into_boxed_path()))
+        }
+    }
+}
+
+impl DownloadInfo {
+    pub fn sha1(&self) -> Option<&str> {
+        match *self {
+            DownloadInfo::PreHashed { ref sha1, .. } => Some(sha1.as_str()),
+            _ => None
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        match *self {
+            DownloadInfo::PreHashed { ref url, .. } | DownloadInfo::RawXzip { ref url } | DownloadInfo::Raw { ref url } => url
+        }
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        match *self {
+            DownloadInfo::PreHashed { size, .. } if size >= 0 => Some(size as u64),
+            _ => None
+        }
+    }
 }
 
 impl From<AssetDownloadInfo> for DownloadInfo {
@@ -392,20 +1756,82 @@ impl From<AssetDownloadInfo> for DownloadInfo {
     }
 }
 
+impl VersionDiff {
+    pub fn added_libraries(&self) -> &[String] {
+        &self.added_libraries
+    }
+
+    pub fn removed_libraries(&self) -> &[String] {
+        &self.removed_libraries
+    }
+
+    pub fn changed_libraries(&self) -> &[(String, String)] {
+        &self.changed_libraries
+    }
+
+    pub fn asset_index(&self) -> Option<(&str, &str)> {
+        self.asset_index.as_ref().map(|(old, new)| (old.as_str(), new.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_libraries.is_empty() && self.removed_libraries.is_empty() && self.changed_libraries.is_empty() && self.asset_index.is_none()
+    }
+}
+
+impl ValidationIssue {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
 impl DownloadStrategy {
-    fn get<'a>(&'a self, arg: &str) -> Option<(&'a str, &'a DownloadInfo)> {
+    fn is_allowed(&self) -> bool {
         let mut allowed = self.rules.is_empty();
         for &(ref action, ref os) in &self.rules {
-            match action.as_str() {
-                "allow" => allowed = os.is_empty() || os == OS_PLATFORM,
-                "disallow" => allowed = !os.is_empty() && os != OS_PLATFORM,
-                _ => () // just ignore it
+            if os.is_empty() || os == OS_PLATFORM {
+                match action.as_str() {
+                    "allow" => allowed = true,
+                    "disallow" => allowed = false,
+                    _ => () // just ignore it
+                }
             }
         }
-        if allowed {
-            match self.with_classifier.get(arg) {
-                Some(&(ref classifier, ref info)) => Some((&classifier, &info)),
-                None => self.default.as_ref().map(|v| ("", v))
+        allowed
+    }
+
+    fn get_by_classifier<'a>(&'a self, classifier: &str) -> Option<(&'a str, &'a DownloadInfo)> {
+        if self.is_allowed() {
+            self.with_classifier.values().find(|v| v.0 == classifier).map(|v| (v.0.as_str(), &v.1))
+        } else {
+            None
+        }
+    }
+
+    fn get<'a>(&'a self, arg: &str) -> Option<(&'a str, &'a DownloadInfo)> {
+        self.get_with_fallback(arg).map(|(classifier, info, _)| (classifier, info))
+    }
+
+    // the flag tells whether x86_64 natives are used in place of missing arm64 ones
+    fn get_with_fallback<'a>(&'a self, arg: &str) -> Option<(&'a str, &'a DownloadInfo, bool)> {
+        if self.is_allowed() {
+            let entry = self.with_classifier.get(arg).map(|entry| (entry, false)).or_else(|| {
+                // old versions only ship x86_64 natives for apple silicon, which still work under rosetta
+                let platform = arg.strip_prefix("arm64bit ")?;
+                self.with_classifier.get(&format!("64bit {}", platform)).map(|entry| (entry, true))
+            });
+            match entry {
+                Some((&(ref classifier, ref info), is_fallback)) => Some((classifier, info, is_fallback)),
+                None => self.default.as_ref().map(|v| ("", v, false))
             }
         } else {
             None
@@ -414,10 +1840,22 @@ impl DownloadStrategy {
 }
 
 impl Library {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn is_native(&self) -> bool {
         self.is_native
     }
 
+    pub fn has_natives_of(&self, arch: &str, platform: &str) -> bool {
+        self.downloads.with_classifier.contains_key(&format!("{}bit {}", arch, platform))
+    }
+
+    pub fn falls_back_to_x86_64(&self, arch: &str, platform: &str) -> bool {
+        self.downloads.get_with_fallback(&format!("{}bit {}", arch, platform)).is_some_and(|entry| entry.2)
+    }
+
     pub fn download_info_default(&self) -> Option<&DownloadInfo> {
         self.download_info_of(OS_ARCH, OS_PLATFORM)
     }
@@ -447,6 +1885,30 @@ impl Library {
         }
     }
 
+    pub fn artifact_classpath(&self, path: &Path) -> Option<PathBuf> {
+        let downloads = self.downloads.as_ref();
+        if !downloads.is_allowed() { return None; }
+        downloads.default.as_ref()?;
+        Library::get_url_suffix(&self.name, "", false).map(|suffix| path.join(suffix))
+    }
+
+    pub fn classpath_with_classifier(&self, path: &Path, classifier: &str) -> Option<PathBuf> {
+        let info = self.downloads.as_ref().get_by_classifier(classifier)?;
+        Library::get_url_suffix(&self.name, info.0, false).map(|suffix| path.join(suffix))
+    }
+
+    pub fn classpath_with_overrides(&self, path: &Path, classifiers: &HashMap<String, String>) -> Option<PathBuf> {
+        let key = self.name.splitn(3, ':').take(2).collect::<Vec<_>>().join(":");
+        match classifiers.get(&key) {
+            Some(classifier) if self.is_native => self.classpath_with_classifier(path, classifier),
+            Some(classifier) if self.downloads.is_allowed() => {
+                Library::get_url_suffix(&self.name, classifier, false).map(|suffix| path.join(suffix))
+            }
+            Some(_) => None,
+            None => self.classpath_default(path)
+        }
+    }
+
     fn get_as_result<E: de::Error>(v: &Value, expected: &str) -> Result<String, E> {
         v.as_str().map(String::from).ok_or_else(|| {
             de::Error::invalid_type(de::Unexpected::UnitVariant, &expected)
@@ -454,16 +1916,29 @@ impl Library {
     }
 
     fn get_url_suffix(name: &str, classifier: &str, is_xz: bool) -> Option<String> {
-        let parts: Vec<_> = name.splitn(3, ':').collect();
-        if parts.len() != 3 { None } else {
-            let suffix = if is_xz { "jar.pack.xz" } else { "jar" };
-            let dir = format!("{}/{}/{}", parts[0].replace(".", "/"), parts[1], parts[2]);
-            if classifier.is_empty() {
-                Some(format!("{}/{}-{}.{}", dir, parts[1], parts[2], suffix))
-            } else {
-                Some(format!("{}/{}-{}-{}.{}", dir, parts[1], parts[2], classifier, suffix))
+        let coordinate = if classifier.is_empty() { name.to_owned() } else { format!("{}:{}", name, classifier) };
+        let suffix = maven_suffix(&coordinate)?;
+        if is_xz { Some(format!("{}.pack.xz", suffix)) } else { Some(suffix) }
+    }
+
+    fn push_rules<E: de::Error>(value: &Value, rules: &mut Vec<(String, String)>) -> Result<(), E> {
+        if let Some(list) = value.as_array() {
+            for v in list {
+                if let Some(map) = v.as_object() {
+                    if let Some(value) = map.get("action") {
+                        let action = Library::get_as_result(value, "rule action")?;
+                        if let Some(os) = map.get("os").and_then(|v| {
+                            v.as_object().and_then(|v| v.get("name"))
+                        }).map(|v| Library::get_as_result(v, "rule os")) {
+                            rules.push((action, os?));
+                        } else {
+                            rules.push((action, String::new()));
+                        }
+                    }
+                }
             }
         }
+        Result::Ok(())
     }
 
     fn deserialize_map<'de, A>(mut map: A) -> Result<Library, A::Error> where A: MapAccess<'de> {
@@ -483,12 +1958,15 @@ impl Library {
                 "name" => name = Library::get_as_result(&value, "library name")?,
                 "url" => url_prefix = Library::get_as_result(&value, "library url prefix")?,
                 "checksums" => is_xz = value.is_array(),
-                "extract" => if let Some(extract_rules) = value.as_object().and_then(|o| {
-                    o.get("exclude").and_then(|v| v.as_array())
-                }) {
-                    for v in extract_rules.iter() {
-                        let rule = Library::get_as_result(v, "extract rules")?;
-                        extract_ignored.push(rule);
+                "extract" => if let Some(extract) = value.as_object() {
+                    if let Some(extract_rules) = extract.get("exclude").and_then(|v| v.as_array()) {
+                        for v in extract_rules.iter() {
+                            let rule = Library::get_as_result(v, "extract rules")?;
+                            extract_ignored.push(rule);
+                        }
+                    }
+                    if let Some(rules) = extract.get("rules") {
+                        Library::push_rules(rules, &mut library_downloads.rules)?;
                     }
                 }
                 "natives" => if let Some(map) = value.as_object() {
@@ -497,22 +1975,7 @@ impl Library {
                         natives.insert(k.clone(), classifier);
                     }
                 }
-                "rules" => if let Some(list) = value.as_array() {
-                    for v in list {
-                        if let Some(map) = v.as_object() {
-                            if let Some(value) = map.get("action") {
-                                let action = Library::get_as_result(value, "rule action")?;
-                                if let Some(os) = map.get("os").and_then(|v| {
-                                    v.as_object().and_then(|v| v.get("name"))
-                                }).map(|v| Library::get_as_result(v, "rule os")) {
-                                    library_downloads.rules.push((action, os?));
-                                } else {
-                                    library_downloads.rules.push((action, String::new()));
-                                }
-                            }
-                        }
-                    }
-                }
+                "rules" => Library::push_rules(&value, &mut library_downloads.rules)?,
                 "downloads" => if value.is_object() {
                     downloads = value.clone();
                 }
@@ -535,12 +1998,19 @@ impl Library {
                             let key = format!("32bit {}", os);
                             library_downloads.with_classifier.insert(key, (classifier_32, download_info));
                         }
+                        let classifier_arm64 = format!("{}-arm64", classifier_64);
                         if let Some(download_info) = classifiers.get(&classifier_64).and_then(|v| {
                             serde_json::from_value::<DownloadInfo>(v.clone()).ok()
                         }) {
                             let key = format!("64bit {}", os);
                             library_downloads.with_classifier.insert(key, (classifier_64, download_info));
                         }
+                        if let Some(download_info) = classifiers.get(&classifier_arm64).and_then(|v| {
+                            serde_json::from_value::<DownloadInfo>(v.clone()).ok()
+                        }) {
+                            let key = format!("arm64bit {}", os);
+                            library_downloads.with_classifier.insert(key, (classifier_arm64, download_info));
+                        }
                     }
                 }
                 if let Some(download_info) = map.get("artifact").and_then(|v| {
@@ -551,11 +2021,11 @@ impl Library {
                 return Result::Ok(Library {
                     name,
                     is_native: !natives.is_empty(),
-                    downloads: Rc::new(library_downloads),
-                    extract_ignored: Rc::new(extract_ignored),
+                    downloads: Arc::new(library_downloads),
+                    extract_ignored: Arc::new(extract_ignored),
                 });
             }
-            url_prefix.push_str("https://libraries.minecraft.net/");
+            url_prefix = format!("{}/", LIBRARIES_HOST);
         }
         if natives.is_empty() {
             if let Some(suffix) = Library::get_url_suffix(&name, "", is_xz) {
@@ -588,8 +2058,8 @@ impl Library {
         Result::Ok(Library {
             name,
             is_native: !natives.is_empty(),
-            downloads: Rc::new(library_downloads),
-            extract_ignored: Rc::new(extract_ignored),
+            downloads: Arc::new(library_downloads),
+            extract_ignored: Arc::new(extract_ignored),
         })
     }
 }
@@ -613,3 +2083,780 @@ impl<'de> Deserialize<'de> for Library {
         deserializer.deserialize_map(LibraryVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn prepare_dir(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("rmcll-test-{}", name));
+        if root.exists() { fs::remove_dir_all(root.as_path()).unwrap() }
+        fs::create_dir_all(root.as_path()).unwrap();
+        fs::canonicalize(root).unwrap()
+    }
+
+    fn prepare_game_dir(name: &str, version_id: &str, version_json: &str) -> PathBuf {
+        let root = prepare_dir(name);
+        let version_dir = root.join("versions").join(version_id);
+        fs::create_dir_all(version_dir.as_path()).unwrap();
+        fs::write(version_dir.join(format!("{}.json", version_id)), version_json).unwrap();
+        root
+    }
+
+    fn touch(path: PathBuf) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    fn write_jar(path: PathBuf, entries: &[(&str, &[u8])]) -> PathBuf {
+        use std::io::Write;
+        use zip::write::{FileOptions, ZipWriter};
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut zip = ZipWriter::new(fs::File::create(path.as_path()).unwrap());
+        for &(name, content) in entries {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn cache_scopes_of_other_threads_are_kept_apart() {
+        let json = |version_type: &str| format!(r#"{{ "id": "1.12.2", "type": "{}", "time": "", "releaseTime": "" }}"#, version_type);
+        let root = prepare_game_dir("cache-scopes", "1.12.2", &json("old_alpha"));
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let outer = manager.cache_scope();
+        {
+            let _inner = manager.cache_scope();
+            assert_eq!(manager.version_of("1.12.2").unwrap().version_type(), "old_alpha");
+        }
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), json("release")).unwrap();
+        let manager = &manager;
+        thread::scope(|scope| scope.spawn(move || {
+            let _scope = manager.cache_scope();
+            assert_eq!(manager.version_of("1.12.2").unwrap().version_type(), "release");
+        }).join().unwrap());
+        // neither the nested scope nor the other thread has cleared the outer cache
+        assert_eq!(manager.version_of("1.12.2").unwrap().version_type(), "old_alpha");
+        assert_eq!(manager.json_reads(), 2);
+        drop(outer);
+        assert_eq!(manager.version_of("1.12.2").unwrap().version_type(), "release");
+        assert_eq!(manager.json_reads(), 3);
+    }
+
+    #[test]
+    fn relative_classpath_for_portable_layout() {
+        let root = prepare_game_dir("relative-classpath", "portable", r#"{
+            "id": "portable", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{ "name": "com.example:lib:1.0" }]
+        }"#);
+        touch(root.join("libraries/com/example/lib/1.0/lib-1.0.jar"));
+        touch(root.join("versions/portable/portable.jar"));
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("portable").unwrap();
+        let classpath = version.classpath_relative_to(root.join("libraries").as_path(), root.as_path(), ":", &manager);
+        let expected = "libraries/com/example/lib/1.0/lib-1.0.jar:versions/portable/portable.jar";
+        assert_eq!(classpath.unwrap(), expected);
+    }
+
+    #[test]
+    fn conflicting_natives_are_reported() {
+        let root = prepare_dir("conflicting-natives");
+        let excluded = Arc::new(vec!["META-INF/".to_owned()]);
+        let first = write_jar(root.join("a-natives.jar"), &[("liblwjgl.so", b"a"), ("META-INF/MANIFEST.MF", b"")]);
+        let second = write_jar(root.join("b-natives.jar"), &[("liblwjgl.so", b"b"), ("libopenal.so", b"")]);
+        let collection = NativeCollection { libraries: vec![(first, excluded.clone()), (second, excluded)], strip_versions: false };
+        assert_eq!(collection.conflicting_entries().unwrap(), vec!["liblwjgl.so".to_owned()]);
+        match collection.check_conflicts() {
+            Result::Err(Error::ConflictingNatives(names)) => assert_eq!(names, vec!["liblwjgl.so".to_owned()]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let first = write_jar(root.join("c-natives.jar"), &[("libglfw.so", b""), ("META-INF/MANIFEST.MF", b"c")]);
+        let second = write_jar(root.join("d-natives.jar"), &[("libjemalloc.so", b""), ("META-INF/MANIFEST.MF", b"d")]);
+        let collection = NativeCollection::new(vec![(first, Vec::new()), (second, Vec::new())]);
+        assert!(collection.check_conflicts().is_ok());
+    }
+
+    #[test]
+    fn unsafe_natives_entries_are_rejected() {
+        let root = prepare_dir("unsafe-natives");
+        let jar = write_jar(root.join("evil-natives.jar"), &[("libfine.so", b""), ("../rmcll-test-escaped.so", b"")]);
+        let collection = NativeCollection::new(vec![(jar, Vec::new())]);
+        match collection.extract_to(root.join("natives").as_path()) {
+            Result::Err(Error::UnsafeArchiveEntry(name)) => assert_eq!(name, "../rmcll-test-escaped.so"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!root.join("rmcll-test-escaped.so").exists());
+    }
+
+    #[test]
+    fn download_cache_key_follows_libraries() {
+        let json = |libraries: &str| format!(r#"{{
+            "id": "1.12.2", "type": "release", "time": "2017-09-18T08:39:46+00:00", "releaseTime": "", "assets": "1.12",
+            "downloads": {{ "client": {{ "sha1": "0f275bc1547d01fa5f56ba34bdc87d981ee12daf", "size": 10180113, "url": "" }} }},
+            "libraries": [{}]
+        }}"#, libraries);
+        let root = prepare_game_dir("download-cache-key", "1.12.2", &json(r#"{ "name": "com.mojang:patchy:1.1" }"#));
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let key = manager.download_cache_key("1.12.2").unwrap();
+        assert_eq!(key.len(), 40);
+        assert_eq!(manager.download_cache_key("1.12.2").unwrap(), key);
+        let json_path = root.join("versions/1.12.2/1.12.2.json");
+        fs::write(json_path.as_path(), json(r#"{ "name": "com.mojang:patchy:1.1" }"#).replace("2017-09-18", "2017-09-19")).unwrap();
+        assert_eq!(manager.download_cache_key("1.12.2").unwrap(), key);
+        fs::write(json_path.as_path(), json(r#"{ "name": "com.mojang:patchy:1.1" }, { "name": "oshi-project:oshi-core:1.1" }"#)).unwrap();
+        assert_ne!(manager.download_cache_key("1.12.2").unwrap(), key);
+    }
+
+    #[test]
+    fn misplaced_version_files_are_relocated() {
+        let root = prepare_dir("repair-layout");
+        let versions = root.join("versions");
+        fs::create_dir_all(versions.join("1.12.2")).unwrap();
+        fs::write(versions.join("1.12.2/client.json"), r#"{ "id": "1.12.2", "type": "release", "time": "", "releaseTime": "" }"#).unwrap();
+        touch(versions.join("1.12.2/client.jar"));
+        fs::create_dir_all(versions.join("Imported")).unwrap();
+        fs::write(versions.join("Imported/pack.json"), r#"{ "id": "pack", "type": "release", "time": "", "releaseTime": "" }"#).unwrap();
+        let manager = VersionManager::new(versions.as_path());
+        assert!(manager.version_of("1.12.2").is_err());
+        let moves = manager.repair_layout().unwrap();
+        assert_eq!(moves, vec![
+            (versions.join("1.12.2/client.json"), versions.join("1.12.2/1.12.2.json")),
+            (versions.join("1.12.2/client.jar"), versions.join("1.12.2/1.12.2.jar")),
+            (versions.join("Imported"), versions.join("pack")),
+        ]);
+        assert_eq!(manager.version_of("1.12.2").unwrap().id(), "1.12.2");
+        assert!(versions.join("1.12.2/1.12.2.jar").is_file());
+        assert_eq!(manager.version_ids().unwrap(), vec!["1.12.2".to_owned(), "pack".to_owned()]);
+    }
+
+    #[test]
+    fn version_icon_falls_back_to_version_type() {
+        let root = prepare_game_dir("version-icon", "1.12.2", r#"{ "id": "1.12.2", "type": "release", "time": "", "releaseTime": "" }"#);
+        for &(id, extra) in &[("17w43a", r#""type": "snapshot""#), ("1.12.2-forge", r#""type": "release", "inheritsFrom": "1.12.2""#)] {
+            fs::create_dir_all(root.join("versions").join(id)).unwrap();
+            let json = format!(r#"{{ "id": "{}", {}, "time": "", "releaseTime": "" }}"#, id, extra);
+            fs::write(root.join("versions").join(id).join(format!("{}.json", id)), json).unwrap();
+        }
+        let manager = VersionManager::new(root.join("versions").as_path());
+        assert_eq!(manager.icon_of("1.12.2").unwrap(), VersionIcon::Builtin("grass"));
+        assert_eq!(manager.icon_of("17w43a").unwrap(), VersionIcon::Builtin("dirt"));
+        assert_eq!(manager.icon_of("1.12.2-forge").unwrap(), VersionIcon::Builtin("furnace"));
+        touch(root.join("versions/1.12.2-forge/icon.png"));
+        assert_eq!(manager.icon_of("1.12.2-forge").unwrap(), VersionIcon::File(root.join("versions/1.12.2-forge/icon.png")));
+    }
+
+    #[test]
+    fn orphan_natives_are_purged() {
+        let root = prepare_game_dir("orphan-natives", "1.12.2", r#"{ "id": "1.12.2", "type": "release", "time": "", "releaseTime": "" }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let valid = manager.get_natives_path("1.12.2");
+        let stale = root.join("versions/1.12.2/1.12.1-natives-linux-x86_64");
+        let orphan = root.join("versions/removed/removed-natives-linux-x86_64");
+        for dir in &[&valid, &stale, &orphan] {
+            touch(dir.join("liblwjgl.so"));
+        }
+        assert_eq!(manager.purge_orphan_natives().unwrap(), vec![stale.clone(), orphan.clone()]);
+        assert!(valid.join("liblwjgl.so").is_file());
+        assert!(!stale.exists() && !orphan.exists());
+    }
+
+    #[test]
+    fn library_with_artifact_and_natives_serves_both() {
+        let download = r#"{ "size": 1, "url": "", "sha1": "" }"#;
+        let root = prepare_game_dir("dual-library", "1.14", &format!(r#"{{
+            "id": "1.14", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{{
+                "name": "org.lwjgl:lwjgl:3.2.1",
+                "natives": {{ "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-macos" }},
+                "downloads": {{ "artifact": {0}, "classifiers": {{ "natives-linux": {0}, "natives-windows": {0}, "natives-macos": {0} }} }}
+            }}, {{
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "natives": {{ "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" }},
+                "downloads": {{ "classifiers": {{ "natives-linux": {0}, "natives-windows": {0}, "natives-osx": {0} }} }}
+            }}]
+        }}"#, download));
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("1.14").unwrap();
+        let libraries = root.join("libraries");
+        assert_eq!(version.library_paths(libraries.as_path(), &manager).unwrap(),
+                   vec![libraries.join("org/lwjgl/lwjgl/3.2.1/lwjgl-3.2.1.jar")]);
+        let natives = version.to_native_collection(&manager, libraries.as_path()).unwrap();
+        let natives: Vec<_> = natives.paths().iter().map(|path| path.to_string_lossy().into_owned()).collect();
+        assert_eq!(natives.len(), 2);
+        assert!(natives[0].contains("lwjgl-3.2.1-natives-") && natives[1].contains("lwjgl-platform-2.9.4-natives-"), "{:?}", natives);
+    }
+
+    #[test]
+    fn natives_without_32bit_classifier_require_64bit() {
+        let download = r#"{ "size": 1, "url": "", "sha1": "" }"#;
+        let version_json = |id: &str, classifiers: &str| format!(r#"{{
+            "id": "{}", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{{
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "natives": {{ "linux": "natives-linux-${{arch}}", "windows": "natives-windows-${{arch}}", "osx": "natives-osx-${{arch}}" }},
+                "downloads": {{ "classifiers": {{ {} }} }}
+            }}]
+        }}"#, id, classifiers);
+        let only_64: Vec<_> = ["linux", "windows", "osx"].iter().map(|os| format!(r#""natives-{}-64": {}"#, os, download)).collect();
+        let root = prepare_game_dir("requires-64bit", "only-64", &version_json("only-64", &only_64.join(", ")));
+        let both: Vec<_> = ["linux", "windows", "osx"].iter().flat_map(|os| vec![
+            format!(r#""natives-{}-32": {}"#, os, download), format!(r#""natives-{}-64": {}"#, os, download)
+        ]).collect();
+        fs::create_dir_all(root.join("versions/both")).unwrap();
+        fs::write(root.join("versions/both/both.json"), version_json("both", &both.join(", "))).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        assert!(manager.version_of("only-64").unwrap().requires_64bit(&manager));
+        assert!(!manager.version_of("both").unwrap().requires_64bit(&manager));
+    }
+
+    #[test]
+    fn versions_are_diffed_by_library_coordinates() {
+        let root = prepare_game_dir("version-diff", "pack-1.0", r#"{
+            "id": "pack-1.0", "type": "release", "time": "", "releaseTime": "", "assets": "1.12",
+            "libraries": [
+                { "name": "com.mojang:brigadier:1.0.17" },
+                { "name": "org.lwjgl:lwjgl:3.2.1" },
+                { "name": "org.lwjgl:lwjgl:3.2.1:natives-linux" },
+                { "name": "net.java.jinput:jinput:2.0.5" }
+            ]
+        }"#);
+        fs::create_dir_all(root.join("versions/pack-2.0")).unwrap();
+        fs::write(root.join("versions/pack-2.0/pack-2.0.json"), r#"{
+            "id": "pack-2.0", "type": "release", "time": "", "releaseTime": "", "assets": "1.13",
+            "libraries": [
+                { "name": "com.mojang:brigadier:1.0.18" },
+                { "name": "org.lwjgl:lwjgl:3.2.1" },
+                { "name": "org.lwjgl:lwjgl:3.2.1:natives-linux" },
+                { "name": "com.mojang:datafixerupper:1.0.20" }
+            ]
+        }"#).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let diff = manager.diff("pack-1.0", "pack-2.0").unwrap();
+        assert_eq!(diff.added_libraries(), ["com.mojang:datafixerupper:1.0.20"]);
+        assert_eq!(diff.removed_libraries(), ["net.java.jinput:jinput:2.0.5"]);
+        assert_eq!(diff.changed_libraries(), [("com.mojang:brigadier:1.0.17".to_owned(), "com.mojang:brigadier:1.0.18".to_owned())]);
+        assert_eq!(diff.asset_index(), Some(("1.12", "1.13")));
+        assert!(manager.diff("pack-2.0", "pack-2.0").unwrap().is_empty());
+    }
+
+    #[test]
+    fn libraries_are_classified_by_role() {
+        let root = prepare_game_dir("classify-libraries", "mixed", r#"{
+            "id": "mixed", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [
+                { "name": "com.mojang:brigadier:1.0.18" },
+                { "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4", "natives": { "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" } },
+                { "name": "org.lwjgl.lwjgl:lwjgl:2.9.4" }
+            ]
+        }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let (regular, natives) = manager.version_of("mixed").unwrap().classify_libraries(&manager).unwrap();
+        assert_eq!(regular.iter().map(Library::name).collect::<Vec<_>>(), vec!["com.mojang:brigadier:1.0.18", "org.lwjgl.lwjgl:lwjgl:2.9.4"]);
+        assert_eq!(natives.iter().map(Library::name).collect::<Vec<_>>(), vec!["org.lwjgl.lwjgl:lwjgl-platform:2.9.4"]);
+    }
+
+    #[test]
+    fn version_json_with_bom_is_parsed() {
+        let json = "\u{feff}{\r\n\"id\": \"bom\", \"type\": \"release\", \"time\": \"\", \"releaseTime\": \"\",\r\n\"libraries\": []\r\n}\r\n";
+        let root = prepare_game_dir("bom-version", "bom", json);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        assert_eq!(manager.version_of("bom").unwrap().id(), "bom");
+        assert_eq!(manager.raw_version_of("bom").unwrap()["id"], "bom");
+    }
+
+    #[test]
+    fn version_id_traversal_is_rejected() {
+        assert_eq!(normalize_version_id(" 1.12.2 ").unwrap(), "1.12.2");
+        for id in &["../evil", "..", "", "a\\b", "con:1"] {
+            match normalize_version_id(id) {
+                Result::Err(Error::InvalidVersionId(ref invalid)) => assert_eq!(invalid, id),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn natives_are_shared_only_when_identical() {
+        let root = prepare_dir("shared-natives");
+        for &(id, lwjgl) in &[("1.8", "2.9.4"), ("1.8.9", "2.9.4"), ("1.7.10", "2.9.1")] {
+            let version_dir = root.join("versions").join(id);
+            fs::create_dir_all(version_dir.as_path()).unwrap();
+            fs::write(version_dir.join(format!("{}.json", id)), format!(r#"{{
+                "id": "{}", "type": "release", "time": "", "releaseTime": "",
+                "libraries": [{{
+                    "name": "org.lwjgl.lwjgl:lwjgl-platform:{}",
+                    "natives": {{ "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" }},
+                    "extract": {{ "exclude": ["META-INF/"] }}
+                }}]
+            }}"#, id, lwjgl)).unwrap();
+            for platform in &["linux", "windows", "osx"] {
+                let jar = format!("libraries/org/lwjgl/lwjgl/lwjgl-platform/{0}/lwjgl-platform-{0}-natives-{1}.jar", lwjgl, platform);
+                write_jar(root.join(jar), &[("liblwjgl.so", lwjgl.as_bytes())]);
+            }
+        }
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let libraries = root.join("libraries");
+        assert!(manager.can_share_natives("1.8", "1.8.9", libraries.as_path()).unwrap());
+        assert!(!manager.can_share_natives("1.8", "1.7.10", libraries.as_path()).unwrap());
+    }
+
+    #[test]
+    fn classpath_file_lists_one_entry_per_line() {
+        let root = prepare_game_dir("classpath-file", "1.12.2", r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{ "name": "com.mojang:patchy:1.1" }, { "name": "com.google.guava:guava:21.0" }]
+        }"#);
+        touch(root.join("libraries/com/mojang/patchy/1.1/patchy-1.1.jar"));
+        touch(root.join("libraries/com/google/guava/guava/21.0/guava-21.0.jar"));
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("1.12.2").unwrap();
+        let path_buf = root.join("classpath.txt");
+        version.write_classpath_file(path_buf.as_path(), &manager, root.join("libraries").as_path()).unwrap();
+        let expected = vec![
+            root.join("libraries/com/mojang/patchy/1.1/patchy-1.1.jar"),
+            root.join("libraries/com/google/guava/guava/21.0/guava-21.0.jar"),
+            root.join("versions/1.12.2/1.12.2.jar"),
+        ];
+        let lines: Vec<PathBuf> = fs::read_to_string(path_buf).unwrap().lines().map(PathBuf::from).collect();
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn only_natives_of_current_platform_are_extracted() {
+        assert_eq!(platform_native_entry("linux/x64/liblwjgl.so", "linux", "64"), Some("liblwjgl.so".to_owned()));
+        assert_eq!(platform_native_entry("linux-x86-64/libjnidispatch.so", "linux", "64"), Some("libjnidispatch.so".to_owned()));
+        assert_eq!(platform_native_entry("windows/x86/lwjgl.dll", "windows", "64"), None);
+        assert_eq!(platform_native_entry("darwin-aarch64/libjnidispatch.jnilib", "linux", "64"), None);
+        assert_eq!(platform_native_entry("macos/liblwjgl.dylib", "osx", "arm64"), Some("liblwjgl.dylib".to_owned()));
+        assert_eq!(platform_native_entry("liblwjgl.so", "windows", "32"), Some("liblwjgl.so".to_owned()));
+        assert_eq!(platform_native_entry("org/lwjgl/liblwjgl.so", "windows", "32"), Some("org/lwjgl/liblwjgl.so".to_owned()));
+        let root = prepare_dir("multi-platform-natives");
+        let current = format!("{}/{}/libcurrent.so", OS_PLATFORM, OS_ARCH);
+        let other_platform = if OS_PLATFORM == "windows" { "linux" } else { "windows" };
+        let other_arch = if OS_ARCH == "32" { "64" } else { "32" };
+        let jar = write_jar(root.join("natives.jar"), &[
+            (current.as_str(), b"current"), (&format!("{}/{}/libother.so", other_platform, OS_ARCH), b"other"),
+            (&format!("{}/{}/libarch.so", OS_PLATFORM, other_arch), b"arch"), ("libshared.so", b"shared")
+        ]);
+        let collection = NativeCollection { libraries: vec![(jar, Arc::new(Vec::new()))], strip_versions: false };
+        assert_eq!(collection.extract_to(root.join("natives").as_path()).unwrap(), vec!["libcurrent.so", "libshared.so"]);
+        assert_eq!(fs::read(root.join("natives/libcurrent.so")).unwrap(), b"current");
+        assert_eq!(fs::read_dir(root.join("natives")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn case_colliding_natives_are_not_overwritten() {
+        let root = prepare_dir("case-insensitive-natives");
+        #[cfg(target_os = "linux")]
+        assert!(!is_case_insensitive(root.as_path()).unwrap());
+        assert_eq!(fs::read_dir(root.as_path()).unwrap().count(), 0);
+        let jar = write_jar(root.join("natives.jar"), &[("libLWJGL.so", b"upper"), ("liblwjgl.so", b"lower")]);
+        let collection = NativeCollection { libraries: vec![(jar, Arc::new(Vec::new()))], strip_versions: false };
+        let cancelled = AtomicBool::new(false);
+        assert_eq!(collection.extract_with(root.join("insensitive").as_path(), &cancelled, true).unwrap(), vec!["libLWJGL.so"]);
+        assert_eq!(fs::read(root.join("insensitive/libLWJGL.so")).unwrap(), b"upper");
+        assert_eq!(collection.extract_with(root.join("sensitive").as_path(), &cancelled, false).unwrap(), vec!["libLWJGL.so", "liblwjgl.so"]);
+    }
+
+    #[test]
+    fn natives_with_wrong_signature_are_flagged() {
+        let root = prepare_dir("native-signatures");
+        fs::write(root.join("liblwjgl.so"), b"\x7fELF\x02\x01\x01").unwrap();
+        fs::write(root.join("libcorrupt.so"), b"<html>404</html>").unwrap();
+        fs::write(root.join("libtruncated.so"), b"\x7fE").unwrap();
+        fs::write(root.join("lwjgl.dll"), b"MZ\x90\x00").unwrap();
+        fs::write(root.join("readme.txt"), b"not a native").unwrap();
+        assert_eq!(invalid_natives_of(root.as_path(), "linux").unwrap(), vec![root.join("libcorrupt.so"), root.join("libtruncated.so"), root.join("lwjgl.dll")]);
+        assert_eq!(invalid_natives_of(root.as_path(), "windows").unwrap().len(), 3);
+        assert!(native_signature_matches(root.join("lwjgl.dll").as_path(), "windows").unwrap());
+    }
+
+    #[test]
+    fn versioned_native_names_are_stripped() {
+        assert_eq!(canonical_native_name("liblwjgl-3.3.1.so"), "liblwjgl.so");
+        assert_eq!(canonical_native_name("windows/x64/lwjgl_opengl-3.3.1.dll"), "windows/x64/lwjgl_opengl.dll");
+        assert_eq!(canonical_native_name("libopenal.so"), "libopenal.so");
+        assert_eq!(canonical_native_name("libjinput-linux64.so"), "libjinput-linux64.so");
+        let root = prepare_dir("strip-native-versions");
+        let jar = write_jar(root.join("natives.jar"), &[("liblwjgl-3.3.1.so", b"lwjgl"), ("libglfw.so", b"glfw")]);
+        let collection = NativeCollection { libraries: vec![(jar, Arc::new(Vec::new()))], strip_versions: false };
+        assert_eq!(collection.clone().extract_to(root.join("kept").as_path()).unwrap(), vec!["liblwjgl-3.3.1.so", "libglfw.so"]);
+        assert_eq!(collection.strip_versions(true).extract_to(root.join("stripped").as_path()).unwrap(), vec!["liblwjgl.so", "libglfw.so"]);
+        assert_eq!(fs::read(root.join("stripped/liblwjgl.so")).unwrap(), b"lwjgl");
+    }
+
+    #[test]
+    fn forced_natives_classifier_is_selected() {
+        let root = prepare_game_dir("forced-classifier", "natives", r#"{
+            "id": "natives", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "natives": { "linux": "natives-linux", "windows": "natives-windows" }
+            }]
+        }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("natives").unwrap();
+        let libraries = root.join("libraries");
+        let collection = version.to_native_collection_with_classifier(&manager, &libraries, "natives-windows").unwrap();
+        let expected = libraries.join("org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-windows.jar");
+        assert_eq!(collection.libraries.iter().map(|l| l.0.clone()).collect::<Vec<_>>(), vec![expected]);
+        match version.to_native_collection_with_classifier(&manager, &libraries, "natives-beos") {
+            Result::Err(Error::UnknownClassifier(ref classifier)) => assert_eq!(classifier, "natives-beos"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn natives_disallowed_by_extract_rules_are_skipped() {
+        let root = prepare_game_dir("extract-rules", "natives", &format!(r#"{{
+            "id": "natives", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{{
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "natives": {{ "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" }},
+                "extract": {{ "exclude": ["META-INF/"], "rules": [{{ "action": "allow" }}, {{ "action": "disallow", "os": {{ "name": "{}" }} }}] }}
+            }}, {{
+                "name": "com.mojang:text2speech:1.10.3",
+                "natives": {{ "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-osx" }},
+                "rules": [{{ "action": "allow", "os": {{ "name": "beos" }} }}, {{ "action": "allow", "os": {{ "name": "{}" }} }}]
+            }}]
+        }}"#, OS_PLATFORM, OS_PLATFORM));
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("natives").unwrap();
+        let libraries = root.join("libraries");
+        let collection = version.to_native_collection(&manager, &libraries).unwrap();
+        let paths: Vec<_> = collection.libraries.iter().map(|l| l.0.clone()).collect();
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].starts_with(libraries.join("com/mojang/text2speech")));
+    }
+
+    #[test]
+    fn tampered_asset_index_is_rejected() {
+        let root = prepare_dir("asset-index");
+        let index: AssetDownloadInfo = serde_json::from_str(r#"{
+            "id": "1.12", "size": 2, "sha1": "bf21a9e8fbc5a3846fb05b4fa0859e0917b2202f"
+        }"#).unwrap();
+        fs::create_dir_all(root.join("indexes")).unwrap();
+        fs::write(index.index_path(root.as_path()), "{}").unwrap();
+        assert!(index.verify_index(root.as_path()).is_ok());
+        fs::write(index.index_path(root.as_path()), "[]").unwrap();
+        match index.verify_index(root.as_path()) {
+            Result::Err(Error::AssetIndexCorrupt(ref path)) => assert!(path.ends_with("indexes/1.12.json")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolved_json_merges_inherited_libraries() {
+        let root = prepare_game_dir("resolved-json", "1.12.2-forge", r#"{
+            "id": "1.12.2-forge", "inheritsFrom": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.launchwrapper.Launch",
+            "libraries": [{ "name": "net.minecraftforge:forge:1.12.2" }]
+        }"#);
+        fs::create_dir_all(root.join("versions/1.12.2")).unwrap();
+        fs::write(root.join("versions/1.12.2/1.12.2.json"), r#"{
+            "id": "1.12.2", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.minecraft.client.main.Main", "assets": "1.12",
+            "libraries": [{ "name": "com.mojang:patchy:1.1" }, { "name": "oshi-project:oshi-core:1.1" }]
+        }"#).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("1.12.2-forge").unwrap();
+        let resolved: Value = serde_json::from_str(&version.to_resolved_json(&manager).unwrap()).unwrap();
+        assert_eq!(resolved["libraries"].as_array().unwrap().len(), version.libraries(&manager).unwrap().len());
+        assert_eq!(resolved["libraries"][2]["name"], "net.minecraftforge:forge:1.12.2");
+        assert_eq!(resolved["mainClass"], "net.minecraft.launchwrapper.Launch");
+        assert_eq!(resolved["assets"], "1.12");
+        assert!(resolved.get("inheritsFrom").is_none());
+    }
+
+    #[test]
+    fn modded_version_requires_library_directory() {
+        let root = prepare_game_dir("required-placeholders", "1.13.2-forge", r#"{
+            "id": "1.13.2-forge", "inheritsFrom": "1.13.2", "type": "release", "time": "", "releaseTime": "",
+            "arguments": {
+                "game": ["--launchTarget", "fmlclient", "--fml.mcVersion", "1.13.2"],
+                "jvm": ["-DlibraryDirectory=${library_directory}"]
+            }
+        }"#);
+        fs::create_dir_all(root.join("versions/1.13.2")).unwrap();
+        fs::write(root.join("versions/1.13.2/1.13.2.json"), r#"{
+            "id": "1.13.2", "type": "release", "time": "", "releaseTime": "",
+            "arguments": {
+                "game": ["--username", "${auth_player_name}", { "rules": [{ "action": "allow", "features": { "is_demo_user": true } }], "value": "--demo" }],
+                "jvm": [{ "rules": [{ "action": "allow", "os": { "name": "beos" } }], "value": "-Dbeos=${beos_directory}" }, "-cp", "${classpath}"]
+            }
+        }"#).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let required = manager.version_of("1.13.2-forge").unwrap().required_placeholders(&manager).unwrap();
+        assert!(required.contains("library_directory"));
+        assert!(required.contains("auth_player_name"));
+        assert!(required.contains("resolution_width"));
+        assert!(!required.contains("beos_directory"));
+        let templates = manager.version_of("1.13.2-forge").unwrap().game_argument_templates(&manager).unwrap();
+        assert_eq!(&templates[..4], &["--username", "${auth_player_name}", "--launchTarget", "fmlclient"]);
+    }
+
+    #[test]
+    fn known_features_are_collected_from_argument_rules() {
+        let root = prepare_game_dir("known-features", "1.20.1", r#"{
+            "id": "1.20.1", "type": "release", "time": "", "releaseTime": "",
+            "arguments": {
+                "game": [
+                    "--username", "${auth_player_name}",
+                    { "rules": [{ "action": "allow", "features": { "is_demo_user": true } }], "value": "--demo" },
+                    { "rules": [{ "action": "allow", "features": { "has_custom_resolution": true } }], "value": ["--width", "${resolution_width}"] },
+                    { "rules": [{ "action": "allow", "features": { "has_quick_plays_support": true } }], "value": ["--quickPlayPath", "${quickPlayPath}"] },
+                    { "rules": [{ "action": "allow", "features": { "is_quick_play_singleplayer": true } }], "value": ["--quickPlaySingleplayer", "${quickPlaySingleplayer}"] }
+                ],
+                "jvm": [{ "rules": [{ "action": "allow", "os": { "name": "osx" } }], "value": "-XstartOnFirstThread" }, "-cp", "${classpath}"]
+            }
+        }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let features = manager.version_of("1.20.1").unwrap().known_features(&manager);
+        let mut features: Vec<_> = features.into_iter().collect();
+        features.sort();
+        assert_eq!(features, vec!["has_custom_resolution", "has_quick_plays_support", "is_demo_user", "is_quick_play_singleplayer"]);
+    }
+
+    #[test]
+    fn module_path_arguments_are_preserved() {
+        let root = prepare_game_dir("module-path", "1.17.1-forge", r#"{
+            "id": "1.17.1-forge", "type": "release", "time": "", "releaseTime": "",
+            "arguments": { "jvm": [
+                "-p", "${library_directory}/cpw/mods/bootstraplauncher.jar${classpath_separator}${library_directory}/cpw/mods/securejarhandler.jar",
+                "--add-modules", "ALL-MODULE-PATH",
+                "--add-opens", "java.base/java.util.jar=cpw.mods.securejarhandler"
+            ] }
+        }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("1.17.1-forge").unwrap();
+        let strategy = parsing::ParameterStrategy::map(|s| match s.as_str() {
+            "library_directory" => "/opt/minecraft/libraries".to_owned(),
+            "classpath_separator" => ";".to_owned(),
+            _ => String::new()
+        });
+        let mut options = Vec::new();
+        version.collect_jvm_arguments(&manager, &mut options, &strategy).unwrap();
+        let args: Vec<_> = options.iter().flat_map(|o| o.to_args()).collect();
+        assert_eq!(args, vec![
+            "-p", "/opt/minecraft/libraries/cpw/mods/bootstraplauncher.jar;/opt/minecraft/libraries/cpw/mods/securejarhandler.jar",
+            "--add-modules", "ALL-MODULE-PATH",
+            "--add-opens", "java.base/java.util.jar=cpw.mods.securejarhandler"
+        ]);
+    }
+
+    #[test]
+    fn maven_path_of_coordinates() {
+        assert_eq!(maven_path("com.mojang:patchy:1.1").unwrap(), PathBuf::from("com/mojang/patchy/1.1/patchy-1.1.jar"));
+        assert_eq!(maven_path("org.lwjgl:lwjgl:3.2.2:natives-linux").unwrap(),
+                   PathBuf::from("org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-linux.jar"));
+        assert_eq!(maven_path("de.oceanlabs.mcp:mcp_config:1.16.5@zip").unwrap(),
+                   PathBuf::from("de/oceanlabs/mcp/mcp_config/1.16.5/mcp_config-1.16.5.zip"));
+        match maven_path("com.mojang:patchy") {
+            Result::Err(Error::InvalidCoordinate(ref coordinate)) => assert_eq!(coordinate, "com.mojang:patchy"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(maven_path("com.mojang::1.1").is_err());
+    }
+
+    #[test]
+    fn asset_layout_follows_index_flags() {
+        let root = prepare_dir("asset-layout");
+        let assets_dir = root.join("assets");
+        fs::create_dir_all(assets_dir.join("indexes")).unwrap();
+        for &(id, index) in &[("custom", r#"{ "virtual": true, "objects": {} }"#), ("pre-1.6", r#"{ "map_to_resources": true, "virtual": true }"#),
+                              ("legacy", r#"{ "objects": {} }"#)] {
+            fs::write(assets_dir.join("indexes").join(format!("{}.json", id)), index).unwrap();
+        }
+        let layout = |id: &str| AssetDownloadInfo::new(id.to_owned()).layout(assets_dir.as_path()).unwrap();
+        assert_eq!(layout("custom"), AssetLayout::Virtual);
+        assert_eq!(layout("pre-1.6"), AssetLayout::Resources);
+        assert_eq!(layout("legacy"), AssetLayout::Objects);
+    }
+
+    #[test]
+    fn legacy_library_url_is_built_from_coordinate() {
+        let root = prepare_game_dir("legacy-library-url", "1.7.10", r#"{
+            "id": "1.7.10", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{ "name": "net.sf.jopt-simple:jopt-simple:4.5" }, { "name": "net.minecraftforge:forge:1.7.10-10.13.4.1614", "url": "https://maven.minecraftforge.net/" }]
+        }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let libraries = manager.version_of("1.7.10").unwrap().libraries(&manager).unwrap();
+        let urls: Vec<_> = libraries.iter().map(|lib| lib.download_info_default().unwrap().url().to_owned()).collect();
+        assert_eq!(urls, vec!["https://libraries.minecraft.net/net/sf/jopt-simple/jopt-simple/4.5/jopt-simple-4.5.jar",
+                              "https://maven.minecraftforge.net/net/minecraftforge/forge/1.7.10-10.13.4.1614/forge-1.7.10-10.13.4.1614.jar"]);
+        assert_eq!(library_url("net.sf.jopt-simple:jopt-simple:4.5", None).unwrap(), urls[0]);
+        assert_eq!(library_url("org.lwjgl.lwjgl:lwjgl-platform:2.9.1:natives-linux", Some("https://mirror.example/maven/")).unwrap(),
+                   "https://mirror.example/maven/org/lwjgl/lwjgl/lwjgl-platform/2.9.1/lwjgl-platform-2.9.1-natives-linux.jar");
+        assert!(library_url("jopt-simple", None).is_err());
+    }
+
+    #[test]
+    fn legacy_resources_use_named_layout() {
+        let assets_dir = Path::new("/games/minecraft/assets");
+        let legacy = AssetDownloadInfo::new("legacy".to_owned());
+        assert!(legacy.is_legacy() && !AssetDownloadInfo::new("1.12".to_owned()).is_legacy());
+        assert_eq!(legacy.legacy_object_path(assets_dir, "sound/step/grass1.ogg"),
+                   Path::new("/games/minecraft/assets/virtual/legacy/sound/step/grass1.ogg"));
+        let hash = "bdf48ef6b5d0d23bbb02e17d04865216179f510a";
+        assert_eq!(AssetDownloadInfo::object_url(RESOURCES_HOST, hash), format!("{}/bd/{}", RESOURCES_HOST, hash));
+        assert_eq!(AssetDownloadInfo::object_url("https://mirror.example/assets/", hash), format!("https://mirror.example/assets/bd/{}", hash));
+    }
+
+    #[test]
+    fn forced_library_classifier_is_resolved() {
+        let root = prepare_game_dir("library-classifiers", "dev", r#"{
+            "id": "dev", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{ "name": "com.mojang:authlib:1.5.25" }, { "name": "com.mojang:patchy:1.1" }]
+        }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("dev").unwrap();
+        let libraries = root.join("libraries");
+        let mut classifiers = HashMap::new();
+        classifiers.insert("com.mojang:authlib".to_owned(), "dev".to_owned());
+        assert_eq!(version.library_paths_with_classifiers(&libraries, &classifiers, &manager).unwrap(), vec![
+            libraries.join("com/mojang/authlib/1.5.25/authlib-1.5.25-dev.jar"),
+            libraries.join("com/mojang/patchy/1.1/patchy-1.1.jar"),
+        ]);
+    }
+
+    #[test]
+    fn compliance_level_is_inherited() {
+        let root = prepare_game_dir("compliance-level", "1.19-fabric", r#"{
+            "id": "1.19-fabric", "inheritsFrom": "1.19", "type": "release", "time": "", "releaseTime": ""
+        }"#);
+        fs::create_dir_all(root.join("versions/1.19")).unwrap();
+        fs::write(root.join("versions/1.19/1.19.json"), r#"{
+            "id": "1.19", "type": "release", "time": "", "releaseTime": "", "complianceLevel": 1
+        }"#).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        assert_eq!(manager.version_of("1.19-fabric").unwrap().compliance_level(&manager), Some(1));
+    }
+
+    #[test]
+    fn display_name_includes_loader() {
+        let root = prepare_game_dir("display-name", "modpack", r#"{
+            "id": "modpack", "inheritsFrom": "1.20.1", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{ "name": "net.minecraftforge:forge:1.20.1-47.2.0" }]
+        }"#);
+        fs::create_dir_all(root.join("versions/1.20.1")).unwrap();
+        fs::write(root.join("versions/1.20.1/1.20.1.json"), r#"{
+            "id": "1.20.1", "type": "release", "time": "", "releaseTime": ""
+        }"#).unwrap();
+        fs::create_dir_all(root.join("versions/1.20.1-forge-47.2.0")).unwrap();
+        fs::write(root.join("versions/1.20.1-forge-47.2.0/1.20.1-forge-47.2.0.json"), r#"{
+            "id": "1.20.1-forge-47.2.0", "inheritsFrom": "1.20.1", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{ "name": "net.minecraftforge:forge:1.20.1-47.2.0" }]
+        }"#).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        assert_eq!(manager.version_of("modpack").unwrap().display_name(&manager), "1.20.1-forge-47.2.0");
+        assert_eq!(manager.version_of("1.20.1-forge-47.2.0").unwrap().display_name(&manager), "1.20.1-forge-47.2.0");
+        assert_eq!(manager.version_of("1.20.1").unwrap().display_name(&manager), "1.20.1");
+    }
+
+    #[test]
+    fn asset_index_is_inherited() {
+        let root = prepare_game_dir("inherited-asset-index", "fabric-loader-0.14.21-1.20.1", r#"{
+            "id": "fabric-loader-0.14.21-1.20.1", "inheritsFrom": "1.20.1", "type": "release", "time": "", "releaseTime": "",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient"
+        }"#);
+        fs::create_dir_all(root.join("versions/1.20.1")).unwrap();
+        fs::write(root.join("versions/1.20.1/1.20.1.json"), r#"{
+            "id": "1.20.1", "type": "release", "time": "", "releaseTime": "", "assets": "5",
+            "assetIndex": { "id": "5", "sha1": "bf21a9e8fbc5a3846fb05b4fa0859e0917b2202f", "size": 2, "url": "https://example.com/5.json" }
+        }"#).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let asset_index = manager.version_of("fabric-loader-0.14.21-1.20.1").unwrap().asset_index(&manager).unwrap();
+        assert_eq!(asset_index.id(), "5");
+        assert_eq!(asset_index.size, Some(2));
+    }
+
+    #[test]
+    fn arm64_natives_fall_back_to_x86_64() {
+        let library = |classifiers: &str| serde_json::from_str::<Library>(&format!(r#"{{
+            "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+            "natives": {{ "osx": "natives-osx" }},
+            "downloads": {{ "classifiers": {{ {} }} }}
+        }}"#, classifiers)).unwrap();
+        let download = r#"{ "size": 1, "url": "", "sha1": "" }"#;
+        let libraries = PathBuf::from("libraries");
+        let x86_64_only = library(&format!(r#""natives-osx": {}"#, download));
+        assert_eq!(x86_64_only.classpath_of(libraries.as_path(), "arm64", "osx"),
+                   Some(libraries.join("org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-osx.jar")));
+        assert!(x86_64_only.falls_back_to_x86_64("arm64", "osx"));
+        assert!(!x86_64_only.falls_back_to_x86_64("64", "osx"));
+        let universal = library(&format!(r#""natives-osx": {0}, "natives-osx-arm64": {0}"#, download));
+        assert_eq!(universal.classpath_of(libraries.as_path(), "arm64", "osx"),
+                   Some(libraries.join("org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-osx-arm64.jar")));
+        assert_eq!(universal.classpath_of(libraries.as_path(), "64", "osx"),
+                   Some(libraries.join("org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-osx.jar")));
+        assert!(!universal.falls_back_to_x86_64("arm64", "osx"));
+    }
+
+    #[test]
+    fn all_schema_problems_are_reported() {
+        let root = prepare_game_dir("validate-version", "broken", r#"{
+            "id": "broken", "type": "release", "time": "", "releaseTime": 1,
+            "arguments": { "game": ["--demo", 42, { "rules": [], "value": ["--width", "${resolution_width}"] }] },
+            "libraries": [{ "name": "com.example:lib:1.0" }, { "name": "not-a-coordinate" }, { "url": "https://example.com/" }]
+        }"#);
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let issues: Vec<String> = manager.validate("broken").unwrap().iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(issues, vec![
+            "/releaseTime: should be a string",
+            "/mainClass: is missing",
+            "/arguments/game/1: should be a string or an object with rules and value",
+            "/libraries/1/name: is not a valid maven coordinate",
+            "/libraries/2/name: is missing",
+        ]);
+    }
+
+    #[test]
+    fn missing_modpack_dependencies_are_reported() {
+        let root = prepare_game_dir("modpack-dependencies", "1.20.1", r#"{
+            "id": "1.20.1", "type": "release", "time": "", "releaseTime": ""
+        }"#);
+        fs::create_dir_all(root.join("versions/fabric-loader-0.14.22-1.20.1")).unwrap();
+        fs::write(root.join("versions/fabric-loader-0.14.22-1.20.1/fabric-loader-0.14.22-1.20.1.json"), r#"{
+            "id": "fabric-loader-0.14.22-1.20.1", "inheritsFrom": "1.20.1", "type": "release", "time": "", "releaseTime": "",
+            "libraries": [{ "name": "net.fabricmc:fabric-loader:0.14.22" }]
+        }"#).unwrap();
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let installed: Value = serde_json::from_str(r#"{ "dependencies": { "minecraft": "1.20.1", "fabric-loader": "0.14.22" } }"#).unwrap();
+        assert!(manager.missing_modpack_dependencies(&installed).unwrap().is_empty());
+        let uninstalled: Value = serde_json::from_str(r#"{
+            "minecraft": { "version": "1.19.2", "modLoaders": [{ "id": "forge-43.2.0", "primary": true }] }
+        }"#).unwrap();
+        assert_eq!(manager.missing_modpack_dependencies(&uninstalled).unwrap(), vec![
+            ("minecraft".to_owned(), "1.19.2".to_owned()),
+            ("forge".to_owned(), "43.2.0".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn natives_of_newest_library_version_are_extracted() {
+        let download = r#"{ "size": 1, "url": "", "sha1": "" }"#;
+        let library = |version: &str| format!(r#"{{
+            "name": "org.lwjgl:lwjgl:{1}",
+            "natives": {{ "linux": "natives-linux", "windows": "natives-windows", "osx": "natives-macos" }},
+            "downloads": {{ "artifact": {0}, "classifiers": {{ "natives-linux": {0}, "natives-windows": {0}, "natives-macos": {0} }} }}
+        }}"#, download, version);
+        let root = prepare_game_dir("newest-natives", "1.14", &format!(r#"{{
+            "id": "1.14", "type": "release", "time": "", "releaseTime": "", "libraries": [{}, {}, {}]
+        }}"#, library("3.2.2"), library("3.2.10"), library("3.1.6")));
+        let manager = VersionManager::new(root.join("versions").as_path());
+        let version = manager.version_of("1.14").unwrap();
+        let libraries = root.join("libraries");
+        assert_eq!(version.library_paths(libraries.as_path(), &manager).unwrap(),
+                   vec![libraries.join("org/lwjgl/lwjgl/3.2.10/lwjgl-3.2.10.jar")]);
+        let natives = version.to_native_collection(&manager, libraries.as_path()).unwrap();
+        let natives: Vec<_> = natives.paths().iter().map(|path| path.to_string_lossy().into_owned()).collect();
+        assert_eq!(natives.len(), 1);
+        assert!(natives[0].contains("lwjgl-3.2.10-natives-"), "{:?}", natives);
+    }
+}
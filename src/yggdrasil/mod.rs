@@ -1,9 +1,12 @@
 #![allow(dead_code)]
 
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::fmt::{self, Display};
 use std::collections::HashMap;
 
-use uuid::{Uuid, NAMESPACE_OID};
+use md5::{Digest, Md5};
+use uuid::Uuid;
 use serde_json;
 
 use requests;
@@ -15,9 +18,17 @@ pub struct Profile {
     properties: HashMap<String, String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountType {
+    Legacy,
+    Mojang,
+    Msa,
+}
+
 #[derive(Debug)]
 pub struct AuthInfo {
-    access_token: Uuid,
+    access_token: String,
+    account_type: AccountType,
     user_profile: Profile,
 }
 
@@ -27,6 +38,12 @@ pub struct YggdrasilLoginAuthenticator {
     username: String,
     password: String,
     client_token: Uuid,
+    auth_server: String,
+}
+
+pub struct AccountsFileAuthenticator {
+    path: PathBuf,
+    name: String,
 }
 
 pub trait Authenticator {
@@ -57,6 +74,16 @@ impl Profile {
     }
 }
 
+impl AccountType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            AccountType::Legacy => "legacy",
+            AccountType::Mojang => "mojang",
+            AccountType::Msa => "msa",
+        }
+    }
+}
+
 impl Display for Profile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.properties.is_empty() {
@@ -71,14 +98,65 @@ impl Display for Profile {
 impl AuthInfo {
     #[inline]
     pub fn new(access_token: Uuid, user_profile: Profile) -> AuthInfo {
-        AuthInfo { access_token, user_profile }
+        let access_token = access_token.simple().to_string();
+        AuthInfo { access_token, account_type: AccountType::Legacy, user_profile }
+    }
+
+    pub fn from_parts(name: &str,
+                      uuid: &str,
+                      access_token: &str,
+                      account_type: AccountType) -> Result<AuthInfo, requests::Error> {
+        let is_hex = |part: &str| part.chars().all(|c| c.is_ascii_hexdigit());
+        let parts: Vec<_> = uuid.split('-').collect();
+        let is_valid_uuid = match parts.len() {
+            1 => uuid.len() == 32 && is_hex(uuid),
+            5 => parts.iter().map(|part| part.len()).eq([8, 4, 4, 4, 12].iter().cloned()) && parts.iter().all(|part| is_hex(part)),
+            _ => false
+        };
+        let invalid_uuid = || requests::Error::InvalidCredentials(format!("malformed uuid {:?}", uuid));
+        let uuid = if is_valid_uuid { Uuid::parse_str(uuid).map_err(|_| invalid_uuid())? } else { return Result::Err(invalid_uuid()) };
+        let user_profile = Profile::new(uuid, name.to_owned(), HashMap::new());
+        let auth_info = AuthInfo { access_token: access_token.to_owned(), account_type, user_profile };
+        auth_info.validate()?;
+        Result::Ok(auth_info)
+    }
+
+    pub fn validate(&self) -> Result<(), requests::Error> {
+        if self.access_token.is_empty() || self.access_token.contains(char::is_whitespace) {
+            return Result::Err(requests::Error::InvalidCredentials("missing or malformed access token".to_owned()));
+        }
+        Result::Ok(())
+    }
+
+    pub fn verify_profile(&self) -> Result<(), requests::Error> {
+        self.verify_profile_with(requests::SERVICES_HOST)
+    }
+
+    pub fn verify_profile_with(&self, services_host: &str) -> Result<(), requests::Error> {
+        // only microsoft tokens are accepted by the services, yggdrasil servers have no endpoint to look them up
+        if self.account_type != AccountType::Msa {
+            let message = format!("profiles of {} accounts cannot be verified", self.account_type.as_str());
+            return Result::Err(requests::Error::Unsupported(message));
+        }
+        // catches credentials whose stored profile is no longer the one the token authorizes
+        let (uuid, name) = requests::req_token_profile(services_host, self.access_token.as_str())?;
+        if uuid != self.user_profile.uuid || name != self.user_profile.name {
+            let message = format!("expected {}, but the token belongs to {}: {}", self.user_profile, name, uuid.simple());
+            return Result::Err(requests::Error::ProfileMismatch(message));
+        }
+        Result::Ok(())
     }
 
     #[inline]
-    pub fn access_token(&self) -> &Uuid {
+    pub fn access_token(&self) -> &str {
         &self.access_token
     }
 
+    #[inline]
+    pub fn account_type(&self) -> AccountType {
+        self.account_type
+    }
+
     #[inline]
     pub fn user_profile(&self) -> &Profile {
         &self.user_profile
@@ -90,7 +168,7 @@ impl Authenticator for OfflineAuthenticator {
 
     fn auth(&self) -> Result<AuthInfo, requests::Error> {
         let access_token = Uuid::new_v4();
-        let uuid = Uuid::new_v5(&NAMESPACE_OID, self.0.as_str());
+        let uuid = offline_uuid(&self.0);
         let profile = Profile::new(uuid, self.0.clone(), HashMap::new());
         Result::Ok(AuthInfo::new(access_token, profile))
     }
@@ -102,16 +180,66 @@ impl Authenticator for YggdrasilLoginAuthenticator {
     fn auth(&self) -> Result<AuthInfo, requests::Error> {
         let username = self.username.as_str();
         let password = self.password.as_str();
-        let (token, profile) = requests::req_authenticate(username, password, &self.client_token)?;
+        let auth_server = self.auth_server.as_str();
+        let (token, profile) = requests::req_authenticate_with(auth_server, username, password, &self.client_token)?;
         Result::Ok(AuthInfo::new(token, profile))
     }
 }
 
+impl YggdrasilLoginAuthenticator {
+    pub fn auth_server(mut self, auth_server: &str) -> Self {
+        self.auth_server = auth_server.to_owned();
+        self
+    }
+
+    pub fn validate(&self, auth_info: &AuthInfo) -> Result<bool, requests::Error> {
+        requests::req_validate_with(self.auth_server.as_str(), auth_info.access_token(), &self.client_token)
+    }
+
+    pub fn refresh(&self, auth_info: &AuthInfo) -> Result<AuthInfo, requests::Error> {
+        let auth_server = self.auth_server.as_str();
+        let (token, profile) = requests::req_refresh_with(auth_server, auth_info.access_token(), &self.client_token)?;
+        Result::Ok(AuthInfo { account_type: auth_info.account_type, ..AuthInfo::new(token, profile) })
+    }
+}
+
+impl Authenticator for AccountsFileAuthenticator {
+    type Error = requests::Error;
+
+    fn auth(&self) -> Result<AuthInfo, requests::Error> {
+        let json: serde_json::Value = serde_json::from_reader(fs::File::open(self.path.as_path())?)?;
+        let error = || requests::Error::UnrecognizedJson(json.to_string());
+        for account in json["accounts"].as_array().ok_or_else(error)? {
+            if account["name"].as_str().ok_or_else(error)? == self.name {
+                let uuid = account["uuid"].as_str().ok_or_else(error)?;
+                let access_token = account["accessToken"].as_str().ok_or_else(error)?;
+                // microsoft tokens are no uuids, so the token is kept as it is and the type is read along with it
+                let account_type = match account["type"].as_str().unwrap_or("mojang") {
+                    "legacy" => AccountType::Legacy,
+                    "mojang" => AccountType::Mojang,
+                    "msa" => AccountType::Msa,
+                    _ => return Result::Err(error())
+                };
+                return AuthInfo::from_parts(&self.name, uuid, access_token, account_type);
+            }
+        }
+        Result::Err(requests::Error::AccountNotFound(self.name.clone()))
+    }
+}
+
 #[inline]
 pub fn offline(offline_name: &str) -> OfflineAuthenticator {
     OfflineAuthenticator(offline_name.to_owned())
 }
 
+pub fn offline_uuid(name: &str) -> Uuid {
+    // the same as UUID.nameUUIDFromBytes(("OfflinePlayer:" + name).getBytes(UTF_8)) in java
+    let mut bytes: [u8; 16] = Md5::digest(format!("OfflinePlayer:{}", name).as_bytes()).into();
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Uuid::from_bytes(&bytes).unwrap()
+}
+
 #[inline]
 pub fn yggdrasil(username: &str, password: &str) -> YggdrasilLoginAuthenticator {
     yggdrasil_with_client_token(username.to_owned(), password.to_owned(), Uuid::new_v4())
@@ -121,5 +249,74 @@ pub fn yggdrasil(username: &str, password: &str) -> YggdrasilLoginAuthenticator
 pub fn yggdrasil_with_client_token(username: String,
                                    password: String,
                                    client_token: Uuid) -> YggdrasilLoginAuthenticator {
-    YggdrasilLoginAuthenticator { username, password, client_token }
+    let auth_server = requests::AUTH_SERVER_HOST.to_owned();
+    YggdrasilLoginAuthenticator { username, password, client_token, auth_server }
+}
+
+pub fn authlib_injector_auth_server(api_root: &str) -> String {
+    // authlib-injector servers expose the auth endpoints under the authserver path of their api root
+    format!("{}/authserver", api_root.trim_end_matches('/'))
+}
+
+#[inline]
+pub fn accounts_file(path: &Path, name: &str) -> AccountsFileAuthenticator {
+    AccountsFileAuthenticator { path: path.to_path_buf(), name: name.to_owned() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_uuid_matches_vanilla() {
+        assert_eq!(offline_uuid("Notch").hyphenated().to_string(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+        assert_eq!(offline_uuid("Steve").hyphenated().to_string(), "5627dd98-e6be-3c21-b8a8-e92344183641");
+        assert_eq!(offline_uuid("jeb_").hyphenated().to_string(), "a762f560-4fce-3236-812a-b80efff0b62b");
+        assert_eq!(offline_uuid("Alex").hyphenated().to_string(), "36532b5e-c442-3dbb-a24c-c7e55d0f979a");
+        assert_eq!(offline("Alex").auth().unwrap().user_profile().uuid(), &offline_uuid("Alex"));
+    }
+
+    #[test]
+    fn malformed_credentials_are_rejected() {
+        let uuid = "853c80ef-3c37-49fd-aa49-938b674adae6";
+        assert!(AuthInfo::from_parts("Alex", uuid, "token", AccountType::Mojang).is_ok());
+        assert!(AuthInfo::from_parts("Alex", &uuid.replace("-", ""), "token", AccountType::Mojang).is_ok());
+        for malformed in ["853c80ef-3c37-49fd-aa49", "853c80ef3c3749fdaa49938b674adaeg", "{853c80ef-3c37-49fd-aa49-938b674adae6}"].iter() {
+            match AuthInfo::from_parts("Alex", malformed, "token", AccountType::Mojang) {
+                Result::Err(requests::Error::InvalidCredentials(_)) => (),
+                other => panic!("unexpected result for {}: {:?}", malformed, other),
+            }
+        }
+        for token in ["", "two words"].iter() {
+            match AuthInfo::from_parts("Alex", uuid, token, AccountType::Mojang) {
+                Result::Err(requests::Error::InvalidCredentials(_)) => (),
+                other => panic!("unexpected result for {:?}: {:?}", token, other),
+            }
+        }
+    }
+
+    #[test]
+    fn account_is_selected_from_file_by_name() {
+        use std::env;
+        let path = env::temp_dir().join(format!("rmcll-test-accounts-{}.json", Uuid::new_v4().simple()));
+        fs::write(path.as_path(), r#"{ "accounts": [
+            { "name": "Steve", "uuid": "5627dd98-e6be-3c21-b8a8-e92344183641", "accessToken": "0123456789abcdef0123456789abcdef" },
+            { "name": "Alex", "uuid": "853c80ef3c3749fdaa49938b674adae6", "accessToken": "eyJhbGciOiJIUzI1NiJ9.e30.c2lnbmF0dXJl", "type": "msa" }
+        ] }"#).unwrap();
+        let alex = accounts_file(path.as_path(), "Alex").auth();
+        let steve = accounts_file(path.as_path(), "Steve").auth();
+        let missing = accounts_file(path.as_path(), "Notch").auth();
+        let _ = fs::remove_file(path.as_path());
+        let alex = alex.unwrap();
+        assert_eq!(alex.user_profile().name(), "Alex");
+        assert_eq!(alex.access_token(), "eyJhbGciOiJIUzI1NiJ9.e30.c2lnbmF0dXJl");
+        assert_eq!(alex.account_type(), AccountType::Msa);
+        let steve = steve.unwrap();
+        assert_eq!(steve.user_profile().uuid(), &offline_uuid("Steve"));
+        assert_eq!(steve.account_type(), AccountType::Mojang);
+        match missing {
+            Result::Err(requests::Error::AccountNotFound(ref name)) if name == "Notch" => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }